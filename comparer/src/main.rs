@@ -1,6 +1,11 @@
 use clap::Parser;
-use parser::file_reader;
-use parser::models::{Format, InputFormat, Record};
+use data_parser::GlobalArgs;
+use data_parser::commands::compare::{self, CompareArgs, CompareMode};
+use data_parser::exit_code;
+use parser::compare::{Field, FieldNormalizer, OutputFormat};
+use parser::models::Format;
+use parser::reconcile::{DescriptionRule, ReportFormat};
+use std::process::ExitCode;
 
 /// CLI-команда `comparer___`
 ///
@@ -18,7 +23,55 @@ use parser::models::{Format, InputFormat, Record};
 /// - `--file2` — путь ко второму файлу
 /// - `--format2` — его формат
 ///
-/// Файлы читаются через `file_reader`, который возвращает `InputFormat`.
+/// ## Выбор полей
+/// - `--fields` — сравнивать только перечисленные поля (`tx_id`, `amount`, ...)
+/// - `--ignore` — не сравнивать перечисленные поля, например `description,timestamp`
+/// - `--normalize` — нормализация перед сравнением: `description:trim`,
+///   `description:case-fold`, `timestamp:round-seconds`
+/// - `--by-key` — сопоставлять записи по `tx_id`, а не по позиции
+///
+/// Эти настройки действуют и при сверке с допусками.
+///
+/// - `--threads` — потоков для разбора CSV и BIN (`0` — по числу ядер)
+///
+/// ## Результат
+/// - `--output-format` — `text` (по умолчанию), `json` или `csv`: документ
+///   со сводкой, списком расходящихся записей и значениями полей до/после
+///
+/// ## Трёхстороннее сравнение
+/// - `--base`, `--base-format` — общий базовый файл; `--file1` и `--file2`
+///   становятся левой и правой копиями, записи сопоставляются по `tx_id`
+///   и классифицируются как `unchanged`, `changed-left`, `changed-right`,
+///   `changed-both` или `conflicting`; выбор полей и нормализация здесь
+///   не применяются, записи из обеих копий объединяются по полям
+/// - `--merge-output` — имя объединённого файла в `static/` (пишется, только
+///   если конфликтов нет); `--merge-format` — его формат (по умолчанию как у базы)
+///
+/// ```bash
+/// comparer --base base.csv --base-format csv --file1 team_a.bin --format1 bin \
+///     --file2 team_b.txt --format2 txt --merge-output merged.csv
+/// ```
+///
+/// Коды возврата: `0` — файлы совпадают, `1` — есть расхождения,
+/// `2` — ошибка (например, файл не найден или не разобран).
+/// При трёхстороннем сравнении `1` означает наличие конфликтов.
+///
+/// ## Сверка с допусками
+/// - `--reconcile` — вместо точного сравнения сопоставить записи с допусками
+/// - `--timestamp-tolerance` — допустимое расхождение времени, в секундах
+/// - `--amount-tolerance` — допустимое расхождение суммы, в наименьших единицах
+/// - `--description` — `exact`, `ignore`, `fuzzy` или `fuzzy:<порог>`
+/// - `--report` — имя файла отчёта в `static/`
+/// - `--report-format` — `csv` (по умолчанию) или `html`
+///
+/// ```bash
+/// comparer --file1 bank.csv --format1 csv --file2 ledger.bin --format2 bin \
+///     --reconcile --timestamp-tolerance 60 --amount-tolerance 1 \
+///     --description fuzzy --report recon.html --report-format html
+/// ```
+///
+/// Утилита сохранена для совместимости: она выполняет подкоманду
+/// `compare` единого CLI `data_parser` со своими прежними флагами.
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -31,47 +84,86 @@ pub struct CliCommandComparer {
     pub file2: String,
     #[arg(long)]
     pub format2: Format,
-}
 
-fn check_file(file_one: InputFormat, file_two: InputFormat) -> Option<Record> {
-    let file1 = InputFormat::get_record(file_one);
-    let file2 = InputFormat::get_record(file_two);
+    #[arg(long, value_delimiter = ',')]
+    pub fields: Vec<Field>,
+    #[arg(long, value_delimiter = ',')]
+    pub ignore: Vec<Field>,
+    #[arg(long, value_delimiter = ',')]
+    pub normalize: Vec<FieldNormalizer>,
+    #[arg(long, conflicts_with = "reconcile")]
+    pub by_key: bool,
+    #[arg(long, default_value = "text", conflicts_with = "reconcile")]
+    pub output_format: OutputFormat,
 
-    let len = file1.len().max(file2.len());
+    #[arg(
+        long,
+        requires = "base_format",
+        conflicts_with_all = ["reconcile", "by_key", "fields", "ignore", "normalize"]
+    )]
+    pub base: Option<String>,
+    #[arg(long, requires = "base")]
+    pub base_format: Option<Format>,
+    #[arg(long, requires = "base")]
+    pub merge_output: Option<String>,
+    #[arg(long, requires = "merge_output")]
+    pub merge_format: Option<Format>,
+
+    #[arg(long)]
+    pub reconcile: bool,
+    #[arg(long, default_value_t = 0, requires = "reconcile")]
+    pub timestamp_tolerance: u64,
+    #[arg(long, default_value_t = 0, requires = "reconcile")]
+    pub amount_tolerance: u64,
+    #[arg(long, default_value = "exact", requires = "reconcile")]
+    pub description: DescriptionRule,
+    #[arg(long, requires = "reconcile")]
+    pub report: Option<String>,
+    #[arg(long, default_value = "csv", requires = "reconcile")]
+    pub report_format: ReportFormat,
+
+    #[arg(long, default_value_t = 1)]
+    pub threads: usize,
+}
 
-    for i in 0..len {
-        if file1[i] != file2[i] {
-            return Some(file1[i].clone());
+impl CliCommandComparer {
+    /// Настройки сравнения в терминах подкоманды `data_parser compare`
+    fn mode(&self) -> CompareMode {
+        CompareMode {
+            fields: self.fields.clone(),
+            ignore: self.ignore.clone(),
+            normalize: self.normalize.clone(),
+            by_key: self.by_key,
+            base: self.base.clone(),
+            base_format: self.base_format.clone(),
+            merge_output: self.merge_output.clone(),
+            merge_format: self.merge_format.clone(),
+            reconcile: self.reconcile,
+            timestamp_tolerance: self.timestamp_tolerance,
+            amount_tolerance: self.amount_tolerance,
+            description: self.description.clone(),
+            report: self.report.clone(),
+            report_format: self.report_format.clone(),
         }
     }
-    None
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     let params: CliCommandComparer = CliCommandComparer::parse();
-    let file_one = file_reader(&params.file1, params.format1);
-    let file_two = file_reader(&params.file2, params.format2);
 
-    let file1 = match file_one {
-        Ok(data_file) => data_file,
-        Err(e) => return Err(Box::new(e)),
+    let global = GlobalArgs {
+        input_formats: vec![params.format1.clone(), params.format2.clone()],
+        style: params.output_format.clone(),
+        threads: params.threads,
+        ..GlobalArgs::default()
     };
-
-    let file2 = match file_two {
-        Ok(data_file) => data_file,
-        Err(e) => return Err(Box::new(e)),
+    let args = CompareArgs {
+        inputs: vec![params.file1.clone(), params.file2.clone()],
+        mode: params.mode(),
     };
 
-    let result = check_file(file1, file2);
-
-    match result {
-        Some(record) => println!("{}", record),
-        None => println!(
-            "The transaction records in '{}' and '{}' are identical.",
-            &params.file1, &params.file2
-        ),
-    }
-    Ok(())
+    global.init_logging();
+    exit_code(compare::run(&global, args))
 }
 
 #[cfg(test)]
@@ -97,4 +189,112 @@ mod tests {
         assert_eq!(args.file2, "b.csv");
         assert_eq!(args.format2, Format::Csv);
     }
+
+    #[test]
+    fn check_cli_reconcile() {
+        let args = CliCommandComparer::parse_from([
+            "comparer",
+            "--file1",
+            "a.csv",
+            "--format1",
+            "csv",
+            "--file2",
+            "b.bin",
+            "--format2",
+            "bin",
+            "--reconcile",
+            "--timestamp-tolerance",
+            "60",
+            "--description",
+            "fuzzy:0.9",
+            "--report-format",
+            "html",
+        ]);
+
+        assert!(args.reconcile);
+        assert_eq!(args.timestamp_tolerance, 60);
+        assert_eq!(args.amount_tolerance, 0);
+        assert_eq!(args.description, DescriptionRule::Fuzzy(0.9));
+        assert_eq!(args.report_format, ReportFormat::Html);
+    }
+
+    #[test]
+    fn check_cli_field_selection() {
+        let args = CliCommandComparer::parse_from([
+            "comparer",
+            "--file1",
+            "a.csv",
+            "--format1",
+            "csv",
+            "--file2",
+            "b.txt",
+            "--format2",
+            "txt",
+            "--ignore",
+            "description,timestamp",
+            "--normalize",
+            "description:trim,timestamp:round-seconds",
+            "--by-key",
+        ]);
+
+        assert_eq!(args.ignore, vec![Field::Description, Field::Timestamp]);
+        assert!(args.fields.is_empty());
+        assert_eq!(args.normalize.len(), 2);
+        assert!(args.by_key);
+        assert_eq!(args.output_format, OutputFormat::Text);
+        assert_eq!(
+            args.mode().compare_options().fields(),
+            &[
+                Field::TxId,
+                Field::TxType,
+                Field::FromUserId,
+                Field::ToUserId,
+                Field::Amount,
+                Field::Status
+            ]
+        );
+    }
+
+    #[test]
+    fn check_cli_three_way() {
+        let args = CliCommandComparer::parse_from([
+            "comparer",
+            "--base",
+            "base.csv",
+            "--base-format",
+            "csv",
+            "--file1",
+            "a.bin",
+            "--format1",
+            "bin",
+            "--file2",
+            "b.txt",
+            "--format2",
+            "txt",
+            "--merge-output",
+            "merged.bin",
+            "--merge-format",
+            "bin",
+        ]);
+
+        assert_eq!(args.base.as_deref(), Some("base.csv"));
+        assert_eq!(args.base_format, Some(Format::Csv));
+        assert_eq!(args.merge_output.as_deref(), Some("merged.bin"));
+        assert_eq!(args.merge_format, Some(Format::Bin));
+
+        let res = CliCommandComparer::try_parse_from([
+            "comparer",
+            "--base",
+            "base.csv",
+            "--file1",
+            "a.bin",
+            "--format1",
+            "bin",
+            "--file2",
+            "b.txt",
+            "--format2",
+            "txt",
+        ]);
+        assert!(res.is_err());
+    }
 }
@@ -1,8 +1,12 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use data_parser::commands::convert;
+use data_parser::commands::stats::StatsArgs;
+use data_parser::{GlobalArgs, exit_code, run};
+use parser::encoding::Encoding;
 use parser::models::Format;
-use parser::{converter, file_reader};
+use std::process::ExitCode;
 
-/// CLI-интерфейс для утилиты **data_parser**.
+/// CLI-интерфейс для утилиты **converter**.
 ///
 /// Позволяет конвертировать файлы между форматами:
 ///
@@ -16,7 +20,7 @@ use parser::{converter, file_reader};
 /// # Пример использования
 ///
 /// ```bash
-/// data_parser -i transactions.csv -f csv -o bin
+/// converter -i transactions.csv -f csv -o bin
 /// ```
 ///
 /// Это прочитает файл `static/transactions.csv`,
@@ -28,38 +32,142 @@ use parser::{converter, file_reader};
 /// * `--input`, `-i` — имя входного файла (только имя, без пути)
 /// * `--input-format`, `-f` — формат входного файла: `csv`, `txt`, `bin`
 /// * `--output-format`, `-o` — формат выходного файла: `csv`, `txt`, `bin`
+/// * `--partition-by` — разбить вывод на несколько файлов: `date`, `tx_type`,
+///   `status`, `records:N`, `bytes:M` (можно перечислить через запятую)
+/// * `--output` — имя выходного файла, при `--partition-by` — шаблон пути
+///   партиции, например `out/{date}/{tx_type}.{ext}`
+/// * `--compress` — сжатие вывода: `none`, `gzip`, `zstd`, `bzip2`
+///   (к имени файла добавляется `.gz`, `.zst` или `.bz2`)
+///
+/// * `--encoding`, `--output-encoding` — кодировка входа и выхода: `utf-8`,
+///   `windows-1251`, `cp866`, `latin-1` (BOM UTF-8/UTF-16 на входе определяется сам)
+/// * `--threads` — потоков для разбора CSV и BIN (`0` — по числу ядер)
+/// * `--accounts` — справочник счетов `USER_ID,ACCOUNT,NAME,BIC` для `-o pain001`
+/// * `--user` — пользователь выписки `-o ofx`, `ofx-xml` или `qif`
+/// * `--row-group-size`, `--column-compression` — группы строк и сжатие `-o parquet`, `arrow`
+/// * `--table`, `--query`, `--sqlite-mode` — таблица, запрос и режим записи `sqlite`
+/// * `--pg-url` (feature `postgres`) — загрузить записи `-o pgcopy` или
+///   `pgcopy-binary` в PostgreSQL вместо записи файла
+/// * `--layout` — раскладка `FIELD,START,WIDTH,ALIGN,PAD` для `-f fixed` и `-o fixed`
+///
+/// Сжатые входные файлы распознаются автоматически, по сигнатуре или расширению.
+///
+/// ```bash
+/// converter -i transactions.csv -f csv -o bin --partition-by date,tx_type \
+///     --output 'out/{date}/{tx_type}.{ext}'
+/// ```
 ///
 /// Все файлы читаются и создаются внутри директории `static/`.
+///
+/// # Подкоманды
+///
+/// * `stats -i <файл> -f <формат> [--top N]` — сводная статистика по файлу
+///   за один потоковый проход, без конвертации.
+///
+/// Утилита сохранена для совместимости и выполняет подкоманды `convert`
+/// и `stats` единого CLI `data_parser` (крейт `data_parser`): аргументы
+/// вывода берутся из `data_parser convert` как есть.
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct CliCommandDataParser {
-    #[arg(short = 'i', long)]
-    pub input: String,
+    #[command(flatten)]
+    pub legacy: Option<LegacyArgs>,
+
+    #[command(flatten)]
+    pub convert: Option<convert::ConvertArgs>,
 
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Общие опции `data_parser` для конвертации (режим по умолчанию, без
+/// подкоманды): здесь они задаются рядом с аргументами `data_parser convert`
+#[derive(Args, Debug)]
+pub struct LegacyArgs {
     #[arg(short = 'f', long)]
     pub input_format: Format,
 
-    #[arg(short = 'o', long)]
-    pub output_format: Format,
+    #[arg(long, default_value = "utf-8")]
+    pub encoding: Encoding,
+
+    #[arg(long, default_value_t = 1)]
+    pub threads: usize,
+
+    #[arg(long)]
+    pub layout: Option<String>,
+
+    #[arg(long)]
+    pub table: Option<String>,
+
+    #[arg(long)]
+    pub query: Option<String>,
 }
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let params: CliCommandDataParser = CliCommandDataParser::parse();
-    let data_file = file_reader(&params.input, params.input_format);
 
-    let data = match data_file {
-        Ok(data_file) => data_file,
-        Err(e) => return Err(Box::new(e)),
+/// Подкоманды `converter`
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Сводная статистика по файлу: количество, суммы, диапазон дат, пользователи
+    Stats {
+        #[arg(short = 'i', long)]
+        input: String,
+
+        #[arg(short = 'f', long)]
+        input_format: Format,
+
+        /// Сколько отправителей и получателей показать в топе
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli: CliCommandDataParser = CliCommandDataParser::parse();
+
+    let (global, command) = match (cli.command, cli.legacy, cli.convert) {
+        (
+            Some(Command::Stats {
+                input,
+                input_format,
+                top,
+            }),
+            _,
+            _,
+        ) => (
+            legacy_global(input_format),
+            data_parser::Command::Stats(StatsArgs { input, top }),
+        ),
+        (None, Some(params), Some(convert)) => (
+            GlobalArgs {
+                threads: params.threads,
+                encoding: params.encoding,
+                layout: params.layout,
+                table: params.table,
+                query: params.query,
+                ..legacy_global(params.input_format)
+            },
+            data_parser::Command::Convert(convert),
+        ),
+        (None, _, _) => unreachable!("clap требует аргументы конвертации без подкоманды"),
     };
 
-    let _ = converter(params.output_format, data);
+    global.init_logging();
+    exit_code(run(&global, command))
+}
 
-    Ok(())
+/// Опции `data_parser` для старого интерфейса: файлы в `static/`, формат задан явно
+fn legacy_global(input_format: Format) -> GlobalArgs {
+    GlobalArgs {
+        input_formats: vec![input_format],
+        ..GlobalArgs::default()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parser::compression::Compression;
+    use parser::partition::PartitionBy;
     #[test]
     fn check_cli_data_parser() {
         let args = CliCommandDataParser::parse_from([
@@ -72,8 +180,67 @@ mod tests {
             "csv",
         ]);
 
+        let legacy = args.legacy.expect("опции конвертации");
+        assert_eq!(legacy.input_format, Format::Bin);
+        let args = args.convert.expect("аргументы конвертации");
         assert_eq!(args.input, "a.bin");
-        assert_eq!(args.input_format, Format::Bin);
         assert_eq!(args.output_format, Format::Csv);
+        assert!(args.partition_by.is_empty());
+        assert_eq!(args.compress, None);
+    }
+
+    #[test]
+    fn check_cli_partition_by() {
+        let args = CliCommandDataParser::parse_from([
+            "converter",
+            "-i",
+            "a.csv",
+            "-f",
+            "csv",
+            "-o",
+            "bin",
+            "--partition-by",
+            "date,tx_type,records:100",
+            "--output",
+            "out/{date}/{tx_type}-{part}.bin",
+            "--compress",
+            "zstd",
+        ]);
+
+        assert_eq!(args.legacy.expect("опции конвертации").threads, 1);
+        let args = args.convert.expect("аргументы конвертации");
+        assert_eq!(
+            args.partition_by,
+            vec![
+                PartitionBy::Date,
+                PartitionBy::TxType,
+                PartitionBy::Records(100)
+            ]
+        );
+        assert_eq!(
+            args.output.as_deref(),
+            Some("out/{date}/{tx_type}-{part}.bin")
+        );
+        assert_eq!(args.compress, Some(Compression::Zstd));
+    }
+
+    #[test]
+    fn check_cli_stats() {
+        let args =
+            CliCommandDataParser::parse_from(["converter", "stats", "-i", "a.txt", "-f", "txt"]);
+
+        assert!(args.legacy.is_none() && args.convert.is_none());
+        match args.command {
+            Some(Command::Stats {
+                input,
+                input_format,
+                top,
+            }) => {
+                assert_eq!(input, "a.txt");
+                assert_eq!(input_format, Format::Txt);
+                assert_eq!(top, 5);
+            }
+            other => panic!("Expected stats, got {:?}", other),
+        }
     }
 }
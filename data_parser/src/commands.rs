@@ -0,0 +1,12 @@
+/// Сравнение, сверка и трёхстороннее сравнение
+pub mod compare;
+/// Конвертация между форматами
+pub mod convert;
+/// Просмотр первых записей
+pub mod inspect;
+/// Балансы пользователей
+pub mod ledger;
+/// Сводная статистика
+pub mod stats;
+/// Проверка файлов на ошибки разбора
+pub mod validate;
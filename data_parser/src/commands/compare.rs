@@ -0,0 +1,212 @@
+use crate::{GlobalArgs, Outcome};
+use clap::Args;
+use parser::compare::{CompareOptions, DiffReport, Field, FieldNormalizer, OutputFormat};
+use parser::merge::three_way;
+use parser::models::{Format, Record};
+use parser::reconcile::{
+    DescriptionRule, MatchStatus, ReportFormat, Tolerance, reconcile, write_report,
+};
+use parser::write_records;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+
+/// Аргументы `data_parser compare`
+#[derive(Args, Debug, Clone)]
+pub struct CompareArgs {
+    /// Два сравниваемых файла: `-i left -i right`
+    #[arg(short = 'i', long = "input", required = true)]
+    pub inputs: Vec<String>,
+
+    ///режим и настройки сравнения
+    #[command(flatten)]
+    pub mode: CompareMode,
+}
+
+/// Настройки сравнения, общие для `data_parser compare` и `comparer`
+#[derive(Args, Debug, Clone)]
+pub struct CompareMode {
+    /// Сравнивать только перечисленные поля
+    #[arg(long, value_delimiter = ',')]
+    pub fields: Vec<Field>,
+    /// Не сравнивать перечисленные поля
+    #[arg(long, value_delimiter = ',')]
+    pub ignore: Vec<Field>,
+    /// Нормализация перед сравнением, например `description:trim`
+    #[arg(long, value_delimiter = ',')]
+    pub normalize: Vec<FieldNormalizer>,
+    /// Сопоставлять записи по `tx_id`, а не по позиции
+    #[arg(long, conflicts_with = "reconcile")]
+    pub by_key: bool,
+
+    /// Общий базовый файл для трёхстороннего сравнения; записи сравниваются
+    /// и объединяются по всем полям
+    #[arg(long, conflicts_with_all = ["reconcile", "by_key", "fields", "ignore", "normalize"])]
+    pub base: Option<String>,
+    /// Формат базового файла (по умолчанию — по расширению)
+    #[arg(long, requires = "base")]
+    pub base_format: Option<Format>,
+    /// Куда записать объединённый файл, если конфликтов нет
+    #[arg(long, requires = "base")]
+    pub merge_output: Option<String>,
+    /// Формат объединённого файла (по умолчанию как у базы)
+    #[arg(long, requires = "merge_output")]
+    pub merge_format: Option<Format>,
+
+    /// Сверка с допусками вместо точного сравнения
+    #[arg(long)]
+    pub reconcile: bool,
+    /// Допустимое расхождение времени, в секундах
+    #[arg(long, default_value_t = 0, requires = "reconcile")]
+    pub timestamp_tolerance: u64,
+    /// Допустимое расхождение суммы, в наименьших единицах
+    #[arg(long, default_value_t = 0, requires = "reconcile")]
+    pub amount_tolerance: u64,
+    /// Правило сравнения описания: `exact`, `ignore`, `fuzzy[:порог]`
+    #[arg(long, default_value = "exact", requires = "reconcile")]
+    pub description: DescriptionRule,
+    /// Файл отчёта сверки
+    #[arg(long, requires = "reconcile")]
+    pub report: Option<String>,
+    /// Формат отчёта сверки
+    #[arg(long, default_value = "csv", requires = "reconcile")]
+    pub report_format: ReportFormat,
+}
+
+impl CompareMode {
+    /// Выбранные поля и нормализация в виде [`CompareOptions`]
+    pub fn compare_options(&self) -> CompareOptions {
+        CompareOptions::new(&self.fields, &self.ignore, self.normalize.clone())
+    }
+}
+
+/// Сравнивает два файла; расхождения или конфликты дают [`Outcome::Differences`]
+pub fn run(global: &GlobalArgs, args: CompareArgs) -> Result<Outcome, Box<dyn Error>> {
+    let [left_name, right_name] = args.inputs.as_slice() else {
+        return Err(format!(
+            "compare expects exactly two inputs, got {}",
+            args.inputs.len()
+        )
+        .into());
+    };
+
+    let left = global.read_records(0, left_name)?;
+    let right = global.read_records(1, right_name)?;
+    let mode = &args.mode;
+
+    let identical = if mode.reconcile {
+        run_reconcile(global, mode, left, right)?
+    } else if let Some(base_name) = &mode.base {
+        let base_format = match &mode.base_format {
+            Some(format) => format.clone(),
+            None => global.input_format(2, base_name)?,
+        };
+        let base = global.read_records_as(base_name, base_format.clone())?;
+        run_three_way(global, mode, base_format, base, left, right)?
+    } else {
+        let report = DiffReport::new(
+            left_name,
+            &left,
+            right_name,
+            &right,
+            &mode.compare_options(),
+            mode.by_key,
+        );
+
+        let mut stdout = std::io::stdout().lock();
+        report.write_to(global.style.clone(), &mut stdout)?;
+        stdout.flush()?;
+        report.summary.identical
+    };
+
+    Ok(if identical {
+        Outcome::Success
+    } else {
+        Outcome::Differences
+    })
+}
+
+fn run_three_way(
+    global: &GlobalArgs,
+    mode: &CompareMode,
+    base_format: Format,
+    base: Vec<Record>,
+    left: Vec<Record>,
+    right: Vec<Record>,
+) -> Result<bool, Box<dyn Error>> {
+    let result = three_way(&base, &left, &right);
+
+    let mut stdout = std::io::stdout().lock();
+    result.write_to(global.style.clone(), &mut stdout)?;
+    stdout.flush()?;
+
+    if result.has_conflicts() {
+        if mode.merge_output.is_some() {
+            log::warn!("Merged file was not written because of conflicts.");
+        }
+        return Ok(false);
+    }
+
+    if let Some(merge_output) = &mode.merge_output {
+        let merge_format = mode.merge_format.clone().unwrap_or(base_format);
+        let mut file = File::create(global.path(merge_output))?;
+        write_records(merge_format, result.merged()?, &mut file)?;
+    }
+
+    Ok(true)
+}
+
+fn run_reconcile(
+    global: &GlobalArgs,
+    mode: &CompareMode,
+    left: Vec<Record>,
+    right: Vec<Record>,
+) -> Result<bool, Box<dyn Error>> {
+    let tolerance = Tolerance {
+        timestamp_ms: mode.timestamp_tolerance.saturating_mul(1000),
+        amount: mode.amount_tolerance,
+        description: mode.description.clone(),
+    };
+    let result = reconcile(left, right, &tolerance, &mode.compare_options());
+
+    let counts: Vec<(String, usize)> = [
+        MatchStatus::Matched,
+        MatchStatus::Mismatched,
+        MatchStatus::UnmatchedLeft,
+        MatchStatus::UnmatchedRight,
+    ]
+    .into_iter()
+    .map(|status| (status.to_string(), result.count(status)))
+    .collect();
+
+    let mut stdout = std::io::stdout().lock();
+    match global.style {
+        OutputFormat::Text => {
+            for (status, count) in &counts {
+                writeln!(stdout, "{}: {}", status, count)?;
+            }
+        }
+        OutputFormat::Json => {
+            let summary: BTreeMap<_, _> = counts.into_iter().collect();
+            serde_json::to_writer_pretty(&mut stdout, &summary)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut stdout);
+            writer.write_record(["STATUS", "COUNT"])?;
+            for (status, count) in counts {
+                writer.write_record([status, count.to_string()])?;
+            }
+            writer.flush()?;
+        }
+    }
+    stdout.flush()?;
+
+    if let Some(report) = &mode.report {
+        let mut file = File::create(global.path(report))?;
+        write_report(&result, mode.report_format.clone(), &mut file)?;
+    }
+
+    Ok(result.is_clean())
+}
@@ -0,0 +1,148 @@
+use crate::{GlobalArgs, Outcome};
+use clap::Args;
+use parser::accounts::AccountMap;
+use parser::compression::Compression;
+use parser::encoding::Encoding;
+use parser::models::{ColumnCompression, Format, FormatOptions, SqliteMode};
+use parser::partition::{PartitionBy, default_template, partition, write_partitions};
+use parser::write_file;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+/// Аргументы `data_parser convert`
+#[derive(Args, Debug, Clone)]
+pub struct ConvertArgs {
+    /// Входной файл
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    /// Формат выходного файла
+    #[arg(short = 'o', long)]
+    pub output_format: Format,
+
+    /// Разбить вывод на партиции: `date`, `tx_type`, `status`, `records:N`, `bytes:M`
+    #[arg(long, value_delimiter = ',')]
+    pub partition_by: Vec<PartitionBy>,
+
+    /// Имя выходного файла (по умолчанию `output.<ext>`),
+    /// при `--partition-by` — шаблон пути партиции
+    #[arg(long)]
+    pub output: Option<String>,
+
+    /// Сжатие вывода: `none`, `gzip`, `zstd`, `bzip2`.
+    /// Без него определяется по расширению `--output` (`.gz`, `.zst`, `.bz2`)
+    #[arg(long)]
+    pub compress: Option<Compression>,
+
+    /// Кодировка вывода: `utf-8`, `windows-1251`, `cp866`, `latin-1`.
+    /// Кодировку входа задаёт общий `--encoding`
+    #[arg(long, default_value = "utf-8")]
+    pub output_encoding: Encoding,
+
+    /// Справочник счетов CSV `USER_ID,ACCOUNT[,NAME][,BIC]`: реквизиты
+    /// плательщика и получателя для `pain001`
+    #[arg(long)]
+    pub accounts: Option<String>,
+
+    /// Пользователь, для которого выгружается выписка `ofx`, `ofx-xml` или `qif`
+    #[arg(long)]
+    pub user: Option<u64>,
+
+    /// Записей в группе строк `parquet` и в пакете `arrow` (по умолчанию 65536)
+    #[arg(long)]
+    pub row_group_size: Option<usize>,
+
+    /// Сжатие колонок `parquet` и `arrow`: `none`, `snappy`, `gzip`, `lz4`, `zstd`
+    #[arg(long)]
+    pub column_compression: Option<ColumnCompression>,
+
+    /// Запись в существующую таблицу `sqlite`: `create` — ошибка, если таблица
+    /// уже есть, `replace` — пересоздать, `append` — дописать, `upsert` —
+    /// дописать и обновить по `TX_ID`
+    #[arg(long, default_value = "create")]
+    pub sqlite_mode: SqliteMode,
+
+    /// Загрузить записи в PostgreSQL по строке подключения вместо записи файла:
+    /// `-o pgcopy` или `pgcopy-binary` выбирает вариант COPY, таблицу — `--table`
+    #[cfg(feature = "postgres")]
+    #[arg(long, conflicts_with_all = ["partition_by", "output", "compress", "output_encoding"])]
+    pub pg_url: Option<String>,
+}
+
+impl ConvertArgs {
+    /// Сжатие вывода: явное `--compress` или по расширению `--output`
+    pub fn compression(&self) -> Compression {
+        self.compress
+            .or_else(|| {
+                self.output
+                    .as_deref()
+                    .and_then(|output| Compression::from_path(Path::new(output)))
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Читает входной файл и записывает его в выходном формате
+pub fn run(global: &GlobalArgs, args: ConvertArgs) -> Result<Outcome, Box<dyn Error>> {
+    let mut records = global.read_records(0, &args.input)?;
+    if let Some(accounts) = &args.accounts {
+        AccountMap::from_read(File::open(global.path(accounts))?)?.apply(&mut records);
+    }
+
+    let compression = args.compression();
+    let options = FormatOptions {
+        user_id: args.user,
+        row_group_size: args.row_group_size,
+        column_compression: args.column_compression,
+        sqlite_mode: args.sqlite_mode,
+        ..global.format_options()?
+    };
+
+    #[cfg(feature = "postgres")]
+    if let Some(url) = &args.pg_url {
+        let rows = parser::load_postgres(url, args.output_format, &options, records)?;
+        log::info!("loaded {} rows into PostgreSQL", rows);
+        return Ok(Outcome::Success);
+    }
+
+    if args.partition_by.is_empty() {
+        let name = args
+            .output
+            .unwrap_or_else(|| format!("output.{}", args.output_format.extension()));
+        let path = compression.with_extension(&global.path(&name));
+        write_file(
+            &path,
+            args.output_format,
+            compression,
+            args.output_encoding,
+            &options,
+            records,
+        )?;
+        log::info!("written '{}'", path.display());
+        return Ok(Outcome::Success);
+    }
+
+    let template = args
+        .output
+        .unwrap_or_else(|| default_template(&args.partition_by));
+    let partitions = partition(
+        records,
+        &args.partition_by,
+        &template,
+        args.output_format.clone(),
+    )?;
+
+    for path in write_partitions(
+        &global.dir,
+        partitions,
+        args.output_format,
+        compression,
+        args.output_encoding,
+        &options,
+    )? {
+        println!("{}", path.display());
+    }
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,55 @@
+use crate::{GlobalArgs, Outcome};
+use clap::Args;
+use parser::compare::OutputFormat;
+use parser::models::{Format, Record};
+use parser::{path_records, write_records};
+use std::error::Error;
+use std::io::Write;
+
+/// Аргументы `data_parser inspect`
+#[derive(Args, Debug, Clone)]
+pub struct InspectArgs {
+    /// Входной файл
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    /// Сколько записей показать
+    #[arg(long, default_value_t = 10)]
+    pub limit: usize,
+
+    /// Сколько записей пропустить с начала файла
+    #[arg(long, default_value_t = 0)]
+    pub skip: usize,
+}
+
+/// Печатает записи с `skip` по `skip + limit`, читая файл потоково
+pub fn run(global: &GlobalArgs, args: InspectArgs) -> Result<Outcome, Box<dyn Error>> {
+    let format = global.input_format(0, &args.input)?;
+    let records: Vec<Record> = path_records(
+        &global.path(&args.input),
+        format,
+        global.encoding,
+        &global.format_options()?,
+    )?
+    .skip(args.skip)
+    .take(args.limit)
+    .collect::<Result<_, _>>()?;
+
+    let mut stdout = std::io::stdout().lock();
+    match global.style {
+        OutputFormat::Text => {
+            for (index, record) in records.iter().enumerate() {
+                writeln!(stdout, "# Record {}", args.skip + index + 1)?;
+                writeln!(stdout, "{}", record)?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &records)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => write_records(Format::Csv, records, &mut stdout)?,
+    }
+    stdout.flush()?;
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,74 @@
+use crate::{GlobalArgs, Outcome};
+use clap::{Args, ValueEnum};
+use parser::compare::OutputFormat;
+use parser::ledger::{ExportFormat, Ledger, export};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+
+/// Что выгружать из книги
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum Report {
+    /// Итоговые балансы пользователей
+    #[value(name = "balances")]
+    Balances,
+    /// Проводки с нарастающим итогом
+    #[value(name = "entries")]
+    Entries,
+    /// Случаи ухода баланса в минус
+    #[value(name = "overdrafts")]
+    Overdrafts,
+}
+
+/// Аргументы `data_parser ledger`
+#[derive(Args, Debug, Clone)]
+pub struct LedgerArgs {
+    /// Входной файл
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    /// Что выгружать
+    #[arg(long, default_value = "balances")]
+    pub report: Report,
+
+    /// Файл выгрузки; без него вывод идёт в stdout
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+/// Формат выгрузки книги для стиля вывода: текст выгружается как CSV
+pub fn export_format(style: &OutputFormat) -> ExportFormat {
+    match style {
+        OutputFormat::Json => ExportFormat::Json,
+        OutputFormat::Text | OutputFormat::Csv => ExportFormat::Csv,
+    }
+}
+
+/// Применяет успешные транзакции и выгружает выбранный отчёт
+pub fn run(global: &GlobalArgs, args: LedgerArgs) -> Result<Outcome, Box<dyn Error>> {
+    let records = global.read_records(0, &args.input)?;
+
+    let ledger = Ledger::from_records(&records);
+
+    for overdraft in ledger.overdrafts() {
+        eprintln!(
+            "Overdraft: user {} has balance {} after transaction {}",
+            overdraft.user_id, overdraft.balance, overdraft.tx_id
+        );
+    }
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(name) => Box::new(File::create(global.path(name))?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    let export_format = export_format(&global.style);
+    match args.report {
+        Report::Balances => export(&ledger.balances(), export_format, &mut writer)?,
+        Report::Entries => export(ledger.entries(), export_format, &mut writer)?,
+        Report::Overdrafts => export(ledger.overdrafts(), export_format, &mut writer)?,
+    }
+    writer.flush()?;
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,91 @@
+use crate::{GlobalArgs, Outcome};
+use clap::Args;
+use parser::compare::OutputFormat;
+use parser::path_records;
+use parser::stats::Stats;
+use std::error::Error;
+use std::io::Write;
+
+/// Аргументы `data_parser stats`
+#[derive(Args, Debug, Clone)]
+pub struct StatsArgs {
+    /// Входной файл
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    /// Сколько отправителей и получателей показать в топе
+    #[arg(long, default_value_t = 5)]
+    pub top: usize,
+}
+
+/// Собирает статистику за один потоковый проход и печатает её
+pub fn run(global: &GlobalArgs, args: StatsArgs) -> Result<Outcome, Box<dyn Error>> {
+    let format = global.input_format(0, &args.input)?;
+    let stats = Stats::from_records(path_records(
+        &global.path(&args.input),
+        format,
+        global.encoding,
+        &global.format_options()?,
+    )?)?;
+
+    let mut stdout = std::io::stdout().lock();
+    match global.style {
+        OutputFormat::Text => write!(stdout, "{}", stats.report(args.top))?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &stats.summary(args.top))?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => {
+            let summary = stats.summary(args.top);
+            let optional = |value: Option<String>| value.unwrap_or_default();
+
+            let mut rows: Vec<(String, String)> = vec![
+                ("count".into(), summary.count.to_string()),
+                ("amount_sum".into(), summary.amount_sum.to_string()),
+                (
+                    "amount_min".into(),
+                    optional(summary.amount_min.map(|v| v.to_string())),
+                ),
+                (
+                    "amount_max".into(),
+                    optional(summary.amount_max.map(|v| v.to_string())),
+                ),
+                (
+                    "amount_mean".into(),
+                    optional(summary.amount_mean.map(|v| format!("{:.2}", v))),
+                ),
+                (
+                    "timestamp_min".into(),
+                    optional(summary.timestamp_min.map(|v| v.to_string())),
+                ),
+                (
+                    "timestamp_max".into(),
+                    optional(summary.timestamp_max.map(|v| v.to_string())),
+                ),
+                ("distinct_users".into(), summary.distinct_users.to_string()),
+            ];
+            rows.extend(
+                summary
+                    .by_tx_type
+                    .iter()
+                    .map(|(tx_type, count)| (format!("tx_type:{}", tx_type), count.to_string())),
+            );
+            rows.extend(
+                summary
+                    .by_status
+                    .iter()
+                    .map(|(status, count)| (format!("status:{}", status), count.to_string())),
+            );
+
+            let mut writer = csv::Writer::from_writer(&mut stdout);
+            writer.write_record(["METRIC", "VALUE"])?;
+            for (metric, value) in rows {
+                writer.write_record([metric, value])?;
+            }
+            writer.flush()?;
+        }
+    }
+    stdout.flush()?;
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,131 @@
+use crate::{GlobalArgs, Outcome};
+use clap::Args;
+use parser::compare::OutputFormat;
+use parser::path_records;
+use serde::Serialize;
+use std::error::Error;
+use std::io::Write;
+
+/// Аргументы `data_parser validate`
+#[derive(Args, Debug, Clone)]
+pub struct ValidateArgs {
+    /// Проверяемые файлы: `-i a.csv -i b.bin`
+    #[arg(short = 'i', long = "input", required = true)]
+    pub inputs: Vec<String>,
+
+    /// Сколько ошибок в одном файле показать, прежде чем остановиться
+    #[arg(long, default_value_t = 100)]
+    pub max_errors: usize,
+}
+
+/// Ошибка разбора одной записи
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RecordError {
+    ///номер записи, начиная с 1
+    pub record: usize,
+    ///текст ошибки
+    pub message: String,
+}
+
+/// Результат проверки одного файла
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FileReport {
+    ///имя файла
+    pub file: String,
+    ///количество успешно разобранных записей
+    pub records: usize,
+    ///ошибки разбора
+    pub errors: Vec<RecordError>,
+}
+
+/// Проверяет каждый файл потоковым чтением; ошибки в данных дают
+/// [`Outcome::Differences`]
+pub fn run(global: &GlobalArgs, args: ValidateArgs) -> Result<Outcome, Box<dyn Error>> {
+    let mut reports = Vec::with_capacity(args.inputs.len());
+    let options = global.format_options()?;
+
+    for (index, name) in args.inputs.iter().enumerate() {
+        let format = global.input_format(index, name)?;
+        let mut report = FileReport {
+            file: name.clone(),
+            records: 0,
+            errors: Vec::new(),
+        };
+
+        for (position, record) in
+            path_records(&global.path(name), format, global.encoding, &options)?.enumerate()
+        {
+            match record {
+                Ok(_) => report.records += 1,
+                Err(e) => {
+                    log::debug!("{}: record {}: {}", name, position + 1, e);
+                    report.errors.push(RecordError {
+                        record: position + 1,
+                        message: e.to_string(),
+                    });
+                    if report.errors.len() >= args.max_errors {
+                        log::warn!("{}: stopped after {} errors", name, args.max_errors);
+                        break;
+                    }
+                }
+            }
+        }
+
+        log::info!(
+            "{}: {} records, {} errors",
+            name,
+            report.records,
+            report.errors.len()
+        );
+        reports.push(report);
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    match global.style {
+        OutputFormat::Text => {
+            for report in &reports {
+                let verdict = if report.errors.is_empty() {
+                    "OK"
+                } else {
+                    "INVALID"
+                };
+                writeln!(
+                    stdout,
+                    "{}: {} ({} records, {} errors)",
+                    report.file,
+                    verdict,
+                    report.records,
+                    report.errors.len()
+                )?;
+                for error in &report.errors {
+                    writeln!(stdout, "  record {}: {}", error.record, error.message)?;
+                }
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &reports)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut stdout);
+            writer.write_record(["FILE", "RECORD", "ERROR"])?;
+            for report in &reports {
+                for error in &report.errors {
+                    writer.write_record([
+                        report.file.as_str(),
+                        &error.record.to_string(),
+                        &error.message,
+                    ])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+    stdout.flush()?;
+
+    Ok(if reports.iter().all(|r| r.errors.is_empty()) {
+        Outcome::Success
+    } else {
+        Outcome::Differences
+    })
+}
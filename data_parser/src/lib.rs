@@ -0,0 +1,298 @@
+#![warn(missing_docs)]
+
+//! Единый CLI **data_parser** с подкомандами:
+//!
+//! - `convert` — конвертация между форматами (с разбиением на партиции),
+//! - `compare` — сравнение двух файлов, сверка с допусками, трёхстороннее сравнение,
+//! - `validate` — проверка, что файлы читаются без ошибок,
+//! - `stats` — сводная статистика по файлу,
+//! - `inspect` — просмотр первых записей файла,
+//! - `ledger` — балансы пользователей.
+//!
+//! Общие опции ([`GlobalArgs`]) задаются один раз для всех подкоманд:
+//! директория с файлами, форматы и кодировка входных файлов, уровень
+//! логирования, стиль вывода отчётов и число потоков разбора.
+//!
+//! Старые утилиты `converter` и `comparer` — тонкие обёртки над
+//! подкомандами из [`commands`], сохраняющие свои флаги.
+//!
+//! ## Коды возврата
+//!
+//! - `0` — успех (файлы совпадают, ошибок нет),
+//! - `1` — найдены расхождения, ошибки валидации или конфликты,
+//! - `2` — ошибка выполнения (файл не найден, не разобран и т.п.).
+
+/// Реализации подкоманд
+pub mod commands;
+
+use clap::{ArgAction, Args, Parser, Subcommand};
+use parser::compare::OutputFormat;
+use parser::encoding::Encoding;
+use parser::layout::Layout;
+use parser::models::{Format, FormatOptions, Record};
+use parser::{path_reader, path_reader_parallel};
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Команда завершилась успешно, расхождений нет
+pub const EXIT_SUCCESS: u8 = 0;
+/// Найдены расхождения, ошибки валидации или конфликты
+pub const EXIT_DIFFERENCES: u8 = 1;
+/// Ошибка выполнения
+pub const EXIT_ERROR: u8 = 2;
+
+/// Результат подкоманды, определяющий код возврата
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Успех, код `0`
+    Success,
+    /// Расхождения или ошибки в данных, код `1`
+    Differences,
+}
+
+/// Единый CLI `data_parser`
+///
+/// # Пример использования
+///
+/// ```bash
+/// data_parser convert -i transactions.csv -o bin
+/// data_parser compare -i a.csv -i b.bin --style json
+/// data_parser -v --dir data validate -i a.csv -i b.txt
+/// ```
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    ///общие опции
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    ///выполняемая подкоманда
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Опции, общие для всех подкоманд
+#[derive(Args, Debug, Clone)]
+pub struct GlobalArgs {
+    /// Директория, относительно которой читаются и пишутся файлы
+    #[arg(long, global = true, default_value = "static")]
+    pub dir: PathBuf,
+
+    /// Формат входных файлов; повторяется для каждого входа по порядку.
+    /// Без него формат определяется по расширению
+    #[arg(short = 'f', long = "input-format", global = true)]
+    pub input_formats: Vec<Format>,
+
+    /// Кодировка входных файлов: `utf-8`, `windows-1251`, `cp866`, `latin-1`.
+    /// BOM UTF-8 и UTF-16 определяется автоматически
+    #[arg(long, global = true, default_value = "utf-8")]
+    pub encoding: Encoding,
+
+    /// Стиль вывода отчётов: `text`, `json` или `csv`
+    #[arg(long, global = true, default_value = "text")]
+    pub style: OutputFormat,
+
+    /// Подробный лог в stderr (`-v` — info, `-vv` — debug)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Выводить в лог только ошибки
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Потоков для разбора CSV и BIN (`0` — по числу ядер, `1` — без параллелизма)
+    #[arg(long, global = true, default_value_t = 1)]
+    pub threads: usize,
+
+    /// Файл раскладки формата фиксированной ширины (`FIELD,START,WIDTH,ALIGN,PAD`)
+    #[arg(long, global = true)]
+    pub layout: Option<String>,
+
+    /// Таблица SQLite для чтения и записи (по умолчанию `transactions`)
+    #[arg(long, global = true)]
+    pub table: Option<String>,
+
+    /// Запрос SQLite, которым читаются записи вместо всей таблицы
+    #[arg(long, global = true)]
+    pub query: Option<String>,
+}
+
+impl Default for GlobalArgs {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("static"),
+            input_formats: Vec::new(),
+            encoding: Encoding::Utf8,
+            style: OutputFormat::Text,
+            verbose: 0,
+            quiet: false,
+            threads: 1,
+            layout: None,
+            table: None,
+            query: None,
+        }
+    }
+}
+
+impl GlobalArgs {
+    /// Путь к файлу внутри рабочей директории
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Параметры форматов из общих опций: раскладка из `--layout`,
+    /// таблица и запрос SQLite
+    pub fn format_options(&self) -> Result<FormatOptions, Box<dyn Error>> {
+        let layout = match &self.layout {
+            Some(name) => Some(Layout::from_read(File::open(self.path(name))?)?),
+            None => None,
+        };
+        Ok(FormatOptions {
+            layout,
+            table: self.table.clone(),
+            query: self.query.clone(),
+            ..FormatOptions::default()
+        })
+    }
+
+    /// Формат `index`-го входного файла.
+    ///
+    /// Берётся из `-f` с тем же номером; единственный `-f` действует на все
+    /// входы; без `-f` формат определяется по расширению.
+    pub fn input_format(&self, index: usize, name: &str) -> Result<Format, Box<dyn Error>> {
+        let explicit = match self.input_formats.as_slice() {
+            [single] => Some(single),
+            formats => formats.get(index),
+        };
+
+        explicit
+            .cloned()
+            .or_else(|| Format::from_path(Path::new(name)))
+            .ok_or_else(|| {
+                format!(
+                    "cannot detect format of '{}', pass it with --input-format",
+                    name
+                )
+                .into()
+            })
+    }
+
+    /// Читает все записи `index`-го входного файла
+    pub fn read_records(&self, index: usize, name: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        self.read_records_as(name, self.input_format(index, name)?)
+    }
+
+    /// Читает все записи файла в заданном формате, параллельно при `--threads`
+    pub fn read_records_as(
+        &self,
+        name: &str,
+        format: Format,
+    ) -> Result<Vec<Record>, Box<dyn Error>> {
+        let path = self.path(name);
+        let options = self.format_options()?;
+        let input = match self.threads {
+            1 => path_reader(&path, format, self.encoding, &options)?,
+            threads => path_reader_parallel(&path, format, self.encoding, &options, threads)?,
+        };
+        let records = input.get_record();
+        log::info!("read {} records from '{}'", records.len(), name);
+        Ok(records)
+    }
+
+    fn log_level(&self) -> log::LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => log::LevelFilter::Error,
+            (false, 0) => log::LevelFilter::Warn,
+            (false, 1) => log::LevelFilter::Info,
+            (false, _) => log::LevelFilter::Debug,
+        }
+    }
+
+    /// Настраивает логирование в stderr согласно `-v`/`-q`
+    pub fn init_logging(&self) {
+        let _ = env_logger::Builder::new()
+            .filter_level(self.log_level())
+            .format_target(false)
+            .format_timestamp(None)
+            .try_init();
+    }
+}
+
+/// Подкоманды `data_parser`
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Конвертировать файл в другой формат
+    Convert(commands::convert::ConvertArgs),
+    /// Сравнить два файла (или две копии с общей базой)
+    Compare(commands::compare::CompareArgs),
+    /// Проверить, что файлы читаются без ошибок
+    Validate(commands::validate::ValidateArgs),
+    /// Сводная статистика по файлу
+    Stats(commands::stats::StatsArgs),
+    /// Показать первые записи файла
+    Inspect(commands::inspect::InspectArgs),
+    /// Балансы пользователей и овердрафты
+    Ledger(commands::ledger::LedgerArgs),
+}
+
+/// Выполняет подкоманду
+pub fn run(global: &GlobalArgs, command: Command) -> Result<Outcome, Box<dyn Error>> {
+    match command {
+        Command::Convert(args) => commands::convert::run(global, args),
+        Command::Compare(args) => commands::compare::run(global, args),
+        Command::Validate(args) => commands::validate::run(global, args),
+        Command::Stats(args) => commands::stats::run(global, args),
+        Command::Inspect(args) => commands::inspect::run(global, args),
+        Command::Ledger(args) => commands::ledger::run(global, args),
+    }
+}
+
+/// Переводит результат команды в код возврата, печатая ошибку в stderr
+pub fn exit_code(result: Result<Outcome, Box<dyn Error>>) -> ExitCode {
+    match result {
+        Ok(Outcome::Success) => ExitCode::from(EXIT_SUCCESS),
+        Ok(Outcome::Differences) => ExitCode::from(EXIT_DIFFERENCES),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_cli_global_options() {
+        let cli = Cli::parse_from([
+            "data_parser",
+            "compare",
+            "-i",
+            "a.csv",
+            "-i",
+            "b.data",
+            "-f",
+            "csv",
+            "-f",
+            "bin",
+            "--style",
+            "json",
+            "-vv",
+            "--dir",
+            "data",
+        ]);
+
+        assert_eq!(cli.global.dir, PathBuf::from("data"));
+        assert_eq!(cli.global.style, OutputFormat::Json);
+        assert_eq!(cli.global.verbose, 2);
+        assert_eq!(cli.global.input_format(1, "b.data").unwrap(), Format::Bin);
+        assert!(matches!(cli.command, Command::Compare(_)));
+
+        let global = GlobalArgs::default();
+        assert_eq!(global.input_format(0, "x.TXT").unwrap(), Format::Txt);
+        assert!(global.input_format(0, "x.dat").is_err());
+    }
+}
@@ -0,0 +1,10 @@
+use clap::Parser;
+use data_parser::{Cli, exit_code, run};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    cli.global.init_logging();
+
+    exit_code(run(&cli.global, cli.command))
+}
@@ -0,0 +1,101 @@
+use clap::Parser;
+use data_parser::GlobalArgs;
+use data_parser::commands::ledger::{self, LedgerArgs, Report};
+use data_parser::exit_code;
+use parser::compare::OutputFormat;
+use parser::ledger::ExportFormat;
+use parser::models::Format;
+use std::process::ExitCode;
+
+/// CLI-команда `ledger`
+///
+/// Применяет успешные транзакции файла в порядке `timestamp`,
+/// считает балансы пользователей и находит овердрафты.
+///
+/// # Пример использования:
+/// ```bash
+/// ledger -i transactions.csv -f csv -e json --output balances.json
+/// ```
+///
+/// # Опции:
+/// - `--input`, `-i` — имя входного файла (внутри `static/`)
+/// - `--input-format`, `-f` — его формат (`csv`, `txt`, `bin`)
+/// - `--export-format`, `-e` — формат выгрузки: `csv` (по умолчанию) или `json`
+/// - `--report` — что выгружать: `balances` (по умолчанию), `entries`, `overdrafts`
+/// - `--output` — имя файла выгрузки в `static/`; без него вывод идёт в stdout
+/// - `--threads` — потоков для разбора CSV и BIN (`0` — по числу ядер)
+///
+/// Найденные овердрафты дополнительно печатаются в stderr.
+///
+/// Утилита сохранена для совместимости и выполняет подкоманду `ledger`
+/// единого CLI `data_parser`.
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct CliCommandLedger {
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    #[arg(short = 'f', long)]
+    pub input_format: Format,
+
+    #[arg(short = 'e', long, default_value = "csv")]
+    pub export_format: ExportFormat,
+
+    #[arg(long, default_value = "balances")]
+    pub report: Report,
+
+    #[arg(long)]
+    pub output: Option<String>,
+
+    #[arg(long, default_value_t = 1)]
+    pub threads: usize,
+}
+
+fn main() -> ExitCode {
+    let params: CliCommandLedger = CliCommandLedger::parse();
+
+    let style = match params.export_format {
+        ExportFormat::Csv => OutputFormat::Csv,
+        ExportFormat::Json => OutputFormat::Json,
+    };
+    let global = GlobalArgs {
+        input_formats: vec![params.input_format],
+        style,
+        threads: params.threads,
+        ..GlobalArgs::default()
+    };
+    let args = LedgerArgs {
+        input: params.input,
+        report: params.report,
+        output: params.output,
+    };
+
+    global.init_logging();
+    exit_code(ledger::run(&global, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_cli_ledger() {
+        let args = CliCommandLedger::parse_from([
+            "ledger",
+            "--input",
+            "a.csv",
+            "--input-format",
+            "csv",
+            "--export-format",
+            "json",
+            "--report",
+            "entries",
+        ]);
+
+        assert_eq!(args.input, "a.csv");
+        assert_eq!(args.input_format, Format::Csv);
+        assert_eq!(args.export_format, ExportFormat::Json);
+        assert_eq!(args.report, Report::Entries);
+        assert_eq!(args.output, None);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::CustomError;
+use crate::models::Record;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// Реквизиты пользователя: строка файла счетов `USER_ID,ACCOUNT,NAME,BIC`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct Account {
+    ///Идентификатор пользователя
+    pub user_id: u64,
+    ///Счёт: IBAN или внутренний номер
+    pub account: String,
+    ///Владелец счёта
+    #[serde(default)]
+    pub name: Option<String>,
+    ///BIC банка
+    #[serde(default)]
+    pub bic: Option<String>,
+}
+
+/// Справочник счетов: соответствие идентификаторов пользователей их реквизитам
+///
+/// Используется при выгрузке платёжных поручений pain.001, где у плательщика
+/// и получателя должны быть счета, а не идентификаторы пользователей.
+#[derive(Debug, Clone, Default)]
+pub struct AccountMap {
+    accounts: BTreeMap<u64, Account>,
+}
+
+impl AccountMap {
+    /// Читает файл счетов CSV с заголовком `USER_ID,ACCOUNT`
+    /// и необязательными колонками `NAME`, `BIC`
+    pub fn from_read<R: Read>(r: R) -> Result<Self, CustomError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(r);
+        let mut accounts = BTreeMap::new();
+        for account in reader.deserialize() {
+            let account: Account = account?;
+            accounts.insert(account.user_id, account);
+        }
+        Ok(AccountMap { accounts })
+    }
+
+    /// Реквизиты пользователя
+    pub fn get(&self, user_id: u64) -> Option<&Account> {
+        self.accounts.get(&user_id)
+    }
+
+    /// Записывает реквизиты отправителя и получателя каждой записи в поля
+    /// `DEBTOR_ACCOUNT`, `DEBTOR_NAME`, `DEBTOR_BIC` и `CREDITOR_*`.
+    ///
+    /// Пользователи, которых нет в справочнике, остаются без изменений.
+    pub fn apply(&self, records: &mut [Record]) {
+        for record in records {
+            let parties = [
+                ("DEBTOR", record.from_user_id),
+                ("CREDITOR", record.to_user_id),
+            ];
+            for (prefix, user_id) in parties {
+                let Some(account) = self.get(user_id) else {
+                    continue;
+                };
+                let fields = [
+                    ("ACCOUNT", Some(&account.account)),
+                    ("NAME", account.name.as_ref()),
+                    ("BIC", account.bic.as_ref()),
+                ];
+                for (key, value) in fields {
+                    if let Some(value) = value {
+                        record
+                            .extra
+                            .insert(format!("{}_{}", prefix, key), value.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromIterator<Account> for AccountMap {
+    fn from_iter<I: IntoIterator<Item = Account>>(iter: I) -> Self {
+        AccountMap {
+            accounts: iter.into_iter().map(|a| (a.user_id, a)).collect(),
+        }
+    }
+}
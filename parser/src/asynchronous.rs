@@ -0,0 +1,353 @@
+use crate::error::CustomError;
+use crate::format::bin::{YPBN, decode_record};
+use crate::format::csv::{CsvLayout, extra_columns, fields, header};
+use crate::format::txt::{TxtFormat, check_extra};
+use crate::models::{Format, Record};
+use futures_core::Stream;
+use futures_util::{StreamExt, stream};
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Асинхронный поток записей
+pub type RecordStream<'a> = Pin<Box<dyn Stream<Item = Result<Record, CustomError>> + Send + 'a>>;
+
+/// Источник, отдающий записи по одной
+trait RecordSource {
+    /// Следующая запись; `Ok(None)` означает конец потока
+    async fn next_record(&mut self) -> Result<Option<Record>, CustomError>;
+
+    /// Можно ли читать дальше после этой ошибки
+    fn recoverable(&self, error: &CustomError) -> bool;
+}
+
+/// Превращает источник в поток, завершая его на первой неустранимой ошибке
+fn into_stream<S: RecordSource>(source: S) -> impl Stream<Item = Result<Record, CustomError>> {
+    stream::unfold(Some(source), |source| async move {
+        let mut source = source?;
+        match source.next_record().await {
+            Ok(Some(record)) => Some((Ok(record), Some(source))),
+            Ok(None) => None,
+            Err(e) if source.recoverable(&e) => Some((Err(e), Some(source))),
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+/// Асинхронное чтение CSV: аналог `CsvRecords` поверх [`AsyncRead`].
+///
+/// Первая строка — заголовок. Запись может занимать несколько строк, если
+/// перевод строки стоит внутри кавычек.
+pub fn csv_records<R: AsyncRead + Unpin>(r: R) -> impl Stream<Item = Result<Record, CustomError>> {
+    into_stream(CsvSource {
+        reader: BufReader::new(r),
+        layout: None,
+    })
+}
+
+/// Асинхронное чтение TXT: аналог `TxtRecords` поверх [`AsyncRead`]
+pub fn txt_records<R: AsyncRead + Unpin>(r: R) -> impl Stream<Item = Result<Record, CustomError>> {
+    into_stream(TxtSource {
+        lines: BufReader::new(r).lines(),
+    })
+}
+
+/// Асинхронное чтение BIN: аналог `BinRecords` поверх [`AsyncRead`].
+///
+/// Как и синхронный вариант, завершается после первой ошибки.
+pub fn bin_records<R: AsyncRead + Unpin>(r: R) -> impl Stream<Item = Result<Record, CustomError>> {
+    into_stream(BinSource { reader: r })
+}
+
+/// Асинхронный поток записей для указанного формата.
+///
+/// Документ camt.053 сначала читается целиком, затем разбирается.
+pub fn records<'a, R: AsyncRead + Unpin + Send + 'a>(format: Format, r: R) -> RecordStream<'a> {
+    match format {
+        Format::Csv => Box::pin(csv_records(r)),
+        Format::Txt => Box::pin(txt_records(r)),
+        Format::Bin => Box::pin(bin_records(r)),
+        _ => Box::pin(
+            stream::once(async move {
+                let mut r = r;
+                let mut data = Vec::new();
+                match r.read_to_end(&mut data).await {
+                    Ok(_) => crate::format::records(format, data.as_slice(), &Default::default())
+                        .collect(),
+                    Err(e) => vec![Err(e.into())],
+                }
+            })
+            .flat_map(stream::iter),
+        ),
+    }
+}
+
+/// Записывает поток записей в [`AsyncWrite`] в указанном формате.
+///
+/// Каждая запись кодируется теми же правилами, что и синхронный
+/// `write_to()`, и сразу отправляется в приёмник. Возвращает количество
+/// записанных записей; на первой ошибке потока запись прекращается.
+///
+/// Остальные форматы (camt.053, pain.001, OFX, QIF) — единые документы,
+/// поэтому их записи сначала собираются целиком; выписки OFX и QIF без
+/// пользователя не пишутся, для них есть синхронный [`crate::write_records_with`].
+///
+/// Дополнительные колонки CSV берутся из `extra` первой записи: поток не
+/// читается заранее, поэтому ключи, появившиеся позже, в CSV не попадут.
+pub async fn write_records<W, S>(
+    format: Format,
+    records: S,
+    writer: &mut W,
+) -> Result<usize, CustomError>
+where
+    W: AsyncWrite + Unpin,
+    S: Stream<Item = Result<Record, CustomError>>,
+{
+    let mut records = std::pin::pin!(records);
+    let mut count = 0;
+    let mut buf = Vec::new();
+    let mut extra = Vec::new();
+
+    if !matches!(format, Format::Csv | Format::Txt | Format::Bin) {
+        let mut all = Vec::new();
+        while let Some(record) = records.next().await {
+            all.push(record?);
+        }
+        count = all.len();
+        crate::write_records(format, all, &mut buf)?;
+        writer.write_all(&buf).await?;
+        writer.flush().await?;
+        return Ok(count);
+    }
+
+    while let Some(record) = records.next().await {
+        let record = record?;
+        buf.clear();
+        match format {
+            Format::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(&mut buf);
+                if count == 0 {
+                    extra = extra_columns([&record]);
+                    csv_writer.write_record(header(&extra)?)?;
+                }
+                csv_writer.write_record(fields(&record, &extra))?;
+                csv_writer.flush()?;
+            }
+            Format::Txt => {
+                use std::io::Write;
+                check_extra(&record)?;
+                writeln!(buf, "# Record {} ({:?})", count + 1, record.tx_type)?;
+                writeln!(buf, "{}", record)?;
+            }
+            Format::Bin => crate::write_records(Format::Bin, vec![record], &mut buf)?,
+            _ => unreachable!("документ записывается целиком"),
+        }
+        writer.write_all(&buf).await?;
+        count += 1;
+    }
+
+    writer.flush().await?;
+    Ok(count)
+}
+
+struct CsvSource<R> {
+    reader: BufReader<R>,
+    layout: Option<CsvLayout>,
+}
+
+impl<R: AsyncRead + Unpin> CsvSource<R> {
+    /// Читает одну логическую строку CSV: до перевода строки вне кавычек
+    async fn read_row(&mut self) -> Result<Option<csv::StringRecord>, CustomError> {
+        let mut line = Vec::new();
+        loop {
+            let start = line.len();
+            if self.reader.read_until(b'\n', &mut line).await? == start {
+                break;
+            }
+            let quotes = line.iter().filter(|b| **b == b'"').count();
+            if quotes % 2 == 0 {
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    line.clear();
+                    continue;
+                }
+                break;
+            }
+        }
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            return Ok(None);
+        }
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(line.as_slice());
+        let mut row = csv::StringRecord::new();
+        reader.read_record(&mut row)?;
+        Ok(Some(row))
+    }
+}
+
+impl<R: AsyncRead + Unpin> RecordSource for CsvSource<R> {
+    async fn next_record(&mut self) -> Result<Option<Record>, CustomError> {
+        if self.layout.is_none() {
+            match self.read_row().await? {
+                Some(headers) => self.layout = Some(CsvLayout::new(headers)),
+                None => return Ok(None),
+            }
+        }
+
+        match (self.read_row().await?, &self.layout) {
+            (Some(row), Some(layout)) => layout.record(&row).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn recoverable(&self, error: &CustomError) -> bool {
+        !matches!(error, CustomError::Io(_))
+    }
+}
+
+struct TxtSource<R> {
+    lines: tokio::io::Lines<BufReader<R>>,
+}
+
+impl<R: AsyncRead + Unpin> RecordSource for TxtSource<R> {
+    async fn next_record(&mut self) -> Result<Option<Record>, CustomError> {
+        let mut current_block_data: HashMap<String, String> = HashMap::new();
+
+        while let Some(line) = self.lines.next_line().await? {
+            if !line.starts_with('#') && !line.is_empty() {
+                if let Some((k, v)) = line.split_once(':') {
+                    current_block_data.insert(k.trim().to_string(), v.trim().to_string());
+                }
+            } else if !current_block_data.is_empty() {
+                return TxtFormat::created_record(current_block_data).map(Some);
+            }
+        }
+
+        if current_block_data.is_empty() {
+            Ok(None)
+        } else {
+            TxtFormat::created_record(current_block_data).map(Some)
+        }
+    }
+
+    fn recoverable(&self, error: &CustomError) -> bool {
+        !matches!(error, CustomError::Io(_))
+    }
+}
+
+struct BinSource<R> {
+    reader: R,
+}
+
+impl<R: AsyncRead + Unpin> RecordSource for BinSource<R> {
+    async fn next_record(&mut self) -> Result<Option<Record>, CustomError> {
+        let mut header = [0u8; 8];
+        if self.reader.read(&mut header[..1]).await? == 0 {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut header[1..]).await?;
+
+        if header[..4] != YPBN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Неверное магическое число заголовка",
+            )
+            .into());
+        }
+
+        // длина из заголовка не проверена: тело читается без выделения памяти заранее
+        let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        let mut body = Vec::new();
+        (&mut self.reader).take(size).read_to_end(&mut body).await?;
+        if (body.len() as u64) < size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Тело кадра короче заголовка: {} из {} байт",
+                    body.len(),
+                    size
+                ),
+            )
+            .into());
+        }
+
+        decode_record(&body).map(Some)
+    }
+
+    fn recoverable(&self, _error: &CustomError) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn sample(n: u64) -> Vec<Record> {
+        (1..=n)
+            .map(|i| Record {
+                tx_id: i,
+                tx_type: TxType::WITHDRAWAL,
+                from_user_id: i,
+                to_user_id: 0,
+                amount: i * 10,
+                timestamp: 1_000 + i,
+                status: Status::PENDING,
+                description: format!("record {}, \"quoted\" text", i),
+                extra: BTreeMap::new(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn async_round_trip_matches_sync() -> Result<(), CustomError> {
+        let expected = sample(3);
+
+        for format in [Format::Csv, Format::Txt, Format::Bin] {
+            let mut sync_buf = Vec::new();
+            crate::write_records(format.clone(), expected.clone(), &mut sync_buf)?;
+
+            let mut async_buf = Vec::new();
+            let source = stream::iter(expected.clone().into_iter().map(Ok));
+            let written = write_records(format.clone(), source, &mut async_buf).await?;
+            assert_eq!(written, 3);
+
+            let parsed: Vec<Result<Record, CustomError>> =
+                records(format.clone(), async_buf.as_slice())
+                    .collect()
+                    .await;
+            let parsed = parsed.into_iter().collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(parsed, expected, "{:?}", format);
+            assert_eq!(async_buf, sync_buf, "{:?}", format);
+        }
+
+        let data = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION\n\
+                    1,DEPOSIT,0,10,100,1000,SUCCESS,\"two\nlines\"\n\
+                    \n\
+                    2,BOGUS,0,10,100,1000,SUCCESS,\"b\"\n";
+        let parsed: Vec<_> = csv_records(data.as_bytes()).collect().await;
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].as_ref().unwrap().description, "two\nlines");
+        assert!(parsed[1].is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_bin_rejects_truncated_frame() {
+        let mut data = YPBN.to_vec();
+        data.extend(u32::MAX.to_be_bytes());
+        data.extend([0u8; 16]);
+
+        let parsed: Vec<_> = records(Format::Bin, data.as_slice()).collect().await;
+        assert_eq!(parsed.len(), 1);
+        match &parsed[0] {
+            Err(CustomError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+            other => panic!("Expected UnexpectedEof, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,657 @@
+use crate::error::CustomError;
+use crate::models::Record;
+use clap::ValueEnum;
+use serde::{Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Поле [`Record`], участвующее в сравнении
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    ///Поле TX_ID
+    #[value(name = "tx_id")]
+    TxId,
+    ///Поле TX_TYPE
+    #[value(name = "tx_type")]
+    TxType,
+    ///Поле FROM_USER_ID
+    #[value(name = "from_user_id")]
+    FromUserId,
+    ///Поле TO_USER_ID
+    #[value(name = "to_user_id")]
+    ToUserId,
+    ///Поле AMOUNT
+    #[value(name = "amount")]
+    Amount,
+    ///Поле TIMESTAMP
+    #[value(name = "timestamp")]
+    Timestamp,
+    ///Поле STATUS
+    #[value(name = "status")]
+    Status,
+    ///Поле DESCRIPTION
+    #[value(name = "description")]
+    Description,
+}
+
+impl Field {
+    /// Все поля в порядке следования в [`Record`]
+    pub const ALL: [Field; 8] = [
+        Field::TxId,
+        Field::TxType,
+        Field::FromUserId,
+        Field::ToUserId,
+        Field::Amount,
+        Field::Timestamp,
+        Field::Status,
+        Field::Description,
+    ];
+
+    /// Имя поля так, как оно записывается в файлах
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::TxId => "TX_ID",
+            Field::TxType => "TX_TYPE",
+            Field::FromUserId => "FROM_USER_ID",
+            Field::ToUserId => "TO_USER_ID",
+            Field::Amount => "AMOUNT",
+            Field::Timestamp => "TIMESTAMP",
+            Field::Status => "STATUS",
+            Field::Description => "DESCRIPTION",
+        }
+    }
+
+    /// Значение поля записи в текстовом виде
+    pub fn value(&self, record: &Record) -> String {
+        match self {
+            Field::TxId => record.tx_id.to_string(),
+            Field::TxType => format!("{:?}", record.tx_type),
+            Field::FromUserId => record.from_user_id.to_string(),
+            Field::ToUserId => record.to_user_id.to_string(),
+            Field::Amount => record.amount.to_string(),
+            Field::Timestamp => record.timestamp.to_string(),
+            Field::Status => format!("{:?}", record.status),
+            Field::Description => record.description.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// Нормализация значения перед сравнением
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalizer {
+    /// Убрать пробелы по краям (`description`)
+    Trim,
+    /// Сравнивать без учёта регистра (`description`)
+    CaseFold,
+    /// Отбросить миллисекунды (`timestamp`)
+    RoundSeconds,
+}
+
+/// Нормализация, привязанная к полю, в виде `поле:нормализация`,
+/// например `description:trim` или `timestamp:round-seconds`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldNormalizer {
+    ///поле, к которому применяется нормализация
+    pub field: Field,
+    ///сама нормализация
+    pub normalizer: Normalizer,
+}
+
+impl FromStr for FieldNormalizer {
+    type Err = CustomError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CustomError::InvalidEnum(format!("Неверная нормализация: {}", s));
+
+        let (field, normalizer) = s.split_once(':').ok_or_else(invalid)?;
+        let field = Field::from_str(field, true).map_err(|_| invalid())?;
+        let normalizer = match (field, normalizer) {
+            (Field::Description, "trim") => Normalizer::Trim,
+            (Field::Description, "case-fold") => Normalizer::CaseFold,
+            (Field::Timestamp, "round-seconds") => Normalizer::RoundSeconds,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self { field, normalizer })
+    }
+}
+
+/// Расхождение одного поля
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    ///поле
+    pub field: Field,
+    ///исходное значение в первом файле
+    pub old: String,
+    ///исходное значение во втором файле
+    pub new: String,
+}
+
+/// Расхождение пары записей (или отсутствие записи с одной из сторон)
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RecordDiff {
+    ///позиция записи (для позиционного сравнения) или её `tx_id` (для сравнения по ключу)
+    pub key: u64,
+    ///вид расхождения
+    pub kind: DiffKind,
+    ///запись левого файла
+    pub left: Option<Record>,
+    ///запись правого файла
+    pub right: Option<Record>,
+    ///расходящиеся поля; пусто, если записи нет с одной из сторон
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Вид расхождения пары записей
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffKind {
+    /// Запись есть в обоих файлах, но поля расходятся
+    Changed,
+    /// Запись есть только в первом файле
+    OnlyLeft,
+    /// Запись есть только во втором файле
+    OnlyRight,
+}
+
+impl DiffKind {
+    fn of(left: Option<&Record>, right: Option<&Record>) -> Self {
+        match (left, right) {
+            (Some(_), None) => DiffKind::OnlyLeft,
+            (None, Some(_)) => DiffKind::OnlyRight,
+            _ => DiffKind::Changed,
+        }
+    }
+}
+
+impl fmt::Display for DiffKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DiffKind::Changed => "changed",
+            DiffKind::OnlyLeft => "only-left",
+            DiffKind::OnlyRight => "only-right",
+        })
+    }
+}
+
+/// Какие поля сравнивать и как их нормализовать.
+///
+/// По умолчанию сравниваются все поля без нормализации, что эквивалентно
+/// точному равенству `Record`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareOptions {
+    fields: Vec<Field>,
+    normalizers: Vec<FieldNormalizer>,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            fields: Field::ALL.to_vec(),
+            normalizers: Vec::new(),
+        }
+    }
+}
+
+impl CompareOptions {
+    /// Собирает настройки: `only` — белый список (пустой означает все поля),
+    /// `ignore` — поля, исключаемые из сравнения.
+    pub fn new(only: &[Field], ignore: &[Field], normalizers: Vec<FieldNormalizer>) -> Self {
+        let fields = Field::ALL
+            .into_iter()
+            .filter(|field| only.is_empty() || only.contains(field))
+            .filter(|field| !ignore.contains(field))
+            .collect();
+
+        Self {
+            fields,
+            normalizers,
+        }
+    }
+
+    /// Поля, участвующие в сравнении
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    fn normalizes(&self, field: Field, normalizer: Normalizer) -> bool {
+        self.normalizers
+            .iter()
+            .any(|n| n.field == field && n.normalizer == normalizer)
+    }
+
+    /// `timestamp` после нормализации
+    pub fn timestamp(&self, record: &Record) -> u64 {
+        if self.normalizes(Field::Timestamp, Normalizer::RoundSeconds) {
+            record.timestamp / 1000 * 1000
+        } else {
+            record.timestamp
+        }
+    }
+
+    /// `description` после нормализации
+    pub fn description<'a>(&self, record: &'a Record) -> Cow<'a, str> {
+        let description = if self.normalizes(Field::Description, Normalizer::Trim) {
+            record.description.trim()
+        } else {
+            record.description.as_str()
+        };
+
+        if self.normalizes(Field::Description, Normalizer::CaseFold) {
+            Cow::Owned(description.to_lowercase())
+        } else {
+            Cow::Borrowed(description)
+        }
+    }
+
+    /// Значение поля после нормализации
+    pub fn normalized(&self, field: Field, record: &Record) -> String {
+        match field {
+            Field::Timestamp => self.timestamp(record).to_string(),
+            Field::Description => self.description(record).into_owned(),
+            _ => field.value(record),
+        }
+    }
+
+    /// Поля, по которым записи расходятся с учётом настроек
+    pub fn diff(&self, left: &Record, right: &Record) -> Vec<FieldDiff> {
+        self.fields
+            .iter()
+            .filter(|field| self.normalized(**field, left) != self.normalized(**field, right))
+            .map(|field| FieldDiff {
+                field: *field,
+                old: field.value(left),
+                new: field.value(right),
+            })
+            .collect()
+    }
+
+    /// Совпадают ли записи с учётом настроек
+    pub fn equal(&self, left: &Record, right: &Record) -> bool {
+        self.diff(left, right).is_empty()
+    }
+}
+
+/// Позиционное сравнение: i-я запись левого файла с i-й записью правого.
+///
+/// Возвращает только расходящиеся позиции; лишние записи более длинного
+/// файла возвращаются как расхождения с отсутствующей парой.
+pub fn compare_positional(
+    left: &[Record],
+    right: &[Record],
+    options: &CompareOptions,
+) -> Vec<RecordDiff> {
+    let len = left.len().max(right.len());
+
+    (0..len)
+        .filter_map(|i| {
+            let (l, r) = (left.get(i), right.get(i));
+            let fields = match (l, r) {
+                (Some(l), Some(r)) => {
+                    let fields = options.diff(l, r);
+                    if fields.is_empty() {
+                        return None;
+                    }
+                    fields
+                }
+                _ => Vec::new(),
+            };
+
+            Some(RecordDiff {
+                key: i as u64,
+                kind: DiffKind::of(l, r),
+                left: l.cloned(),
+                right: r.cloned(),
+                fields,
+            })
+        })
+        .collect()
+}
+
+/// Сравнение по ключу `tx_id`: порядок записей в файлах не важен.
+///
+/// Расхождения возвращаются в порядке левого файла, затем записи,
+/// которые есть только в правом.
+pub fn compare_by_key(
+    left: &[Record],
+    right: &[Record],
+    options: &CompareOptions,
+) -> Vec<RecordDiff> {
+    let right_by_id: HashMap<u64, &Record> = right.iter().map(|r| (r.tx_id, r)).collect();
+    let left_ids: HashMap<u64, &Record> = left.iter().map(|l| (l.tx_id, l)).collect();
+
+    let mut diffs: Vec<RecordDiff> = left
+        .iter()
+        .filter_map(|l| {
+            let r = right_by_id.get(&l.tx_id).copied();
+            let fields = match r {
+                Some(r) => {
+                    let fields = options.diff(l, r);
+                    if fields.is_empty() {
+                        return None;
+                    }
+                    fields
+                }
+                None => Vec::new(),
+            };
+
+            Some(RecordDiff {
+                key: l.tx_id,
+                kind: DiffKind::of(Some(l), r),
+                left: Some(l.clone()),
+                right: r.cloned(),
+                fields,
+            })
+        })
+        .collect();
+
+    diffs.extend(
+        right
+            .iter()
+            .filter(|r| !left_ids.contains_key(&r.tx_id))
+            .map(|r| RecordDiff {
+                key: r.tx_id,
+                kind: DiffKind::OnlyRight,
+                left: None,
+                right: Some(r.clone()),
+                fields: Vec::new(),
+            }),
+    );
+
+    diffs
+}
+
+/// Формат документа с результатом сравнения
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    ///Человекочитаемый текст
+    #[value(name = "text")]
+    Text,
+    ///Формат JSON
+    #[value(name = "json")]
+    Json,
+    ///Формат CSV: одна строка на расходящееся поле
+    #[value(name = "csv")]
+    Csv,
+}
+
+/// Сводка сравнения
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DiffSummary {
+    ///имя первого файла
+    pub left_file: String,
+    ///имя второго файла
+    pub right_file: String,
+    ///количество записей в первом файле
+    pub left_records: usize,
+    ///количество записей во втором файле
+    pub right_records: usize,
+    ///`key` — сопоставление по `tx_id`, `positional` — по позиции
+    pub mode: &'static str,
+    ///поля, участвовавшие в сравнении
+    pub compared_fields: Vec<Field>,
+    ///файлы совпадают
+    pub identical: bool,
+    ///количество пар с расходящимися полями
+    pub changed: usize,
+    ///количество записей только в первом файле
+    pub only_left: usize,
+    ///количество записей только во втором файле
+    pub only_right: usize,
+}
+
+/// Документ с результатом сравнения: сводка и все расхождения
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    ///сводка
+    pub summary: DiffSummary,
+    ///расхождения по записям
+    pub differences: Vec<RecordDiff>,
+}
+
+impl DiffReport {
+    /// Сравнивает два набора записей и собирает документ
+    pub fn new(
+        left_file: &str,
+        left: &[Record],
+        right_file: &str,
+        right: &[Record],
+        options: &CompareOptions,
+        by_key: bool,
+    ) -> Self {
+        let differences = if by_key {
+            compare_by_key(left, right, options)
+        } else {
+            compare_positional(left, right, options)
+        };
+        let count = |kind: DiffKind| differences.iter().filter(|d| d.kind == kind).count();
+
+        Self {
+            summary: DiffSummary {
+                left_file: left_file.to_string(),
+                right_file: right_file.to_string(),
+                left_records: left.len(),
+                right_records: right.len(),
+                mode: if by_key { "key" } else { "positional" },
+                compared_fields: options.fields().to_vec(),
+                identical: differences.is_empty(),
+                changed: count(DiffKind::Changed),
+                only_left: count(DiffKind::OnlyLeft),
+                only_right: count(DiffKind::OnlyRight),
+            },
+            differences,
+        }
+    }
+
+    /// Записывает документ в выбранном формате
+    pub fn write_to<W: Write>(
+        &self,
+        format: OutputFormat,
+        writer: &mut W,
+    ) -> Result<(), CustomError> {
+        match format {
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, self)?;
+                writeln!(writer)?;
+            }
+            OutputFormat::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                csv_writer.write_record(["KEY", "KIND", "FIELD", "OLD", "NEW"])?;
+                for diff in &self.differences {
+                    let (key, kind) = (diff.key.to_string(), diff.kind.to_string());
+                    if diff.fields.is_empty() {
+                        csv_writer.write_record([key.as_str(), kind.as_str(), "", "", ""])?;
+                    }
+                    for field in &diff.fields {
+                        csv_writer.write_record([
+                            key.as_str(),
+                            kind.as_str(),
+                            field.field.name(),
+                            field.old.as_str(),
+                            field.new.as_str(),
+                        ])?;
+                    }
+                }
+                csv_writer.flush()?;
+            }
+            OutputFormat::Text => self.write_text(writer)?,
+        }
+        Ok(())
+    }
+
+    fn write_text<W: Write>(&self, writer: &mut W) -> Result<(), CustomError> {
+        let summary = &self.summary;
+        if summary.identical {
+            writeln!(
+                writer,
+                "The transaction records in '{}' and '{}' are identical.",
+                summary.left_file, summary.right_file
+            )?;
+            return Ok(());
+        }
+
+        writeln!(
+            writer,
+            "'{}' ({} records) and '{}' ({} records) differ: {} changed, {} only in '{}', {} only in '{}'.",
+            summary.left_file,
+            summary.left_records,
+            summary.right_file,
+            summary.right_records,
+            summary.changed,
+            summary.only_left,
+            summary.left_file,
+            summary.only_right,
+            summary.right_file
+        )?;
+
+        let key_name = if summary.mode == "key" {
+            "TX_ID"
+        } else {
+            "Position"
+        };
+        for diff in &self.differences {
+            writeln!(writer)?;
+            writeln!(writer, "{} {}: {}", key_name, diff.key, diff.kind)?;
+            for field in &diff.fields {
+                writeln!(
+                    writer,
+                    "  {}: {:?} -> {:?}",
+                    field.field, field.old, field.new
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn record(tx_id: u64, timestamp: u64, description: &str) -> Record {
+        Record {
+            tx_id,
+            tx_type: TxType::DEPOSIT,
+            from_user_id: 0,
+            to_user_id: 5,
+            amount: 10,
+            timestamp,
+            status: Status::SUCCESS,
+            description: description.to_string(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn compare_with_ignore_and_normalizers() {
+        let left = vec![record(1, 1_000_123, " Payment "), record(2, 2_000, "Rent")];
+        let right = vec![record(1, 1_000_000, "payment"), record(2, 2_000, "rent!")];
+
+        let strict = compare_positional(&left, &right, &CompareOptions::default());
+        assert_eq!(strict.len(), 2);
+        let fields: Vec<_> = strict[0].fields.iter().map(|d| d.field).collect();
+        assert_eq!(fields, vec![Field::Timestamp, Field::Description]);
+        assert_eq!(strict[0].fields[0].old, "1000123");
+
+        let normalized = CompareOptions::new(
+            &[],
+            &[],
+            vec![
+                "description:trim".parse().unwrap(),
+                "description:case-fold".parse().unwrap(),
+                "timestamp:round-seconds".parse().unwrap(),
+            ],
+        );
+        let diffs = compare_positional(&left, &right, &normalized);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].key, 1);
+
+        let ignored = CompareOptions::new(&[], &[Field::Description, Field::Timestamp], vec![]);
+        assert!(compare_positional(&left, &right, &ignored).is_empty());
+
+        let only_ids = CompareOptions::new(&[Field::TxId, Field::Amount], &[], vec![]);
+        assert_eq!(only_ids.fields(), &[Field::TxId, Field::Amount]);
+        assert!(only_ids.equal(&left[0], &right[0]));
+
+        assert!("amount:trim".parse::<FieldNormalizer>().is_err());
+    }
+
+    #[test]
+    fn compare_by_key_ignores_order() {
+        let left = vec![record(1, 0, "a"), record(2, 0, "b"), record(3, 0, "c")];
+        let right = vec![record(4, 0, "d"), record(2, 0, "b"), record(1, 0, "A")];
+
+        let diffs = compare_by_key(&left, &right, &CompareOptions::default());
+        let keys: Vec<_> = diffs
+            .iter()
+            .map(|d| (d.key, d.left.is_some(), d.right.is_some()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![(1, true, true), (3, true, false), (4, false, true)]
+        );
+
+        let positional = compare_positional(&left[..1], &right, &CompareOptions::default());
+        assert_eq!(positional.len(), 3);
+        assert!(positional[2].left.is_none());
+    }
+
+    #[test]
+    fn diff_report_formats() -> Result<(), CustomError> {
+        let left = vec![record(1, 0, "a"), record(2, 0, "b")];
+        let right = vec![record(1, 0, "A")];
+
+        let report = DiffReport::new(
+            "left.csv",
+            &left,
+            "right.bin",
+            &right,
+            &CompareOptions::default(),
+            true,
+        );
+        assert!(!report.summary.identical);
+        assert_eq!(report.summary.changed, 1);
+        assert_eq!(report.summary.only_left, 1);
+
+        let mut json = Vec::new();
+        report.write_to(OutputFormat::Json, &mut json)?;
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["summary"]["mode"], "key");
+        assert_eq!(value["differences"][0]["kind"], "changed");
+        assert_eq!(value["differences"][0]["fields"][0]["field"], "DESCRIPTION");
+        assert_eq!(value["differences"][0]["fields"][0]["old"], "a");
+        assert_eq!(value["differences"][0]["fields"][0]["new"], "A");
+        assert_eq!(value["differences"][1]["right"], serde_json::Value::Null);
+
+        let mut csv_buf = Vec::new();
+        report.write_to(OutputFormat::Csv, &mut csv_buf)?;
+        assert_eq!(
+            String::from_utf8(csv_buf).unwrap(),
+            "KEY,KIND,FIELD,OLD,NEW\n1,changed,DESCRIPTION,a,A\n2,only-left,,,\n"
+        );
+
+        let same = DiffReport::new("a", &left, "b", &left, &CompareOptions::default(), false);
+        let mut text = Vec::new();
+        same.write_to(OutputFormat::Text, &mut text)?;
+        assert!(String::from_utf8(text).unwrap().contains("are identical"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::error::CustomError;
+use bzip2::bufread::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use clap::ValueEnum;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::ffi::OsString;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const BZIP2_MAGIC: [u8; 3] = *b"BZh";
+/// Начало первого блока bzip2 (или конца пустого потока) после `BZh1`..`BZh9`
+const BZIP2_BLOCK: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+const BZIP2_EOS: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+/// Алгоритм сжатия файла
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    ///Без сжатия
+    #[default]
+    #[value(name = "none")]
+    None,
+    ///Gzip, расширение `.gz`
+    #[value(name = "gzip")]
+    Gzip,
+    ///Zstandard, расширение `.zst`
+    #[value(name = "zstd")]
+    Zstd,
+    ///Bzip2, расширение `.bz2`
+    #[value(name = "bzip2")]
+    Bzip2,
+}
+
+impl Compression {
+    /// Расширение сжатого файла, без точки
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+            Compression::Bzip2 => Some("bz2"),
+        }
+    }
+
+    /// Определяет сжатие по последнему расширению файла (`a.csv.gz`)
+    pub fn from_path(path: &Path) -> Option<Compression> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Определяет сжатие по первым байтам потока
+    pub fn from_magic(bytes: &[u8]) -> Option<Compression> {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if bytes.starts_with(&ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else if bytes.starts_with(&BZIP2_MAGIC)
+            && matches!(bytes.get(3), Some(b'1'..=b'9'))
+            && bytes
+                .get(4..10)
+                .is_some_and(|block| block == BZIP2_BLOCK || block == BZIP2_EOS)
+        {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    /// Добавляет к пути расширение сжатия, если его там ещё нет
+    pub fn with_extension(&self, path: &Path) -> PathBuf {
+        match self.extension() {
+            Some(extension) if Compression::from_path(path) != Some(*self) => {
+                let mut name = OsString::from(path.as_os_str());
+                name.push(".");
+                name.push(extension);
+                PathBuf::from(name)
+            }
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+/// Оборачивает источник в распаковщик.
+///
+/// Сжатие определяется по магическим байтам в начале потока, а если они не
+/// распознаны — по `hint` (обычно [`Compression::from_path`]). Несжатый
+/// поток возвращается как есть.
+pub fn decoder<'a, R: BufRead + 'a>(
+    mut reader: R,
+    hint: Option<Compression>,
+) -> Result<Box<dyn Read + 'a>, CustomError> {
+    let detected = Compression::from_magic(reader.fill_buf()?).or(hint);
+
+    Ok(match detected.unwrap_or_default() {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+    })
+}
+
+/// Приёмник, сжимающий записываемые данные.
+///
+/// После записи нужно вызвать [`Encoder::finish`], чтобы дописать
+/// завершающие блоки архива и получить ошибку, если она возникнет.
+pub enum Encoder<W: Write> {
+    ///Без сжатия
+    Plain(W),
+    ///Gzip
+    Gzip(GzEncoder<W>),
+    ///Zstandard
+    Zstd(zstd::Encoder<'static, W>),
+    ///Bzip2
+    Bzip2(BzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Оборачивает приёмник уровнем сжатия по умолчанию
+    pub fn new(writer: W, compression: Compression) -> Result<Self, CustomError> {
+        Ok(match compression {
+            Compression::None => Encoder::Plain(writer),
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(writer, 0)?),
+            Compression::Bzip2 => {
+                Encoder::Bzip2(BzEncoder::new(writer, bzip2::Compression::default()))
+            }
+        })
+    }
+
+    /// Завершает архив и возвращает исходный приёмник
+    pub fn finish(self) -> Result<W, CustomError> {
+        let mut writer = match self {
+            Encoder::Plain(writer) => writer,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            Encoder::Bzip2(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn compressed_round_trip_is_detected_by_magic() -> Result<(), CustomError> {
+        let data = b"TX_ID,TX_TYPE\n1,DEPOSIT\n".repeat(100);
+
+        for compression in [
+            Compression::None,
+            Compression::Gzip,
+            Compression::Zstd,
+            Compression::Bzip2,
+        ] {
+            let mut encoder = Encoder::new(Vec::new(), compression)?;
+            encoder.write_all(&data)?;
+            let compressed = encoder.finish()?;
+
+            let expected = (compression != Compression::None).then_some(compression);
+            assert_eq!(Compression::from_magic(&compressed), expected);
+
+            let mut decoded = Vec::new();
+            decoder(BufReader::new(compressed.as_slice()), None)?.read_to_end(&mut decoded)?;
+            assert_eq!(decoded, data);
+        }
+
+        assert_eq!(
+            Compression::from_path(Path::new("a.csv.GZ")),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::Zstd.with_extension(Path::new("out/a.bin")),
+            PathBuf::from("out/a.bin.zst")
+        );
+        assert_eq!(
+            Compression::Zstd.with_extension(Path::new("a.bin.zst")),
+            PathBuf::from("a.bin.zst")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+use crate::error::CustomError;
+use crate::format::bin::{YPBN, transcode_body};
+use crate::models::Format;
+use crate::wire;
+use clap::ValueEnum;
+use encoding_rs::{IBM866, UTF_8, WINDOWS_1251};
+use std::borrow::Cow;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+/// Кодировка текста во входных и выходных файлах
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    ///UTF-8
+    #[default]
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    ///Windows-1251 (кириллица)
+    #[value(name = "windows-1251", alias = "cp1251")]
+    Windows1251,
+    ///CP866 (кириллица DOS)
+    #[value(name = "cp866", alias = "ibm866")]
+    Cp866,
+    ///ISO-8859-1
+    #[value(name = "latin-1", alias = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+impl Encoding {
+    /// Имя кодировки, как в `--encoding`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Windows1251 => "windows-1251",
+            Encoding::Cp866 => "cp866",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    /// Декодирует байты в строку; байты, недопустимые в кодировке, — ошибка
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, CustomError> {
+        let text = match self {
+            Encoding::Utf8 => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|e| CustomError::InvalidData(format!("Неверный UTF-8: {}", e)))?,
+            Encoding::Latin1 => encoding_rs::mem::decode_latin1(bytes),
+            _ => self
+                .codec()
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .ok_or_else(|| {
+                    CustomError::InvalidData(format!("Текст не в кодировке {}", self.name()))
+                })?,
+        };
+        Ok(text.into_owned())
+    }
+
+    /// Кодирует строку; символ, которого нет в кодировке, — ошибка
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, CustomError> {
+        let unmappable = || {
+            CustomError::InvalidData(format!(
+                "Текст не представим в кодировке {}: {:?}",
+                self.name(),
+                text
+            ))
+        };
+
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| unmappable()))
+                .collect(),
+            _ => match self.codec().encode(text) {
+                (_, _, true) => Err(unmappable()),
+                (bytes, _, false) => Ok(bytes.into_owned()),
+            },
+        }
+    }
+
+    fn codec(&self) -> &'static encoding_rs::Encoding {
+        match self {
+            Encoding::Windows1251 => WINDOWS_1251,
+            Encoding::Cp866 => IBM866,
+            Encoding::Utf8 | Encoding::Latin1 => UTF_8,
+        }
+    }
+}
+
+fn io_error(error: CustomError) -> io::Error {
+    match error {
+        CustomError::Io(e) => e,
+        other => io::Error::new(io::ErrorKind::InvalidData, other.to_string()),
+    }
+}
+
+/// Оборачивает источник в перекодировщик в UTF-8.
+///
+/// Для CSV и TXT поток перекодируется целиком; метка порядка байт (BOM)
+/// UTF-8 или UTF-16 распознаётся автоматически, удаляется и имеет приоритет
+/// над `encoding`. В BIN перекодируются только строки записей — описание и
+/// дополнительные поля. Поток в UTF-8 без BOM и форматы, где строки всегда
+/// в UTF-8 (см. [`Format::utf8_only`]), возвращаются как есть.
+pub fn decoder<'a, R: Read + 'a>(
+    reader: R,
+    format: &Format,
+    encoding: Encoding,
+) -> Result<Box<dyn Read + 'a>, CustomError> {
+    if format.utf8_only() {
+        return Ok(Box::new(reader));
+    }
+    if *format == Format::Bin {
+        return Ok(match encoding {
+            Encoding::Utf8 => Box::new(reader),
+            _ => Box::new(BinDecoder {
+                reader,
+                encoding,
+                frame: Vec::new(),
+                pos: 0,
+            }),
+        });
+    }
+
+    let mut reader = BufReader::new(reader);
+    let decoding = match encoding_rs::Encoding::for_bom(reader.fill_buf()?) {
+        Some((bom, len)) => {
+            reader.consume(len);
+            match bom == UTF_8 {
+                true => return Ok(Box::new(reader)),
+                false => TextDecoding::Decoder(bom.new_decoder_without_bom_handling()),
+            }
+        }
+        None => match encoding {
+            Encoding::Utf8 => return Ok(Box::new(reader)),
+            Encoding::Latin1 => TextDecoding::Latin1,
+            _ => TextDecoding::Decoder(encoding.codec().new_decoder_without_bom_handling()),
+        },
+    };
+
+    Ok(Box::new(TextDecoder {
+        reader,
+        decoding,
+        text: Vec::new(),
+        pos: 0,
+        done: false,
+    }))
+}
+
+enum TextDecoding {
+    Latin1,
+    Decoder(encoding_rs::Decoder),
+}
+
+/// Потоковое перекодирование текста в UTF-8
+struct TextDecoder<R> {
+    reader: BufReader<R>,
+    decoding: TextDecoding,
+    text: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> Read for TextDecoder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.text.len() {
+            if self.done {
+                return Ok(0);
+            }
+
+            let input = self.reader.fill_buf()?;
+            let last = input.is_empty();
+            self.pos = 0;
+            self.text.clear();
+            let read = match &mut self.decoding {
+                TextDecoding::Latin1 => {
+                    self.text
+                        .extend_from_slice(encoding_rs::mem::decode_latin1(input).as_bytes());
+                    input.len()
+                }
+                TextDecoding::Decoder(decoder) => {
+                    let capacity = decoder
+                        .max_utf8_buffer_length(input.len())
+                        .ok_or_else(|| io::Error::other("слишком большой блок"))?;
+                    self.text.resize(capacity, 0);
+                    let (_, read, written, _) = decoder.decode_to_utf8(input, &mut self.text, last);
+                    self.text.truncate(written);
+                    read
+                }
+            };
+            self.reader.consume(read);
+            self.done = last;
+        }
+
+        let n = out.len().min(self.text.len() - self.pos);
+        out[..n].copy_from_slice(&self.text[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Читает кадры BIN и отдаёт их со строками, перекодированными в UTF-8
+struct BinDecoder<R> {
+    reader: R,
+    encoding: Encoding,
+    frame: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> BinDecoder<R> {
+    fn next_frame(&mut self) -> Result<bool, CustomError> {
+        let Some(body) = wire::read_frame_body(&mut self.reader, &YPBN)? else {
+            return Ok(false);
+        };
+        let body = transcode_body(&body, |bytes| Ok(self.encoding.decode(bytes)?.into_bytes()))?;
+
+        self.frame.clear();
+        self.pos = 0;
+        self.frame.extend_from_slice(&YPBN);
+        self.frame
+            .extend_from_slice(&(body.len() as u32).to_be_bytes());
+        self.frame.extend_from_slice(&body);
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for BinDecoder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.frame.len() && !self.next_frame().map_err(io_error)? {
+            return Ok(0);
+        }
+
+        let n = out.len().min(self.frame.len() - self.pos);
+        out[..n].copy_from_slice(&self.frame[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Приёмник, перекодирующий записываемый UTF-8 в выбранную кодировку.
+///
+/// Как и [`decoder`], для CSV и TXT перекодирует весь текст, а для BIN —
+/// только строки внутри записей. После записи нужно вызвать
+/// [`EncodeWriter::finish`]: он проверит, что не осталось оборванных
+/// символов или записей.
+pub struct EncodeWriter<W: Write> {
+    writer: W,
+    format: Format,
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> EncodeWriter<W> {
+    /// Оборачивает приёмник для записи файла формата `format`
+    pub fn new(writer: W, format: Format, encoding: Encoding) -> Self {
+        Self {
+            writer,
+            format,
+            encoding,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Проверяет, что все данные записаны, и возвращает исходный приёмник
+    pub fn finish(mut self) -> Result<W, CustomError> {
+        self.drain()?;
+        if !self.pending.is_empty() {
+            return Err(CustomError::InvalidData(format!(
+                "Незавершённые данные в конце вывода: {} байт",
+                self.pending.len()
+            )));
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Перекодирует и отправляет всё, что уже можно: целые символы текста
+    /// или целые кадры BIN
+    fn drain(&mut self) -> Result<(), CustomError> {
+        if self.format == Format::Bin {
+            while let Some(len) = self.pending.get(4..8) {
+                let end = 8 + u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+                if self.pending.len() < end {
+                    break;
+                }
+                let body = transcode_body(&self.pending[8..end], |bytes| {
+                    self.encoding.encode(&Encoding::Utf8.decode(bytes)?)
+                })?;
+                self.writer.write_all(&self.pending[..4])?;
+                self.writer.write_all(&(body.len() as u32).to_be_bytes())?;
+                self.writer.write_all(&body)?;
+                self.pending.drain(..end);
+            }
+            return Ok(());
+        }
+
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(CustomError::InvalidData(format!("Неверный UTF-8: {}", e))),
+        };
+        let text = Encoding::Utf8.decode(&self.pending[..valid])?;
+        self.writer.write_all(&self.encoding.encode(&text)?)?;
+        self.pending.drain(..valid);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncodeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.encoding == Encoding::Utf8 || self.format.utf8_only() {
+            return self.writer.write(buf);
+        }
+        self.pending.extend_from_slice(buf);
+        self.drain().map_err(io_error)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Record, Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn record() -> Record {
+        Record {
+            tx_id: 7,
+            tx_type: TxType::DEPOSIT,
+            from_user_id: 0,
+            to_user_id: 3,
+            amount: 500,
+            timestamp: 1_000,
+            status: Status::SUCCESS,
+            description: "Пополнение счёта".to_string(),
+            extra: BTreeMap::from([("КАНАЛ".to_string(), "касса".to_string())]),
+        }
+    }
+
+    #[test]
+    fn legacy_encodings_round_trip() -> Result<(), CustomError> {
+        let records = vec![record()];
+
+        for encoding in [Encoding::Windows1251, Encoding::Cp866] {
+            for format in [Format::Csv, Format::Txt, Format::Bin] {
+                let mut writer = EncodeWriter::new(Vec::new(), format.clone(), encoding);
+                crate::write_records(format.clone(), records.clone(), &mut writer)?;
+                let encoded = writer.finish()?;
+                assert!(!encoded.windows(2).any(|w| w == "П".as_bytes()));
+
+                let reader = decoder(encoded.as_slice(), &format, encoding)?;
+                let parsed = crate::format::records(format.clone(), reader, &Default::default())
+                    .collect::<Result<Vec<_>, _>>()?;
+                assert_eq!(parsed, records, "{:?} {:?}", encoding, format);
+            }
+        }
+
+        let mut writer = EncodeWriter::new(Vec::new(), Format::Txt, Encoding::Latin1);
+        assert!(crate::write_records(Format::Txt, records, &mut writer).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bom_overrides_encoding() -> Result<(), CustomError> {
+        let csv = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION\n\
+                   1,DEPOSIT,0,10,100,1000,SUCCESS,Café\n";
+        let mut utf8 = vec![0xEF, 0xBB, 0xBF];
+        utf8.extend_from_slice(csv.as_bytes());
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(csv.encode_utf16().flat_map(u16::to_le_bytes));
+        let latin1 = encoding_rs::mem::encode_latin1_lossy(csv).into_owned();
+
+        for (bytes, encoding) in [
+            (utf8, Encoding::Windows1251),
+            (utf16, Encoding::Utf8),
+            (latin1, Encoding::Latin1),
+        ] {
+            let mut reader = decoder(bytes.as_slice(), &Format::Csv, encoding)?;
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            assert_eq!(text, csv);
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,15 @@
+#[cfg(feature = "columnar")]
+use arrow_schema::ArrowError;
 use csv::Error as CsvError;
+#[cfg(feature = "columnar")]
+use parquet::errors::ParquetError;
+use quick_xml::Error as XmlError;
+use quick_xml::events::attributes::AttrError;
+use rmp_serde::decode::Error as MsgpackDecodeError;
+use rmp_serde::encode::Error as MsgpackEncodeError;
+use rusqlite::Error as SqliteError;
+use rust_xlsxwriter::XlsxError;
+use serde_json::Error as JsonError;
 use std::io::Error as IoError;
 use thiserror::Error;
 
@@ -13,6 +24,45 @@ pub enum CustomError {
     #[error("Ошибка чтения CSV: {0}")]
     Csv(#[from] CsvError),
 
+    #[error("Ошибка JSON: {0}")]
+    Json(#[from] JsonError),
+
+    #[error("Ошибка XML: {0}")]
+    Xml(#[from] XmlError),
+
+    #[error("Ошибка атрибута XML: {0}")]
+    XmlAttr(#[from] AttrError),
+
+    #[error("Ошибка чтения MessagePack: {0}")]
+    MsgpackDecode(#[from] MsgpackDecodeError),
+
+    #[error("Ошибка записи MessagePack: {0}")]
+    MsgpackEncode(#[from] MsgpackEncodeError),
+
+    #[error("Ошибка чтения CBOR: {0}")]
+    CborDecode(#[from] ciborium::de::Error<IoError>),
+
+    #[error("Ошибка записи CBOR: {0}")]
+    CborEncode(#[from] ciborium::ser::Error<IoError>),
+
+    #[error("Ошибка SQLite: {0}")]
+    Sqlite(#[from] SqliteError),
+
+    #[error("Ошибка записи XLSX: {0}")]
+    Xlsx(#[from] XlsxError),
+
+    #[cfg(feature = "columnar")]
+    #[error("Ошибка Arrow: {0}")]
+    Arrow(#[from] ArrowError),
+
+    #[cfg(feature = "columnar")]
+    #[error("Ошибка Parquet: {0}")]
+    Parquet(#[from] ParquetError),
+
+    #[cfg(feature = "postgres")]
+    #[error("Ошибка PostgreSQL: {}", postgres_message(.0))]
+    Postgres(#[from] postgres::Error),
+
     #[error("Неверные данные: {0}")]
     InvalidData(String),
 
@@ -25,3 +75,14 @@ pub enum CustomError {
     #[error("{0}")]
     InvalidEnum(String),
 }
+
+/// Сообщение сервера PostgreSQL или причина ошибки вместо общих
+/// `db error` и `error connecting to server`
+#[cfg(feature = "postgres")]
+fn postgres_message(e: &postgres::Error) -> String {
+    match (e.as_db_error(), std::error::Error::source(e)) {
+        (Some(db), _) => db.to_string(),
+        (None, Some(source)) => format!("{}: {}", e, source),
+        (None, None) => e.to_string(),
+    }
+}
@@ -1,8 +1,26 @@
 use crate::error::CustomError;
+use crate::models::{Format, FormatOptions, Record};
+use chrono::{DateTime, Utc};
+use std::io::{BufRead, BufReader, Read};
 
+pub mod bai2;
 pub mod bin;
+pub mod camt053;
+pub mod cbor;
+#[cfg(feature = "columnar")]
+pub mod columnar;
 pub mod csv;
+pub mod fixed;
+pub mod msgpack;
+pub mod ofx;
+pub mod pain001;
+pub mod pgcopy;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod qif;
+pub mod sqlite;
 pub mod txt;
+pub mod xlsx;
 
 /// Родительский trait для всех форматов файлов
 pub trait DataFormat
@@ -15,3 +33,151 @@ where
     // Записывает отчёт в любой приёмник, реализующий трейт Write
     fn write_to<W: std::io::Write>(&mut self, writer: &mut W) -> Result<(), CustomError>;
 }
+
+/// Итератор записей, читаемых из потока по одной
+pub type RecordIter<'a> = Box<dyn Iterator<Item = Result<Record, CustomError>> + 'a>;
+
+/// Потоковое чтение записей, идущих подряд без разделителей, по одной
+///
+/// Запись разбирает функция `decode`; после первой ошибки итератор
+/// заканчивается.
+pub struct StreamRecords<R: Read> {
+    reader: BufReader<R>,
+    decode: fn(&mut BufReader<R>) -> Result<Record, CustomError>,
+    failed: bool,
+}
+
+impl<R: Read> StreamRecords<R> {
+    /// Создаёт итератор записей поверх источника
+    pub fn new(reader: R, decode: fn(&mut BufReader<R>) -> Result<Record, CustomError>) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            decode,
+            failed: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for StreamRecords<R> {
+    type Item = Result<Record, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let next = match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => (self.decode)(&mut self.reader),
+            Err(e) => Err(e.into()),
+        };
+        self.failed = next.is_err();
+        Some(next)
+    }
+}
+
+/// Возвращает потоковый итератор записей для указанного формата.
+///
+/// Документы camt.053, BAI2, SQLite, Parquet и Arrow разбираются целиком при
+/// создании итератора, формат фиксированной ширины читается по раскладке
+/// `options.layout`.
+pub fn records<'a, R: Read + 'a>(
+    format: Format,
+    mut r: R,
+    options: &FormatOptions,
+) -> RecordIter<'a> {
+    match format {
+        Format::Csv => Box::new(csv::CsvRecords::new(r)),
+        Format::Txt => Box::new(txt::TxtRecords::new(r)),
+        Format::Bin => Box::new(bin::BinRecords::new(r)),
+        Format::Sqlite => match sqlite::SqliteFormat::read(&mut r, options) {
+            Ok(sqlite) => Box::new(sqlite.sqlite_rows.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        },
+        Format::Msgpack => Box::new(msgpack::records(r)),
+        Format::Cbor => Box::new(cbor::records(r)),
+        Format::Camt053 => match camt053::Camt053Format::from_read(&mut r) {
+            Ok(camt) => Box::new(camt.camt_rows.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        },
+        Format::Bai2 => match bai2::Bai2Format::from_read(&mut r) {
+            Ok(bai2) => Box::new(bai2.bai2_rows.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        },
+        #[cfg(feature = "columnar")]
+        Format::Parquet => match columnar::ParquetFormat::from_read(&mut r) {
+            Ok(parquet) => Box::new(parquet.parquet_rows.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        },
+        #[cfg(feature = "columnar")]
+        Format::Arrow => match columnar::ArrowFormat::from_read(&mut r) {
+            Ok(arrow) => Box::new(arrow.arrow_rows.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        },
+        Format::Fixed => match &options.layout {
+            Some(layout) => Box::new(fixed::FixedRecords::new(r, layout.clone())),
+            None => Box::new(std::iter::once(Err(fixed::missing_layout()))),
+        },
+        Format::Pain001
+        | Format::Ofx
+        | Format::OfxXml
+        | Format::Qif
+        | Format::Xlsx
+        | Format::PgCopy
+        | Format::PgCopyBinary => Box::new(std::iter::once(Err(write_only(&format)))),
+    }
+}
+
+/// Ошибка записи формата, который поддерживает только чтение
+pub(crate) fn read_only(format: &Format) -> CustomError {
+    CustomError::InvalidData(format!("формат {:?} поддерживает только чтение", format))
+}
+
+/// Ошибка чтения формата, который поддерживает только запись
+pub(crate) fn write_only(format: &Format) -> CustomError {
+    CustomError::InvalidData(format!("формат {:?} поддерживает только запись", format))
+}
+
+/// Время записи (миллисекунды Unix) в UTC; `TIMESTAMP` вне диапазона
+/// дат — ошибка, а не 1970-01-01
+pub(crate) fn utc_datetime(timestamp: u64) -> Result<DateTime<Utc>, CustomError> {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(DateTime::from_timestamp_millis)
+        .ok_or_else(|| {
+            CustomError::InvalidData(format!("TIMESTAMP {} вне диапазона дат", timestamp))
+        })
+}
+
+/// Записи для проверки потоковых форматов: UTF-8, `u64::MAX`, пустое
+/// описание и дополнительные поля
+#[cfg(test)]
+pub(crate) fn stream_records() -> Vec<Record> {
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    vec![
+        Record {
+            tx_id: 1,
+            tx_type: TxType::DEPOSIT,
+            from_user_id: 0,
+            to_user_id: u64::MAX,
+            amount: 100,
+            timestamp: 1_633_036_860_000,
+            status: Status::SUCCESS,
+            description: "Пополнение".to_string(),
+            extra: BTreeMap::new(),
+        },
+        Record {
+            tx_id: 2,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 7,
+            to_user_id: 8,
+            amount: 200,
+            timestamp: 1_633_036_920_000,
+            status: Status::PENDING,
+            description: String::new(),
+            extra: BTreeMap::from([("CHANNEL".to_string(), "WEB".to_string())]),
+        },
+    ]
+}
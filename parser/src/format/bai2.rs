@@ -0,0 +1,547 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::models::{Format, Record, Status, TxType};
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Коды деталей (`16`) межбанковских и внутренних переводов: 195/206/208 —
+/// входящие, 495/506/508 — исходящие
+const TRANSFER_CODES: [u16; 6] = [195, 206, 208, 495, 506, 508];
+
+/// Файл BAI2 (Cash Management Balance Reporting Specification, версия 2)
+///
+/// Формат только для чтения. Разбираются записи `01` (заголовок файла),
+/// `02` (группа), `03` (счёт), `16` (детали операции), `88` (продолжение
+/// предыдущей записи), `49`, `98` и `99` (итоги счёта, группы и файла).
+/// Контрольные суммы и количество записей в итогах сверяются с данными,
+/// расхождение — ошибка с номером строки.
+///
+/// Каждая запись `16` становится записью:
+/// - код операции 100–399 и 900–919 — поступление (`DEPOSIT`), 400–699 и
+///   920–999 — списание (`WITHDRAWAL`), коды переводов 195/206/208/495/506/508 —
+///   `TRANSFER`; код сохраняется в `TYPE_CODE`;
+/// - детали с неденежными кодами (например, 890) пропускаются, но их сумма
+///   входит в контрольную сумму счёта;
+/// - сумма — в минимальных единицах, как в файле;
+/// - номер счёта из `03` — получатель поступления или отправитель списания
+///   (если это число) и поле `ACCOUNT`, валюта счёта или группы — `CURRENCY`;
+/// - `TX_ID` — банковская ссылка, иначе ссылка клиента, если это число,
+///   иначе номер записи `16` в файле; нечисловые ссылки — в `BANK_REF`
+///   и `CUSTOMER_REF`;
+/// - время — дата и время группы (`as-of`), статус — `SUCCESS`;
+/// - текст (вместе с продолжениями `88`) — описание.
+#[derive(Debug)]
+pub struct Bai2Format {
+    pub bai2_rows: Vec<Record>,
+}
+
+impl DataFormat for Bai2Format {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        let mut text = String::new();
+        r.read_to_string(&mut text)?;
+
+        let mut parser = Parser::default();
+        for line in logical_lines(&text)? {
+            parser.line(&line)?;
+        }
+        if !parser.finished {
+            return Err(CustomError::InvalidData(
+                "BAI2: нет итоговой записи файла 99".to_string(),
+            ));
+        }
+        Ok(Bai2Format {
+            bai2_rows: parser.records,
+        })
+    }
+
+    fn write_to<W: Write>(&mut self, _writer: &mut W) -> Result<(), CustomError> {
+        Err(super::read_only(&Format::Bai2))
+    }
+}
+
+/// Логическая запись: физическая строка вместе с продолжениями `88`
+struct Line {
+    /// Номер первой физической строки
+    number: usize,
+    code: String,
+    body: String,
+    /// Число физических строк, учитываемое в итогах
+    physical: u64,
+}
+
+/// Склеивает записи `88` с предыдущей записью.
+///
+/// Если предыдущая строка закончилась `/`, продолжаются её поля, иначе —
+/// текст записи `16`, и части соединяются пробелом.
+fn logical_lines(text: &str) -> Result<Vec<Line>, CustomError> {
+    let mut lines: Vec<Line> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let raw = raw.trim_end();
+        if raw.is_empty() {
+            continue;
+        }
+        let (code, rest) = raw
+            .split_once(',')
+            .unwrap_or((raw.trim_end_matches('/'), ""));
+
+        if code != "88" {
+            lines.push(Line {
+                number: index + 1,
+                code: code.to_string(),
+                body: rest.to_string(),
+                physical: 1,
+            });
+            continue;
+        }
+        let Some(last) = lines.last_mut() else {
+            return Err(invalid(index + 1, "продолжение 88 без предыдущей записи"));
+        };
+        match last.body.strip_suffix('/') {
+            Some(fields) => last.body = format!("{},{}", fields, rest),
+            None => {
+                last.body.push(' ');
+                last.body.push_str(rest);
+            }
+        }
+        last.physical += 1;
+    }
+    Ok(lines)
+}
+
+/// Поля записи через запятую; `/` завершает запись
+struct Fields<'a> {
+    rest: Option<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(body: &'a str) -> Self {
+        Fields { rest: Some(body) }
+    }
+
+    /// Следующее поле, `None` после конца записи
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest?;
+        match rest.find([',', '/']) {
+            Some(i) if rest.as_bytes()[i] == b',' => {
+                self.rest = Some(&rest[i + 1..]);
+                Some(&rest[..i])
+            }
+            Some(i) => {
+                self.rest = None;
+                Some(&rest[..i])
+            }
+            None => {
+                self.rest = None;
+                Some(rest)
+            }
+        }
+    }
+
+    /// Следующее поле или пустое значение по умолчанию
+    fn field(&mut self) -> &'a str {
+        self.next().unwrap_or("").trim()
+    }
+
+    /// Остаток записи — текст `16`, который может содержать запятые
+    fn text(self) -> &'a str {
+        let text = self.rest.unwrap_or("").trim();
+        text.strip_suffix('/').unwrap_or(text).trim()
+    }
+
+    /// Пропускает поля доступности средств после кода `funds type`
+    fn skip_funds(&mut self, line: usize) -> Result<(), CustomError> {
+        let extra = match self.field() {
+            "" | "Z" | "0" | "1" | "2" => 0,
+            "V" => 2,
+            "S" => 3,
+            "D" => {
+                let count = self.field();
+                2 * count
+                    .parse::<usize>()
+                    .map_err(|_| invalid(line, &format!("число распределений {:?}", count)))?
+            }
+            other => {
+                return Err(invalid(
+                    line,
+                    &format!("неизвестный funds type {:?}", other),
+                ));
+            }
+        };
+        for _ in 0..extra {
+            self.field();
+        }
+        Ok(())
+    }
+}
+
+/// Итоги уровня (файл, группа, счёт), накопленные по записям
+#[derive(Default)]
+struct Totals {
+    control: i128,
+    records: u64,
+    children: u64,
+}
+
+impl Totals {
+    /// Сверяет итоговую запись `49`, `98` или `99` с накопленными итогами
+    fn verify(&self, line: &Line, level: &str, children: bool) -> Result<i128, CustomError> {
+        let mut fields = Fields::new(&line.body);
+        let control = amount(fields.field(), line.number)?;
+        let expected = |name: &str, declared: &str, actual: String| match declared == actual {
+            true => Ok(()),
+            false => Err(invalid(
+                line.number,
+                &format!(
+                    "{} {}: в итоге {}, по записям {}",
+                    name, level, declared, actual
+                ),
+            )),
+        };
+        expected(
+            "контрольная сумма",
+            &control.to_string(),
+            self.control.to_string(),
+        )?;
+        if children {
+            expected("число вложенных", fields.field(), self.children.to_string())?;
+        }
+        expected("число записей", fields.field(), self.records.to_string())?;
+        Ok(control)
+    }
+}
+
+/// Открытая группа `02`
+struct Group {
+    totals: Totals,
+    date: NaiveDate,
+    time: NaiveTime,
+    currency: String,
+}
+
+/// Открытый счёт `03`
+struct Account {
+    totals: Totals,
+    number: String,
+    currency: String,
+}
+
+/// Разбор логических записей по уровням файл → группа → счёт
+#[derive(Default)]
+struct Parser {
+    file: Option<Totals>,
+    group: Option<Group>,
+    account: Option<Account>,
+    finished: bool,
+    details: u64,
+    records: Vec<Record>,
+}
+
+impl Parser {
+    fn line(&mut self, line: &Line) -> Result<(), CustomError> {
+        let number = line.number;
+        if self.finished {
+            return Err(invalid(number, "запись после итога файла 99"));
+        }
+        if self.file.is_none() && line.code != "01" {
+            return Err(invalid(number, "файл должен начинаться с заголовка 01"));
+        }
+        let mut fields = Fields::new(&line.body);
+
+        match line.code.as_str() {
+            "01" => {
+                if self.file.is_some() {
+                    return Err(invalid(number, "повторный заголовок файла 01"));
+                }
+                for _ in 0..7 {
+                    fields.field();
+                }
+                let version = fields.field();
+                if version != "2" {
+                    return Err(invalid(
+                        number,
+                        &format!("версия BAI {:?}, ожидается 2", version),
+                    ));
+                }
+                self.file = Some(Totals::default());
+            }
+            "02" => {
+                if self.group.is_some() {
+                    return Err(invalid(number, "группа 02 внутри незакрытой группы"));
+                }
+                for _ in 0..3 {
+                    fields.field();
+                }
+                let date = fields.field();
+                let date = NaiveDate::parse_from_str(date, "%y%m%d")
+                    .map_err(|_| invalid(number, &format!("дата группы {:?}", date)))?;
+                let time = match fields.field() {
+                    "" => NaiveTime::MIN,
+                    // конец дня
+                    "2400" | "9999" => NaiveTime::from_hms_opt(23, 59, 0).unwrap_or_default(),
+                    time => NaiveTime::parse_from_str(time, "%H%M")
+                        .map_err(|_| invalid(number, &format!("время группы {:?}", time)))?,
+                };
+                self.group = Some(Group {
+                    totals: Totals::default(),
+                    date,
+                    time,
+                    currency: fields.field().to_string(),
+                });
+            }
+            "03" => {
+                if self.group.is_none() || self.account.is_some() {
+                    return Err(invalid(
+                        number,
+                        "счёт 03 вне группы или внутри другого счёта",
+                    ));
+                }
+                let mut account = Account {
+                    totals: Totals::default(),
+                    number: fields.field().to_string(),
+                    currency: fields.field().to_string(),
+                };
+                while let Some(type_code) = fields.next() {
+                    if type_code.trim().is_empty() && fields.rest.is_none() {
+                        break;
+                    }
+                    account.totals.control += amount(fields.field(), number)?;
+                    fields.field();
+                    fields.skip_funds(number)?;
+                }
+                self.account = Some(account);
+            }
+            "16" => {
+                let (control, record) = self.detail(line)?;
+                if let Some(account) = self.account.as_mut() {
+                    account.totals.control += control;
+                }
+                self.records.extend(record);
+            }
+            "49" | "98" | "99" => {}
+            other => {
+                return Err(invalid(
+                    number,
+                    &format!("неизвестный тип записи {:?}", other),
+                ));
+            }
+        }
+
+        self.count(line.physical);
+
+        match line.code.as_str() {
+            "49" => {
+                let account = self
+                    .account
+                    .take()
+                    .ok_or_else(|| invalid(number, "итог счёта 49 без записи 03"))?;
+                let control = account.totals.verify(line, "счёта", false)?;
+                if let Some(group) = self.group.as_mut() {
+                    group.totals.control += control;
+                    group.totals.children += 1;
+                }
+            }
+            "98" => {
+                if self.account.is_some() {
+                    return Err(invalid(number, "итог группы 98 внутри незакрытого счёта"));
+                }
+                let group = self
+                    .group
+                    .take()
+                    .ok_or_else(|| invalid(number, "итог группы 98 без записи 02"))?;
+                let control = group.totals.verify(line, "группы", true)?;
+                if let Some(file) = self.file.as_mut() {
+                    file.control += control;
+                    file.children += 1;
+                }
+            }
+            "99" => {
+                if self.group.is_some() {
+                    return Err(invalid(number, "итог файла 99 внутри незакрытой группы"));
+                }
+                if let Some(file) = &self.file {
+                    file.verify(line, "файла", true)?;
+                }
+                self.finished = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Добавляет физические строки к открытым уровням
+    fn count(&mut self, physical: u64) {
+        if let Some(file) = self.file.as_mut() {
+            file.records += physical;
+        }
+        if let Some(group) = self.group.as_mut() {
+            group.totals.records += physical;
+        }
+        if let Some(account) = self.account.as_mut() {
+            account.totals.records += physical;
+        }
+    }
+
+    /// Запись `16` в контексте открытых группы и счёта
+    /// Сумма для контрольной суммы счёта и запись, если код описывает операцию
+    fn detail(&mut self, line: &Line) -> Result<(i128, Option<Record>), CustomError> {
+        let number = line.number;
+        let (Some(group), Some(account)) = (&self.group, &self.account) else {
+            return Err(invalid(number, "детали 16 вне счёта 03"));
+        };
+        self.details += 1;
+
+        let mut fields = Fields::new(&line.body);
+        let type_code = fields.field();
+        let code: u16 = type_code
+            .parse()
+            .map_err(|_| CustomError::InvalidNumber(format!("type code (строка {})", number)))?;
+        let credit = matches!(code, 100..=399 | 900..=919);
+        let tx_type = match code {
+            _ if TRANSFER_CODES.contains(&code) => TxType::TRANSFER,
+            100..=399 | 900..=919 => TxType::DEPOSIT,
+            400..=699 | 920..=999 => TxType::WITHDRAWAL,
+            _ => {
+                return Ok((amount(fields.field(), number)?, None));
+            }
+        };
+        let value = fields.field();
+        let amount: u64 = value
+            .parse()
+            .map_err(|_| CustomError::InvalidNumber(format!("amount (строка {})", number)))?;
+        fields.skip_funds(number)?;
+        let bank_ref = fields.field();
+        let customer_ref = fields.field();
+        let description = fields.text().to_string();
+
+        let tx_id = [bank_ref, customer_ref]
+            .into_iter()
+            .find_map(|r| r.parse().ok())
+            .unwrap_or(self.details);
+        let user = account.number.parse().unwrap_or(0);
+
+        let mut extra = BTreeMap::new();
+        let currency = match account.currency.as_str() {
+            "" => group.currency.as_str(),
+            currency => currency,
+        };
+        let fields = [
+            ("CURRENCY", currency),
+            ("ACCOUNT", account.number.as_str()),
+            ("TYPE_CODE", type_code),
+            ("BANK_REF", bank_ref),
+            ("CUSTOMER_REF", customer_ref),
+        ];
+        for (key, value) in fields {
+            if !value.is_empty() && value != tx_id.to_string() {
+                extra.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        let record = Record {
+            tx_id,
+            tx_type,
+            from_user_id: if credit { 0 } else { user },
+            to_user_id: if credit { user } else { 0 },
+            amount,
+            timestamp: group.date.and_time(group.time).and_utc().timestamp_millis() as u64,
+            status: Status::SUCCESS,
+            description,
+            extra,
+        };
+        Ok((amount as i128, Some(record)))
+    }
+}
+
+/// Сумма со знаком; пустое поле — ноль
+fn amount(value: &str, line: usize) -> Result<i128, CustomError> {
+    match value {
+        "" => Ok(0),
+        value => value
+            .strip_prefix('+')
+            .unwrap_or(value)
+            .parse()
+            .map_err(|_| CustomError::InvalidNumber(format!("amount (строка {})", line))),
+    }
+}
+
+fn invalid(line: usize, message: &str) -> CustomError {
+    CustomError::InvalidData(format!("BAI2, строка {}: {}", line, message))
+}
+
+impl From<Vec<Record>> for Bai2Format {
+    fn from(records: Vec<Record>) -> Self {
+        Bai2Format { bai2_rows: records }
+    }
+}
+
+impl From<Bai2Format> for Vec<Record> {
+    fn from(bai2: Bai2Format) -> Self {
+        bai2.bai2_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = "\
+01,122099999,123456789,240301,1200,1,80,,2/
+02,123456789,122099999,1,240301,0800,USD,2/
+03,0975312468,,010,500000,,,015,-12345,,/
+88,100,3000,2,Z,400,1250,1,Z/
+16,195,1500,Z,991,CUST-1,Incoming wire from ACME, Inc./
+16,475,1250,0,,,Check 1001
+88,paid to landlord
+16,301,1500,V,240302,,REF7,,/
+16,890,,,,Account analysis statement follows/
+49,496155,8/
+98,496155,1,10/
+99,496155,1,12/
+";
+
+    #[test]
+    fn bai2_reads_details_with_continuations() -> Result<(), CustomError> {
+        let records = Bai2Format::from_read(&mut STATEMENT.as_bytes())?.bai2_rows;
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].tx_id, 991);
+        assert_eq!(records[0].tx_type, TxType::TRANSFER);
+        assert_eq!(records[0].to_user_id, 975_312_468);
+        assert_eq!(records[0].amount, 1500);
+        assert_eq!(records[0].timestamp, 1_709_280_000_000);
+        assert_eq!(records[0].description, "Incoming wire from ACME, Inc.");
+        assert_eq!(records[0].extra["CUSTOMER_REF"], "CUST-1");
+        assert_eq!(records[0].extra["CURRENCY"], "USD");
+        assert_eq!(records[0].extra["ACCOUNT"], "0975312468");
+
+        assert_eq!(records[1].tx_id, 2);
+        assert_eq!(records[1].tx_type, TxType::WITHDRAWAL);
+        assert_eq!(records[1].from_user_id, 975_312_468);
+        assert_eq!(records[1].description, "Check 1001 paid to landlord");
+
+        assert_eq!(records[2].tx_id, 3);
+        assert_eq!(records[2].tx_type, TxType::DEPOSIT);
+        assert_eq!(records[2].extra["BANK_REF"], "REF7");
+        assert_eq!(records[2].description, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn bai2_verifies_control_totals() {
+        for (from, to) in [
+            ("49,496155,8/", "49,496154,8/"),
+            ("49,496155,8/", "49,496155,7/"),
+            ("98,496155,1,10/", "98,496155,2,10/"),
+            ("99,496155,1,12/\n", ""),
+        ] {
+            let broken = STATEMENT.replace(from, to);
+            assert!(
+                Bai2Format::from_read(&mut broken.as_bytes()).is_err(),
+                "{}",
+                to
+            );
+        }
+    }
+}
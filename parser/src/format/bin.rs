@@ -1,7 +1,9 @@
 use crate::error::CustomError;
 use crate::format::DataFormat;
 use crate::models::{Record, Status, TxType};
-use std::io;
+use crate::wire;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::{Read, Write};
 
 /// Бинарный формат
@@ -11,113 +13,195 @@ pub struct BinFormat {
     pub bin_rows: Vec<Record>,
 }
 
-const YPBN: [u8; 4] = [0x59, 0x50, 0x42, 0x4E];
+pub(crate) const YPBN: [u8; 4] = [0x59, 0x50, 0x42, 0x4E];
 
 impl DataFormat for BinFormat {
     fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
-        let mut bin_rows: Vec<Record> = Vec::new();
+        let bin_rows = BinRecords::new(r).collect::<Result<Vec<Record>, _>>()?;
 
-        loop {
-            let mut magic_buf = [0u8; 4];
-            if r.read(&mut magic_buf)? == 0 {
-                break;
-            }
+        Ok(Self { bin_rows })
+    }
 
-            if magic_buf != YPBN {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Неверное магическое число заголовка",
-                )
-                .into());
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        for record in &self.bin_rows {
+            if record.extra.is_empty() {
+                wire::write_frame(writer, &YPBN, &BinBody::from(record))?;
+            } else {
+                wire::write_frame(writer, &YPBN, &(BinBody::from(record), &record.extra))?;
             }
+        }
 
-            let mut size_buf = [0u8; 4];
-            r.read_exact(&mut size_buf)?;
-            let record_size = u32::from_be_bytes(size_buf) as u64;
-
-            let mut record_body_reader = r.by_ref().take(record_size);
-
-            let mut buf_u64 = [0u8; 8];
-            record_body_reader.read_exact(&mut buf_u64)?;
-            let tx_id = u64::from_be_bytes(buf_u64);
-
-            let mut buf_u8 = [0u8; 1];
-            record_body_reader.read_exact(&mut buf_u8)?;
-            let tx_type_raw = buf_u8[0];
-
-            record_body_reader.read_exact(&mut buf_u64)?;
-            let from_user_id = u64::from_be_bytes(buf_u64);
-
-            record_body_reader.read_exact(&mut buf_u64)?;
-            let to_user_id = u64::from_be_bytes(buf_u64);
-
-            record_body_reader.read_exact(&mut buf_u64)?;
-            let amount = u64::from_be_bytes(buf_u64);
-
-            record_body_reader.read_exact(&mut buf_u64)?;
-            let timestamp = u64::from_be_bytes(buf_u64);
-
-            record_body_reader.read_exact(&mut buf_u8)?;
-            let status_raw = buf_u8[0];
-
-            record_body_reader.read_exact(&mut size_buf)?;
-            let desc_len = u32::from_be_bytes(size_buf) as usize;
+        Ok(())
+    }
+}
 
-            let mut description_buf = vec![0u8; desc_len];
-            record_body_reader.read_exact(&mut description_buf)?;
+/// Тело записи BIN: поля [`Record`] в порядке формата.
+///
+/// Кодируется сериализатором [`wire`]: числа — big-endian `u64`, типы и
+/// статусы — номер варианта `u8`, описание — длина `u32` и UTF-8 в
+/// двойных кавычках.
+///
+/// В версии 2 за телом может следовать блок расширений — словарь
+/// [`Record::extra`]: количество пар `u32`, затем ключи и значения строками.
+/// Записи без дополнительных полей пишутся без блока и совпадают с версией 1,
+/// а читатели версии 1 пропускают блок, так как длина кадра его учитывает.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct BinBody {
+    tx_id: u64,
+    tx_type: TxType,
+    from_user_id: u64,
+    to_user_id: u64,
+    amount: u64,
+    timestamp: u64,
+    status: Status,
+    description: String,
+}
 
-            let description_raw = String::from_utf8(description_buf).map_err(|e| {
-                io::Error::new(io::ErrorKind::InvalidData, format!("Неверный UTF-8: {}", e))
-            })?;
+impl From<&Record> for BinBody {
+    fn from(record: &Record) -> Self {
+        BinBody {
+            tx_id: record.tx_id,
+            tx_type: record.tx_type.clone(),
+            from_user_id: record.from_user_id,
+            to_user_id: record.to_user_id,
+            amount: record.amount,
+            timestamp: record.timestamp,
+            status: record.status.clone(),
+            description: format!("\"{}\"", record.description),
+        }
+    }
+}
 
-            let description = description_raw
+impl From<BinBody> for Record {
+    fn from(body: BinBody) -> Self {
+        Record {
+            tx_id: body.tx_id,
+            tx_type: body.tx_type,
+            from_user_id: body.from_user_id,
+            to_user_id: body.to_user_id,
+            amount: body.amount,
+            timestamp: body.timestamp,
+            status: body.status,
+            description: body
+                .description
                 .trim()
                 .trim_start_matches('"')
                 .trim_end_matches('"')
-                .to_string();
-
-            bin_rows.push(Record {
-                tx_id,
-                tx_type: TxType::from(tx_type_raw),
-                from_user_id,
-                to_user_id,
-                amount,
-                timestamp,
-                status: Status::from(status_raw),
-                description,
-            });
+                .to_string(),
+            extra: BTreeMap::new(),
         }
+    }
+}
 
-        Ok(Self { bin_rows })
+/// Длина полей тела до описания: пять `u64` и два номера варианта `u8`
+const FIXED_LEN: usize = 5 * 8 + 2;
+
+/// Перекодирует строки тела записи — описание и ключи/значения блока
+/// расширений — функцией `convert`, не трогая остальные поля.
+///
+/// Нужен слою кодировок ([`crate::encoding`]): числовые поля BIN не зависят
+/// от кодировки, а строки хранятся байтами с префиксом длины.
+pub(crate) fn transcode_body(
+    body: &[u8],
+    convert: impl Fn(&[u8]) -> Result<Vec<u8>, CustomError>,
+) -> Result<Vec<u8>, CustomError> {
+    fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], CustomError> {
+        if rest.len() < len {
+            return Err(CustomError::InvalidData(
+                "запись BIN обрывается посреди строки".to_string(),
+            ));
+        }
+        let (head, tail) = rest.split_at(len);
+        *rest = tail;
+        Ok(head)
+    }
+    fn length(rest: &mut &[u8]) -> Result<usize, CustomError> {
+        let bytes = take(rest, 4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
     }
 
-    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
-        for record in &self.bin_rows {
-            let quoted_description = format!("\"{}\"", record.description);
-            let description_bytes = quoted_description.as_bytes();
-            let desc_len = description_bytes.len() as u32;
+    fn string(
+        rest: &mut &[u8],
+        output: &mut Vec<u8>,
+        convert: &impl Fn(&[u8]) -> Result<Vec<u8>, CustomError>,
+    ) -> Result<(), CustomError> {
+        let len = length(rest)?;
+        let converted = convert(take(rest, len)?)?;
+        let len = u32::try_from(converted.len())
+            .map_err(|_| CustomError::InvalidData("строка BIN слишком длинная".to_string()))?;
+        output.extend_from_slice(&len.to_be_bytes());
+        output.extend_from_slice(&converted);
+        Ok(())
+    }
 
-            let record_body_size = 8 + 1 + 8 + 8 + 8 + 8 + 1 + 4 + desc_len as u64;
+    let mut rest = body;
+    let mut output = take(&mut rest, FIXED_LEN)?.to_vec();
+    string(&mut rest, &mut output, &convert)?;
 
-            writer.write_all(&YPBN)?;
+    if !rest.is_empty() {
+        let count = length(&mut rest)?;
+        output.extend_from_slice(&(count as u32).to_be_bytes());
+        for _ in 0..2 * count {
+            string(&mut rest, &mut output, &convert)?;
+        }
+    }
+    output.extend_from_slice(rest);
+    Ok(output)
+}
 
-            writer.write_all(&((record_body_size as u32).to_be_bytes()))?;
+/// Потоковое чтение BIN: отдаёт записи по одной.
+///
+/// После первой ошибки итератор завершается, так как позиция в потоке
+/// перестаёт указывать на начало записи.
+pub struct BinRecords<R: Read> {
+    reader: R,
+    failed: bool,
+}
 
-            writer.write_all(&record.tx_id.to_be_bytes())?;
-            writer.write_all(&[record.tx_type.clone() as u8])?;
-            writer.write_all(&record.from_user_id.to_be_bytes())?;
-            writer.write_all(&record.to_user_id.to_be_bytes())?;
-            writer.write_all(&record.amount.to_be_bytes())?;
-            writer.write_all(&record.timestamp.to_be_bytes())?;
-            writer.write_all(&[record.status.clone() as u8])?;
-            writer.write_all(&desc_len.to_be_bytes())?;
-            writer.write_all(description_bytes)?;
+impl<R: Read> BinRecords<R> {
+    /// Создаёт итератор записей поверх источника
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            failed: false,
         }
+    }
+}
 
-        Ok(())
+impl<R: Read> Iterator for BinRecords<R> {
+    type Item = Result<Record, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let next = read_record(&mut self.reader).transpose();
+        if let Some(Err(_)) = next {
+            self.failed = true;
+        }
+        next
     }
 }
 
+/// Читает одну запись вместе с блоком расширений; `Ok(None)` означает конец потока
+pub(crate) fn read_record<R: Read>(r: &mut R) -> Result<Option<Record>, CustomError> {
+    let Some(body) = wire::read_frame_body(r, &YPBN)? else {
+        return Ok(None);
+    };
+    decode_record(&body).map(Some)
+}
+
+/// Разбирает тело кадра: поля записи и необязательный блок расширений
+pub(crate) fn decode_record(body: &[u8]) -> Result<Record, CustomError> {
+    let mut deserializer = wire::Deserializer::from_slice(body);
+    let mut record = Record::from(BinBody::deserialize(&mut deserializer)?);
+    if !deserializer.is_empty() {
+        record.extra = BTreeMap::deserialize(&mut deserializer)?;
+    }
+    Ok(record)
+}
+
 impl From<Vec<Record>> for BinFormat {
     fn from(records: Vec<Record>) -> Self {
         BinFormat { bin_rows: records }
@@ -144,6 +228,7 @@ mod tests {
             tx_type: TxType::DEPOSIT,
             status: Status::FAILURE,
             description: "Record number 1".to_string(),
+            extra: BTreeMap::new(),
         }
     }
     #[test]
@@ -170,4 +255,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn bin_record_layout() -> Result<(), CustomError> {
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&YPBN);
+        expected.extend_from_slice(&63u32.to_be_bytes());
+        expected.extend_from_slice(&1u64.to_be_bytes());
+        expected.push(0);
+        expected.extend_from_slice(&10u64.to_be_bytes());
+        expected.extend_from_slice(&20u64.to_be_bytes());
+        expected.extend_from_slice(&100u64.to_be_bytes());
+        expected.extend_from_slice(&123456789u64.to_be_bytes());
+        expected.push(1);
+        expected.extend_from_slice(&17u32.to_be_bytes());
+        expected.extend_from_slice(b"\"Record number 1\"");
+
+        let mut buf = Vec::new();
+        BinFormat::from(vec![bin_record()]).write_to(&mut buf)?;
+        assert_eq!(buf, expected);
+
+        expected[8 + 8] = 7;
+        assert!(BinFormat::from_read(&mut expected.as_slice()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bin_extension_block_keeps_extra() -> Result<(), CustomError> {
+        let mut rec = bin_record();
+        rec.extra.insert("CHANNEL".to_string(), "web".to_string());
+
+        let mut buf = Vec::new();
+        BinFormat::from(vec![rec.clone()]).write_to(&mut buf)?;
+        assert_eq!(&buf[4..8], &(63u32 + 4 + 4 + 7 + 4 + 3).to_be_bytes());
+        assert_eq!(
+            BinFormat::from_read(&mut buf.as_slice())?.bin_rows,
+            vec![rec]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,669 @@
+use crate::error::CustomError;
+use crate::format::{DataFormat, utc_datetime};
+use crate::models::{Record, Status, TxType};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use quick_xml::escape::{escape, resolve_predefined_entity};
+use quick_xml::events::Event;
+use quick_xml::{Reader, XmlVersion};
+use std::collections::BTreeMap;
+use std::io::{BufReader, Read, Write};
+
+/// Пространство имён выписки, которую пишет [`Camt053Format`]
+pub const CAMT053_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:camt.053.001.02";
+/// Код валюты «без валюты» по ISO 4217: пишется, если у записи нет `CURRENCY`
+const NO_CURRENCY: &str = "XXX";
+/// Счёт выписки, если у записей нет `ACCOUNT`
+const NO_ACCOUNT: &str = "NOTPROVIDED";
+/// Наибольшая длина текста `Max140Text`, например `RmtInf/Ustrd`
+const MAX_TEXT: usize = 140;
+
+/// Выписка ISO 20022 camt.053 (BkToCstmrStmt)
+///
+/// Каждая проводка `<Ntry>` становится записью:
+/// - `Amt` — сумма в минимальных единицах (два знака после точки),
+///   атрибут `Ccy` — дополнительное поле `CURRENCY`;
+/// - `CdtDbtInd` — `CRDT` даёт `DEPOSIT`, `DBIT` — `WITHDRAWAL`, если тип не
+///   задан кодом `BkTxCd/Prtry/Cd`;
+/// - `Sts` — `BOOK`/`PDNG`/`INFO` соответствуют `SUCCESS`/`PENDING`/`FAILURE`;
+/// - `BookgDt` — время записи;
+/// - `EndToEndId` (или `NtryRef`) — `TX_ID`, если это число, иначе номер
+///   проводки в файле; нечисловые ссылки сохраняются в `extra`;
+/// - идентификаторы `Dbtr`/`Cdtr` из `RltdPties` — отправитель и получатель,
+///   при их отсутствии используется счёт выписки (`Acct`);
+/// - `RmtInf/Ustrd` (или `AddtlNtryInf`) — описание.
+///
+/// При записи проводки группируются в выписки `<Stmt>` по полям `ACCOUNT`
+/// и `CURRENCY`, у каждой выписки есть закрывающий баланс `CLBD` по
+/// проведённым записям. Описание длиннее 140 символов обрезается, пустое
+/// не пишется. `MsgId` составлен из времени создания и хеша записей;
+/// `TIMESTAMP` вне диапазона дат — ошибка. Документ всегда в UTF-8.
+#[derive(Debug)]
+pub struct Camt053Format {
+    pub camt_rows: Vec<Record>,
+}
+
+impl DataFormat for Camt053Format {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        // текст не обрезается на уровне событий: иначе пропадут пробелы вокруг
+        // `&amp;`; значение элемента обрезается целиком при его закрытии
+        let mut reader = Reader::from_reader(BufReader::new(r));
+
+        let mut camt_rows = Vec::new();
+        let mut path: Vec<String> = Vec::new();
+        let mut text = String::new();
+        let mut account: Option<String> = None;
+        let mut entry: Option<Entry> = None;
+        let mut buf = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(start) => {
+                    let name = start.local_name().as_ref().to_string();
+                    if name == "Ntry" {
+                        entry = Some(Entry::default());
+                    } else if name == "Amt"
+                        && path.last().is_some_and(|p| p == "Ntry")
+                        && let (Some(entry), Some(ccy)) =
+                            (entry.as_mut(), start.try_get_attribute("Ccy")?)
+                    {
+                        let ccy = ccy.normalized_value(XmlVersion::Implicit1_0)?;
+                        entry.currency = Some(ccy.into_owned());
+                    }
+                    path.push(name);
+                    text.clear();
+                }
+                Event::Text(content) => text.push_str(&content.xml10_content()),
+                Event::CData(content) => text.push_str(&content.xml10_content()),
+                Event::GeneralRef(reference) => match reference.resolve_char_ref()? {
+                    Some(c) => text.push(c),
+                    None => {
+                        text.push_str(resolve_predefined_entity(&reference).ok_or_else(|| {
+                            CustomError::InvalidData(format!(
+                                "неизвестная сущность &{};",
+                                &*reference
+                            ))
+                        })?)
+                    }
+                },
+                Event::End(_) => {
+                    let value = text.trim().to_string();
+                    text.clear();
+                    match entry.as_mut() {
+                        Some(entry) if path.iter().any(|p| p == "Ntry") => {
+                            if ends_with(&path, &["Ntry"]) {
+                                let entry = std::mem::take(entry);
+                                camt_rows.push(
+                                    entry.into_record(
+                                        camt_rows.len() as u64 + 1,
+                                        account.as_deref(),
+                                    )?,
+                                );
+                            } else {
+                                entry.set(&path, value);
+                            }
+                        }
+                        _ if ends_with(&path, &["Acct", "Id", "IBAN"])
+                            || ends_with(&path, &["Acct", "Id", "Othr", "Id"]) =>
+                        {
+                            account = Some(value);
+                        }
+                        _ => {}
+                    }
+                    if path.pop().as_deref() == Some("Stmt") {
+                        account = None;
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(Self { camt_rows })
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let mut statements: Vec<((&str, &str), Vec<&Record>)> = Vec::new();
+        for record in &self.camt_rows {
+            let account = record
+                .extra
+                .get("ACCOUNT")
+                .map_or(NO_ACCOUNT, String::as_str);
+            let key = (account, currency(record));
+            match statements.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, records)) => records.push(record),
+                None => statements.push((key, vec![record])),
+            }
+        }
+        let created = iso_datetime(
+            self.camt_rows
+                .iter()
+                .map(|r| r.timestamp)
+                .max()
+                .unwrap_or(0),
+        )?;
+        let rows: Vec<&Record> = self.camt_rows.iter().collect();
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<Document xmlns="{}">"#, CAMT053_NAMESPACE)?;
+        writeln!(writer, "  <BkToCstmrStmt>")?;
+        writeln!(writer, "    <GrpHdr>")?;
+        writeln!(
+            writer,
+            "      <MsgId>{}</MsgId>",
+            message_id("CAMT053", &created, &rows)?
+        )?;
+        writeln!(writer, "      <CreDtTm>{}</CreDtTm>", created)?;
+        writeln!(writer, "    </GrpHdr>")?;
+
+        for (n, ((account, currency), records)) in statements.iter().enumerate() {
+            let balance: i128 = records
+                .iter()
+                .filter(|r| r.status == Status::SUCCESS)
+                .map(|r| match credit_debit(r) {
+                    "CRDT" => r.amount as i128,
+                    _ => -(r.amount as i128),
+                })
+                .sum();
+            let last = records.iter().map(|r| r.timestamp).max().unwrap_or(0);
+
+            writeln!(writer, "    <Stmt>")?;
+            writeln!(writer, "      <Id>STMT-{}</Id>", n + 1)?;
+            writeln!(writer, "      <CreDtTm>{}</CreDtTm>", created)?;
+            writeln!(
+                writer,
+                "      <Acct><Id><Othr><Id>{}</Id></Othr></Id></Acct>",
+                escape(*account)
+            )?;
+            writeln!(writer, "      <Bal>")?;
+            writeln!(
+                writer,
+                "        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>"
+            )?;
+            writeln!(
+                writer,
+                r#"        <Amt Ccy="{}">{}</Amt>"#,
+                escape(*currency),
+                decimal(balance.unsigned_abs() as u64)
+            )?;
+            writeln!(
+                writer,
+                "        <CdtDbtInd>{}</CdtDbtInd>",
+                if balance < 0 { "DBIT" } else { "CRDT" }
+            )?;
+            writeln!(
+                writer,
+                "        <Dt><Dt>{}</Dt></Dt>",
+                &iso_datetime(last)?[..10]
+            )?;
+            writeln!(writer, "      </Bal>")?;
+            for record in records {
+                write_entry(writer, record)?;
+            }
+            writeln!(writer, "    </Stmt>")?;
+        }
+
+        writeln!(writer, "  </BkToCstmrStmt>")?;
+        writeln!(writer, "</Document>")?;
+        Ok(())
+    }
+}
+
+fn write_entry<W: Write>(writer: &mut W, record: &Record) -> Result<(), CustomError> {
+    let extra = |key: &str| record.extra.get(key).map(|v| escape(v.as_str()));
+    let ntry_ref = extra("NTRY_REF").unwrap_or_else(|| record.tx_id.to_string().into());
+    let end_to_end = extra("END_TO_END_ID").unwrap_or_else(|| record.tx_id.to_string().into());
+
+    writeln!(writer, "      <Ntry>")?;
+    writeln!(writer, "        <NtryRef>{}</NtryRef>", ntry_ref)?;
+    writeln!(
+        writer,
+        r#"        <Amt Ccy="{}">{}</Amt>"#,
+        escape(currency(record)),
+        decimal(record.amount)
+    )?;
+    writeln!(
+        writer,
+        "        <CdtDbtInd>{}</CdtDbtInd>",
+        credit_debit(record)
+    )?;
+    writeln!(
+        writer,
+        "        <Sts>{}</Sts>",
+        match record.status {
+            Status::SUCCESS => "BOOK",
+            Status::PENDING => "PDNG",
+            Status::FAILURE => "INFO",
+        }
+    )?;
+    writeln!(
+        writer,
+        "        <BookgDt><DtTm>{}</DtTm></BookgDt>",
+        iso_datetime(record.timestamp)?
+    )?;
+    if let Some(reference) = extra("ACCT_SVCR_REF") {
+        writeln!(writer, "        <AcctSvcrRef>{}</AcctSvcrRef>", reference)?;
+    }
+    writeln!(
+        writer,
+        "        <BkTxCd><Prtry><Cd>{:?}</Cd></Prtry></BkTxCd>",
+        record.tx_type
+    )?;
+    writeln!(writer, "        <NtryDtls><TxDtls>")?;
+    writeln!(
+        writer,
+        "          <Refs><EndToEndId>{}</EndToEndId></Refs>",
+        end_to_end
+    )?;
+    writeln!(writer, "          <RltdPties>")?;
+    writeln!(
+        writer,
+        "            <Dbtr><Id><PrvtId><Othr><Id>{}</Id></Othr></PrvtId></Id></Dbtr>",
+        record.from_user_id
+    )?;
+    writeln!(
+        writer,
+        "            <Cdtr><Id><PrvtId><Othr><Id>{}</Id></Othr></PrvtId></Id></Cdtr>",
+        record.to_user_id
+    )?;
+    writeln!(writer, "          </RltdPties>")?;
+    if !record.description.is_empty() {
+        writeln!(
+            writer,
+            "          <RmtInf><Ustrd>{}</Ustrd></RmtInf>",
+            escape(max_text(&record.description))
+        )?;
+    }
+    writeln!(writer, "        </TxDtls></NtryDtls>")?;
+    writeln!(writer, "      </Ntry>")?;
+    Ok(())
+}
+
+/// Поля проводки `<Ntry>`, собранные при чтении
+#[derive(Default)]
+struct Entry {
+    ntry_ref: Option<String>,
+    amount: Option<String>,
+    currency: Option<String>,
+    credit_debit: Option<String>,
+    status: Option<String>,
+    booking: Option<String>,
+    acct_svcr_ref: Option<String>,
+    tx_code: Option<String>,
+    end_to_end: Option<String>,
+    debtor: Option<String>,
+    creditor: Option<String>,
+    remittance: Vec<String>,
+    additional: Option<String>,
+}
+
+impl Entry {
+    /// Запоминает значение закрытого элемента по его пути
+    fn set(&mut self, path: &[String], value: String) {
+        let field = match () {
+            _ if ends_with(path, &["Ntry", "NtryRef"]) => &mut self.ntry_ref,
+            _ if ends_with(path, &["Ntry", "Amt"]) => &mut self.amount,
+            _ if ends_with(path, &["Ntry", "CdtDbtInd"]) => &mut self.credit_debit,
+            _ if ends_with(path, &["Ntry", "Sts"]) || ends_with(path, &["Ntry", "Sts", "Cd"]) => {
+                &mut self.status
+            }
+            _ if ends_with(path, &["BookgDt", "DtTm"]) || ends_with(path, &["BookgDt", "Dt"]) => {
+                &mut self.booking
+            }
+            _ if ends_with(path, &["Ntry", "AcctSvcrRef"]) => &mut self.acct_svcr_ref,
+            _ if ends_with(path, &["Ntry", "BkTxCd", "Prtry", "Cd"]) => &mut self.tx_code,
+            _ if ends_with(path, &["Refs", "EndToEndId"]) => &mut self.end_to_end,
+            _ if ends_with(path, &["Othr", "Id"]) && party(path) == Some("Dbtr") => {
+                &mut self.debtor
+            }
+            _ if ends_with(path, &["Othr", "Id"]) && party(path) == Some("Cdtr") => {
+                &mut self.creditor
+            }
+            _ if ends_with(path, &["RmtInf", "Ustrd"]) => {
+                self.remittance.push(value);
+                return;
+            }
+            _ if ends_with(path, &["Ntry", "AddtlNtryInf"]) => &mut self.additional,
+            _ => return,
+        };
+        field.get_or_insert(value);
+    }
+
+    fn into_record(self, position: u64, account: Option<&str>) -> Result<Record, CustomError> {
+        let required =
+            |field: Option<String>, name: &str| field.ok_or(CustomError::MissingField(name.into()));
+
+        let credit_debit = required(self.credit_debit, "CdtDbtInd")?;
+        let credit = match credit_debit.as_str() {
+            "CRDT" => true,
+            "DBIT" => false,
+            other => return Err(CustomError::InvalidEnum(format!("CdtDbtInd: {}", other))),
+        };
+        let status = match required(self.status, "Sts")?.as_str() {
+            "BOOK" => Status::SUCCESS,
+            "PDNG" => Status::PENDING,
+            "INFO" => Status::FAILURE,
+            other => return Err(CustomError::InvalidEnum(format!("Sts: {}", other))),
+        };
+        let tx_type = match self.tx_code.as_deref().and_then(|code| code.parse().ok()) {
+            Some(tx_type) => tx_type,
+            None if credit => TxType::DEPOSIT,
+            None => TxType::WITHDRAWAL,
+        };
+        let account_id = account.and_then(|a| a.parse().ok());
+        let debtor = self.debtor.as_deref().and_then(|d| d.parse().ok());
+        let creditor = self.creditor.as_deref().and_then(|c| c.parse().ok());
+
+        let tx_id = [&self.end_to_end, &self.ntry_ref]
+            .into_iter()
+            .find_map(|id| id.as_deref().and_then(|id| id.parse().ok()))
+            .unwrap_or(position);
+
+        let mut extra = BTreeMap::new();
+        if let Some(currency) = self.currency.filter(|c| c != NO_CURRENCY) {
+            extra.insert("CURRENCY".to_string(), currency);
+        }
+        if let Some(account) = account.filter(|a| *a != NO_ACCOUNT) {
+            extra.insert("ACCOUNT".to_string(), account.to_string());
+        }
+        let references = [
+            ("NTRY_REF", self.ntry_ref),
+            ("END_TO_END_ID", self.end_to_end),
+            ("ACCT_SVCR_REF", self.acct_svcr_ref),
+        ];
+        for (key, reference) in references {
+            if let Some(reference) = reference.filter(|r| *r != tx_id.to_string()) {
+                extra.insert(key.to_string(), reference);
+            }
+        }
+
+        let description = match self.remittance.is_empty() {
+            true => self.additional.unwrap_or_default(),
+            false => self.remittance.join(" "),
+        };
+
+        Ok(Record {
+            tx_id,
+            tx_type,
+            from_user_id: debtor.or(account_id.filter(|_| !credit)).unwrap_or(0),
+            to_user_id: creditor.or(account_id.filter(|_| credit)).unwrap_or(0),
+            amount: parse_amount(&required(self.amount, "Amt")?)?,
+            timestamp: parse_datetime(&required(self.booking, "BookgDt")?)?,
+            status,
+            description,
+            extra,
+        })
+    }
+}
+
+fn ends_with(path: &[String], suffix: &[&str]) -> bool {
+    path.len() >= suffix.len() && path[path.len() - suffix.len()..].iter().eq(suffix.iter())
+}
+
+/// Сторона (`Dbtr`/`Cdtr`) внутри `RltdPties`, которой принадлежит элемент
+fn party(path: &[String]) -> Option<&str> {
+    let index = path.iter().position(|p| p == "RltdPties")?;
+    path.get(index + 1).map(String::as_str)
+}
+
+/// Текст не длиннее [`MAX_TEXT`] символов
+pub(super) fn max_text(text: &str) -> &str {
+    match text.char_indices().nth(MAX_TEXT) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+pub(super) fn currency(record: &Record) -> &str {
+    record
+        .extra
+        .get("CURRENCY")
+        .map_or(NO_CURRENCY, String::as_str)
+}
+
+/// Направление проводки: поступление для `DEPOSIT`, списание для остальных
+fn credit_debit(record: &Record) -> &'static str {
+    match record.tx_type {
+        TxType::DEPOSIT => "CRDT",
+        TxType::TRANSFER | TxType::WITHDRAWAL => "DBIT",
+    }
+}
+
+/// Сумма в минимальных единицах в виде `123.45`
+pub(super) fn decimal(amount: u64) -> String {
+    format!("{}.{:02}", amount / 100, amount % 100)
+}
+
+/// Разбирает сумму `123`, `123.4` или `123.45` в минимальные единицы
+fn parse_amount(value: &str) -> Result<u64, CustomError> {
+    let invalid = || CustomError::InvalidNumber(format!("Amt ({})", value));
+    let (units, cents) = value.split_once('.').unwrap_or((value, ""));
+    if cents.len() > 2 || !cents.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let units: u64 = units.parse().map_err(|_| invalid())?;
+    let cents: u64 = format!("{:0<2}", cents).parse().map_err(|_| invalid())?;
+    units
+        .checked_mul(100)
+        .and_then(|units| units.checked_add(cents))
+        .ok_or_else(invalid)
+}
+
+/// Время в формате ISO 8601 (UTC) с миллисекундами
+pub(super) fn iso_datetime(timestamp: u64) -> Result<String, CustomError> {
+    Ok(utc_datetime(timestamp)?
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string())
+}
+
+/// `MsgId` (не длиннее 35 символов): префикс, время создания
+/// `ГГГГММДДччммсс` и 48 бит хеша FNV-1a записей в JSON, поэтому разные
+/// выгрузки получают разные идентификаторы
+pub(super) fn message_id(
+    prefix: &str,
+    created: &str,
+    records: &[&Record],
+) -> Result<String, CustomError> {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for record in records {
+        for byte in serde_json::to_vec(record)? {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    let time: String = created
+        .chars()
+        .filter(char::is_ascii_digit)
+        .take(14)
+        .collect();
+    Ok(format!("{}-{}-{:012X}", prefix, time, hash >> 16))
+}
+
+/// Разбирает `DtTm` (со смещением или без, тогда UTC) или `Dt` в миллисекунды
+fn parse_datetime(value: &str) -> Result<u64, CustomError> {
+    let millis = DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.timestamp_millis())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|dt| dt.and_utc().timestamp_millis())
+        })
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| {
+                d.and_hms_opt(0, 0, 0)
+                    .unwrap_or_default()
+                    .and_utc()
+                    .timestamp_millis()
+            })
+        })
+        .map_err(|_| CustomError::InvalidData(format!("дата BookgDt: {}", value)))?;
+    u64::try_from(millis).map_err(|_| CustomError::InvalidData(format!("дата BookgDt: {}", value)))
+}
+
+impl From<Vec<Record>> for Camt053Format {
+    fn from(records: Vec<Record>) -> Self {
+        Camt053Format { camt_rows: records }
+    }
+}
+
+impl From<Camt053Format> for Vec<Record> {
+    fn from(camt: Camt053Format) -> Self {
+        camt.camt_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{EncodeWriter, Encoding};
+    use crate::models::{Format, FormatOptions};
+
+    const BANK_STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>X</MsgId><CreDtTm>2024-03-01T10:00:00+01:00</CreDtTm></GrpHdr>
+    <Stmt>
+      <Id>1</Id>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      <Ntry>
+        <NtryRef>A-1</NtryRef>
+        <Amt Ccy="EUR">12.5</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2024-03-01</Dt></BookgDt>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+          <RmtInf><Ustrd>Rent &amp; fees</Ustrd><Ustrd>March</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn camt053_reads_bank_entries() -> Result<(), CustomError> {
+        let records = Camt053Format::from_read(&mut BANK_STATEMENT.as_bytes())?.camt_rows;
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.tx_id, 1);
+        assert_eq!(record.tx_type, TxType::WITHDRAWAL);
+        assert_eq!(record.amount, 1250);
+        assert_eq!(record.timestamp, 1_709_251_200_000);
+        assert_eq!(record.status, Status::SUCCESS);
+        assert_eq!(record.description, "Rent & fees March");
+        assert_eq!(record.extra["CURRENCY"], "EUR");
+        assert_eq!(record.extra["ACCOUNT"], "DE89370400440532013000");
+        assert_eq!(record.extra["NTRY_REF"], "A-1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn camt053_write_then_read() -> Result<(), CustomError> {
+        let records: Vec<Record> = [
+            (TxType::DEPOSIT, Status::SUCCESS),
+            (TxType::TRANSFER, Status::PENDING),
+            (TxType::WITHDRAWAL, Status::FAILURE),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (tx_type, status))| Record {
+            tx_id: i as u64 + 1,
+            tx_type,
+            from_user_id: 10,
+            to_user_id: 20,
+            amount: 1_001 * (i as u64 + 1),
+            timestamp: 1_700_000_000_123 + i as u64,
+            status,
+            description: "<a & b>".to_string(),
+            extra: BTreeMap::from([("CURRENCY".to_string(), "RUB".to_string())]),
+        })
+        .collect();
+
+        let mut buf = Vec::new();
+        Camt053Format::from(records.clone()).write_to(&mut buf)?;
+        let xml = String::from_utf8_lossy(&buf);
+        assert!(xml.contains(r#"<Amt Ccy="RUB">10.01</Amt>"#));
+        assert!(xml.contains("&lt;a &amp; b&gt;"));
+
+        let parsed = Camt053Format::from_read(&mut buf.as_slice())?.camt_rows;
+        assert_eq!(parsed, records);
+
+        Ok(())
+    }
+
+    #[test]
+    fn camt053_statements_per_account_and_currency() -> Result<(), CustomError> {
+        let record = |tx_id, currency: &str, description: String| Record {
+            tx_id,
+            tx_type: TxType::DEPOSIT,
+            from_user_id: 0,
+            to_user_id: 20,
+            amount: 100,
+            timestamp: 1_700_000_000_000,
+            status: Status::SUCCESS,
+            description,
+            extra: BTreeMap::from([
+                ("ACCOUNT".to_string(), "ACC-1".to_string()),
+                ("CURRENCY".to_string(), currency.to_string()),
+            ]),
+        };
+        let records = vec![
+            record(1, "EUR", "Пополнение".repeat(20)),
+            record(2, "USD", String::new()),
+            record(3, "EUR", "Пополнение".to_string()),
+        ];
+
+        let mut writer = EncodeWriter::new(Vec::new(), Format::Camt053, Encoding::Windows1251);
+        crate::write_records(Format::Camt053, records.clone(), &mut writer)?;
+        let buf = writer.finish()?;
+        let xml = String::from_utf8(buf.clone()).unwrap();
+
+        assert_eq!(xml.matches("<Stmt>").count(), 2);
+        assert!(xml.contains(r#"<Amt Ccy="EUR">2.00</Amt>"#));
+        assert!(xml.contains(r#"<Amt Ccy="USD">1.00</Amt>"#));
+        assert_eq!(xml.matches("<RmtInf>").count(), 2);
+
+        let parsed = Camt053Format::from_read(&mut buf.as_slice())?.camt_rows;
+        assert_eq!(parsed[0].description.chars().count(), MAX_TEXT);
+        assert_eq!(parsed[1].description, "Пополнение");
+        assert_eq!(parsed[2].description, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn camt053_message_id_and_timestamp_range() -> Result<(), CustomError> {
+        let msg_id = |amount| -> Result<String, CustomError> {
+            let mut record = crate::format::stream_records().remove(0);
+            record.amount = amount;
+            let mut buf = Vec::new();
+            Camt053Format::from(vec![record]).write_to(&mut buf)?;
+            let xml = String::from_utf8(buf).unwrap();
+            let start = xml.find("<MsgId>").unwrap() + "<MsgId>".len();
+            Ok(xml[start..xml.find("</MsgId>").unwrap()].to_string())
+        };
+        assert_ne!(msg_id(100)?, msg_id(200)?);
+        assert!(msg_id(100)?.starts_with("CAMT053-") && msg_id(100)?.len() <= 35);
+
+        let mut record = crate::format::stream_records().remove(0);
+        record.timestamp = u64::MAX;
+        for format in [Format::Camt053, Format::Qif, Format::Ofx] {
+            let options = FormatOptions {
+                user_id: Some(record.to_user_id),
+                ..FormatOptions::default()
+            };
+            let err = crate::write_records_with(
+                format.clone(),
+                vec![record.clone()],
+                &mut Vec::new(),
+                &options,
+            );
+            assert!(
+                matches!(err, Err(CustomError::InvalidData(_))),
+                "{:?}",
+                format
+            );
+        }
+        Ok(())
+    }
+}
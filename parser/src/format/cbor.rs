@@ -0,0 +1,73 @@
+use crate::error::CustomError;
+use crate::format::{DataFormat, StreamRecords};
+use crate::models::Record;
+use std::io::{Read, Write};
+
+/// Последовательность записей CBOR (RFC 8742)
+///
+/// Каждая запись — отдельный элемент-map с теми же ключами, что у
+/// [`MsgpackFormat`](crate::format::msgpack::MsgpackFormat). Элементы идут
+/// подряд без обрамляющего массива, поэтому поток читается по одной записи.
+#[derive(Debug)]
+pub struct CborFormat {
+    pub cbor_rows: Vec<Record>,
+}
+
+impl DataFormat for CborFormat {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        let cbor_rows = records(r).collect::<Result<Vec<Record>, _>>()?;
+
+        Ok(Self { cbor_rows })
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        for record in &self.cbor_rows {
+            ciborium::into_writer(record, &mut *writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Потоковое чтение записей CBOR по одной
+pub fn records<R: Read>(reader: R) -> StreamRecords<R> {
+    StreamRecords::new(reader, |reader| {
+        ciborium::from_reader(reader).map_err(CustomError::from)
+    })
+}
+
+impl From<Vec<Record>> for CborFormat {
+    fn from(records: Vec<Record>) -> Self {
+        Self { cbor_rows: records }
+    }
+}
+
+impl From<CborFormat> for Vec<Record> {
+    fn from(cbor: CborFormat) -> Self {
+        cbor.cbor_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cbor_sequence_of_named_maps() -> Result<(), CustomError> {
+        let records = crate::format::stream_records();
+
+        let mut buf = Vec::new();
+        CborFormat::from(records.clone()).write_to(&mut buf)?;
+        // map из 8 пар, первый ключ — текстовая строка TX_ID
+        assert_eq!(&buf[..7], b"\xA8\x65TX_ID");
+        assert!(buf.windows(7).any(|w| w == b"DEPOSIT"));
+
+        let parsed = super::records(buf.as_slice()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parsed, records);
+
+        let truncated = &buf[..buf.len() - 3];
+        let results: Vec<_> = super::records(truncated).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,480 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::csv::extra_columns;
+use crate::models::{ColumnCompression, FormatOptions, Record, Status, TxType};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int64Type, TimestampMillisecondType, UInt8Type, UInt64Type};
+use arrow_array::{
+    Array, ArrayRef, DictionaryArray, RecordBatch, StringArray, TimestampMillisecondArray,
+    UInt8Array, UInt64Array,
+};
+use arrow_ipc::CompressionType;
+use arrow_ipc::reader::FileReader;
+use arrow_ipc::writer::{FileWriter, IpcWriteOptions};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use bytes::Bytes;
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use parquet::schema::types::ColumnPath;
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Записей в группе строк Parquet и в пакете Arrow IPC по умолчанию
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Значения словаря `TX_TYPE`; индекс — ключ словаря
+const TX_TYPES: [&str; 3] = ["DEPOSIT", "TRANSFER", "WITHDRAWAL"];
+/// Значения словаря `STATUS`; индекс — ключ словаря
+const STATUSES: [&str; 3] = ["SUCCESS", "FAILURE", "PENDING"];
+
+/// Файл Apache Parquet
+///
+/// Поля записи — типизированные колонки с именами колонок CSV: числа —
+/// `UInt64`, `TIMESTAMP` — время в миллисекундах UTC, `TX_TYPE` и `STATUS` —
+/// строки со словарным кодированием, `DESCRIPTION` — строка. Дополнительные
+/// поля — необязательные строковые колонки. Записи делятся на группы строк
+/// по `row_group_size`.
+#[derive(Debug)]
+pub struct ParquetFormat {
+    pub parquet_rows: Vec<Record>,
+    pub row_group_size: Option<usize>,
+    pub compression: Option<ColumnCompression>,
+}
+
+/// Файл Arrow IPC (Feather v2)
+///
+/// Колонки те же, что у [`ParquetFormat`]; записи делятся на пакеты
+/// по `row_group_size`. Сжимается только `lz4` и `zstd`.
+#[derive(Debug)]
+pub struct ArrowFormat {
+    pub arrow_rows: Vec<Record>,
+    pub row_group_size: Option<usize>,
+    pub compression: Option<ColumnCompression>,
+}
+
+impl ParquetFormat {
+    /// Записи с размером группы строк и сжатием из `options`
+    pub fn with_options(records: Vec<Record>, options: &FormatOptions) -> Self {
+        ParquetFormat {
+            parquet_rows: records,
+            row_group_size: options.row_group_size,
+            compression: options.column_compression,
+        }
+    }
+}
+
+impl ArrowFormat {
+    /// Записи с размером пакета и сжатием из `options`
+    pub fn with_options(records: Vec<Record>, options: &FormatOptions) -> Self {
+        ArrowFormat {
+            arrow_rows: records,
+            row_group_size: options.row_group_size,
+            compression: options.column_compression,
+        }
+    }
+}
+
+impl DataFormat for ParquetFormat {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+
+        let mut records = Vec::new();
+        for batch in ParquetRecordBatchReaderBuilder::try_new(Bytes::from(data))?.build()? {
+            records.extend(batch_records(&batch?)?);
+        }
+        Ok(ParquetFormat::from(records))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let schema = schema(&self.parquet_rows);
+        let compression = match self.compression.unwrap_or(ColumnCompression::Snappy) {
+            ColumnCompression::None => Compression::UNCOMPRESSED,
+            ColumnCompression::Snappy => Compression::SNAPPY,
+            ColumnCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ColumnCompression::Lz4 => Compression::LZ4_RAW,
+            ColumnCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        };
+        let props = WriterProperties::builder()
+            .set_max_row_group_row_count(Some(row_group_size(self.row_group_size)?))
+            .set_compression(compression)
+            .set_dictionary_enabled(false)
+            .set_column_dictionary_enabled(ColumnPath::from("TX_TYPE"), true)
+            .set_column_dictionary_enabled(ColumnPath::from("STATUS"), true)
+            .build();
+
+        // ArrowWriter требует Send, поэтому файл собирается в памяти
+        let mut buf = Vec::new();
+        let mut parquet = ArrowWriter::try_new(&mut buf, schema.clone(), Some(props))?;
+        for chunk in self
+            .parquet_rows
+            .chunks(row_group_size(self.row_group_size)?)
+        {
+            parquet.write(&batch(&schema, chunk)?)?;
+        }
+        parquet.close()?;
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+impl DataFormat for ArrowFormat {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+
+        let mut records = Vec::new();
+        for batch in FileReader::try_new(Cursor::new(data), None)? {
+            records.extend(batch_records(&batch?)?);
+        }
+        Ok(ArrowFormat::from(records))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let schema = schema(&self.arrow_rows);
+        let compression = match self.compression {
+            None | Some(ColumnCompression::None) => None,
+            Some(ColumnCompression::Lz4) => Some(CompressionType::LZ4_FRAME),
+            Some(ColumnCompression::Zstd) => Some(CompressionType::ZSTD),
+            Some(other) => {
+                return Err(CustomError::InvalidData(format!(
+                    "Arrow IPC не поддерживает сжатие {:?}, только lz4 и zstd",
+                    other
+                )));
+            }
+        };
+        let options = IpcWriteOptions::default().try_with_compression(compression)?;
+
+        let mut arrow = FileWriter::try_new_with_options(writer, &schema, options)?;
+        for chunk in self.arrow_rows.chunks(row_group_size(self.row_group_size)?) {
+            arrow.write(&batch(&schema, chunk)?)?;
+        }
+        arrow.finish()?;
+        Ok(())
+    }
+}
+
+/// Размер группы строк: по умолчанию [`ROW_GROUP_SIZE`], ноль недопустим
+fn row_group_size(size: Option<usize>) -> Result<usize, CustomError> {
+    match size.unwrap_or(ROW_GROUP_SIZE) {
+        0 => Err(CustomError::InvalidData(
+            "размер группы строк должен быть больше нуля".to_string(),
+        )),
+        size => Ok(size),
+    }
+}
+
+/// Схема колонок: поля записи и дополнительные поля всех записей
+fn schema(records: &[Record]) -> SchemaRef {
+    let dictionary = DataType::Dictionary(Box::new(DataType::UInt8), Box::new(DataType::Utf8));
+    let mut fields = vec![
+        Field::new("TX_ID", DataType::UInt64, false),
+        Field::new("TX_TYPE", dictionary.clone(), false),
+        Field::new("FROM_USER_ID", DataType::UInt64, false),
+        Field::new("TO_USER_ID", DataType::UInt64, false),
+        Field::new("AMOUNT", DataType::UInt64, false),
+        Field::new(
+            "TIMESTAMP",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("STATUS", dictionary, false),
+        Field::new("DESCRIPTION", DataType::Utf8, false),
+    ];
+    fields.extend(
+        extra_columns(records)
+            .into_iter()
+            .map(|name| Field::new(name, DataType::Utf8, true)),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+/// Пакет колонок из записей.
+///
+/// Словари `TX_TYPE` и `STATUS` одинаковы во всех пакетах: файл Arrow IPC
+/// не допускает замены словаря между пакетами.
+fn batch(schema: &SchemaRef, records: &[Record]) -> Result<RecordBatch, CustomError> {
+    let u64_column = |value: fn(&Record) -> u64| -> ArrayRef {
+        Arc::new(records.iter().map(value).collect::<UInt64Array>())
+    };
+    let dictionary = |keys: UInt8Array, values: &[&str]| -> Result<ArrayRef, CustomError> {
+        let values = Arc::new(StringArray::from(values.to_vec()));
+        Ok(Arc::new(DictionaryArray::<UInt8Type>::try_new(
+            keys, values,
+        )?))
+    };
+
+    let timestamps = records
+        .iter()
+        .map(|r| {
+            i64::try_from(r.timestamp).map_err(|_| {
+                CustomError::InvalidData(format!("TIMESTAMP {} вне диапазона", r.timestamp))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut columns = vec![
+        u64_column(|r| r.tx_id),
+        dictionary(
+            records
+                .iter()
+                .map(|r| match r.tx_type {
+                    TxType::DEPOSIT => 0,
+                    TxType::TRANSFER => 1,
+                    TxType::WITHDRAWAL => 2,
+                })
+                .collect(),
+            &TX_TYPES,
+        )?,
+        u64_column(|r| r.from_user_id),
+        u64_column(|r| r.to_user_id),
+        u64_column(|r| r.amount),
+        Arc::new(TimestampMillisecondArray::from(timestamps).with_timezone("UTC")),
+        dictionary(
+            records
+                .iter()
+                .map(|r| match r.status {
+                    Status::SUCCESS => 0,
+                    Status::FAILURE => 1,
+                    Status::PENDING => 2,
+                })
+                .collect(),
+            &STATUSES,
+        )?,
+        Arc::new(
+            records
+                .iter()
+                .map(|r| Some(r.description.as_str()))
+                .collect::<StringArray>(),
+        ),
+    ];
+    for field in &schema.fields()[Record::FIELDS.len()..] {
+        columns.push(Arc::new(
+            records
+                .iter()
+                .map(|r| r.extra.get(field.name()).map(String::as_str))
+                .collect::<StringArray>(),
+        ));
+    }
+
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Записи из пакета колонок.
+///
+/// Кроме собственной схемы принимает колонки, записанные другими
+/// инструментами: целые `Int64`, строки без словаря, время как число.
+fn batch_records(batch: &RecordBatch) -> Result<Vec<Record>, CustomError> {
+    let column = |name: &str| {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| CustomError::MissingField(name.to_string()))
+    };
+    let tx_ids = integers(column("TX_ID")?, "TX_ID")?;
+    let tx_types = strings(column("TX_TYPE")?, "TX_TYPE")?;
+    let from_user_ids = integers(column("FROM_USER_ID")?, "FROM_USER_ID")?;
+    let to_user_ids = integers(column("TO_USER_ID")?, "TO_USER_ID")?;
+    let amounts = integers(column("AMOUNT")?, "AMOUNT")?;
+    let timestamps = integers(column("TIMESTAMP")?, "TIMESTAMP")?;
+    let statuses = strings(column("STATUS")?, "STATUS")?;
+    let descriptions = match batch.column_by_name("DESCRIPTION") {
+        Some(array) => strings(array, "DESCRIPTION")?,
+        None => vec![None; batch.num_rows()],
+    };
+
+    let schema = batch.schema();
+    let mut extra = Vec::new();
+    for (field, array) in schema.fields().iter().zip(batch.columns()) {
+        if !Record::FIELDS.contains(&field.name().as_str()) {
+            extra.push((field.name(), strings(array, field.name())?));
+        }
+    }
+
+    (0..batch.num_rows())
+        .map(|i| {
+            let required = |values: &[Option<u64>], name: &str| {
+                values[i].ok_or_else(|| CustomError::MissingField(name.to_string()))
+            };
+            let text = |values: &[Option<String>], name: &str| {
+                values[i]
+                    .clone()
+                    .ok_or_else(|| CustomError::MissingField(name.to_string()))
+            };
+            Ok(Record {
+                tx_id: required(&tx_ids, "TX_ID")?,
+                tx_type: TxType::from_str(&text(&tx_types, "TX_TYPE")?)
+                    .map_err(|e| CustomError::InvalidEnum(format!("TX_TYPE: {}", e)))?,
+                from_user_id: required(&from_user_ids, "FROM_USER_ID")?,
+                to_user_id: required(&to_user_ids, "TO_USER_ID")?,
+                amount: required(&amounts, "AMOUNT")?,
+                timestamp: required(&timestamps, "TIMESTAMP")?,
+                status: Status::from_str(&text(&statuses, "STATUS")?)
+                    .map_err(|e| CustomError::InvalidEnum(format!("STATUS: {}", e)))?,
+                description: descriptions[i].clone().unwrap_or_default(),
+                extra: extra
+                    .iter()
+                    .filter_map(|(name, values)| Some((name.to_string(), values[i].clone()?)))
+                    .collect::<BTreeMap<_, _>>(),
+            })
+        })
+        .collect()
+}
+
+/// Значения целочисленной колонки или колонки времени в миллисекундах
+fn integers(array: &ArrayRef, name: &str) -> Result<Vec<Option<u64>>, CustomError> {
+    let signed = |values: Vec<Option<i64>>| {
+        values
+            .into_iter()
+            .map(|v| {
+                v.map(u64::try_from)
+                    .transpose()
+                    .map_err(|_| CustomError::InvalidNumber(name.to_string()))
+            })
+            .collect()
+    };
+    match array.data_type() {
+        DataType::UInt64 => Ok(array.as_primitive::<UInt64Type>().iter().collect()),
+        DataType::Int64 => signed(array.as_primitive::<Int64Type>().iter().collect()),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => signed(
+            array
+                .as_primitive::<TimestampMillisecondType>()
+                .iter()
+                .collect(),
+        ),
+        _ => Err(CustomError::InvalidNumber(name.to_string())),
+    }
+}
+
+/// Значения строковой колонки, со словарём или без
+fn strings(array: &ArrayRef, name: &str) -> Result<Vec<Option<String>>, CustomError> {
+    let plain = |array: &dyn Array| -> Option<Vec<Option<String>>> {
+        let values = match array.data_type() {
+            DataType::Utf8 => array.as_string::<i32>().iter().collect::<Vec<_>>(),
+            DataType::LargeUtf8 => array.as_string::<i64>().iter().collect(),
+            _ => return None,
+        };
+        Some(values.into_iter().map(|v| v.map(str::to_string)).collect())
+    };
+
+    let values = match array.as_any_dictionary_opt() {
+        Some(dictionary) => plain(dictionary.values().as_ref()).map(|values| {
+            dictionary
+                .normalized_keys()
+                .into_iter()
+                .enumerate()
+                .map(|(i, key)| match array.is_null(i) {
+                    true => None,
+                    false => values[key].clone(),
+                })
+                .collect()
+        }),
+        None => plain(array.as_ref()),
+    };
+    values
+        .ok_or_else(|| CustomError::InvalidData(format!("колонка {} должна быть строковой", name)))
+}
+
+impl From<Vec<Record>> for ParquetFormat {
+    fn from(records: Vec<Record>) -> Self {
+        ParquetFormat::with_options(records, &FormatOptions::default())
+    }
+}
+
+impl From<ParquetFormat> for Vec<Record> {
+    fn from(parquet: ParquetFormat) -> Self {
+        parquet.parquet_rows
+    }
+}
+
+impl From<Vec<Record>> for ArrowFormat {
+    fn from(records: Vec<Record>) -> Self {
+        ArrowFormat::with_options(records, &FormatOptions::default())
+    }
+}
+
+impl From<ArrowFormat> for Vec<Record> {
+    fn from(arrow: ArrowFormat) -> Self {
+        arrow.arrow_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<Record> {
+        (0..5u64)
+            .map(|i| Record {
+                tx_id: i,
+                tx_type: TxType::from(i as u8 % 3),
+                from_user_id: 10 + i,
+                to_user_id: u64::MAX - i,
+                amount: 1_000 * i,
+                timestamp: 1_633_036_860_000 + i,
+                status: [Status::SUCCESS, Status::FAILURE, Status::PENDING][i as usize % 3].clone(),
+                description: format!("Record \"{}\"", i),
+                extra: match i {
+                    2 => BTreeMap::from([("CHANNEL".to_string(), "WEB".to_string())]),
+                    _ => BTreeMap::new(),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parquet_round_trip_with_dictionary_columns() -> Result<(), CustomError> {
+        for compression in [None, Some(ColumnCompression::Zstd)] {
+            let options = FormatOptions {
+                row_group_size: Some(2),
+                column_compression: compression,
+                ..FormatOptions::default()
+            };
+            let mut buf = Vec::new();
+            ParquetFormat::with_options(records(), &options).write_to(&mut buf)?;
+
+            let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buf.clone()))?;
+            assert_eq!(reader.metadata().num_row_groups(), 3);
+            assert!(matches!(
+                reader.schema().field_with_name("STATUS")?.data_type(),
+                DataType::Dictionary(_, _)
+            ));
+
+            let parsed = ParquetFormat::from_read(&mut buf.as_slice())?.parquet_rows;
+            assert_eq!(parsed, records());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn arrow_round_trip_in_batches() -> Result<(), CustomError> {
+        let options = FormatOptions {
+            row_group_size: Some(2),
+            column_compression: Some(ColumnCompression::Lz4),
+            ..FormatOptions::default()
+        };
+        let mut buf = Vec::new();
+        ArrowFormat::with_options(records(), &options).write_to(&mut buf)?;
+
+        assert_eq!(
+            FileReader::try_new(Cursor::new(buf.clone()), None)?.num_batches(),
+            3
+        );
+        let parsed = ArrowFormat::from_read(&mut buf.as_slice())?.arrow_rows;
+        assert_eq!(parsed, records());
+
+        let snappy = FormatOptions {
+            column_compression: Some(ColumnCompression::Snappy),
+            ..FormatOptions::default()
+        };
+        assert!(
+            ArrowFormat::with_options(records(), &snappy)
+                .write_to(&mut Vec::new())
+                .is_err()
+        );
+        Ok(())
+    }
+}
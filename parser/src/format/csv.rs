@@ -1,6 +1,9 @@
 use crate::error::CustomError;
 use crate::format::DataFormat;
 use crate::models::Record;
+use csv::StringRecord;
+use std::collections::BTreeSet;
+use std::io::Read;
 
 /// CSV формат
 /// Чтение и создание csv формата
@@ -13,29 +16,167 @@ pub struct CsvFormat {
 impl DataFormat for CsvFormat {
     // Парсит из любого источника, реализующего трейт Read
     fn from_read<R: std::io::Read>(r: &mut R) -> Result<Self, CustomError> {
-        let mut rdr = csv::Reader::from_reader(r);
-
-        let mut csv_rows: Vec<Record> = Vec::new();
-
-        for record in rdr.deserialize() {
-            let record: Record = record?;
-            csv_rows.push(record);
-        }
+        let csv_rows = CsvRecords::new(r).collect::<Result<Vec<Record>, _>>()?;
 
         Ok(Self { csv_rows })
     }
 
-    // Записывает отчёт в любой приёмник, реализующий трейт Write
+    // Записывает отчёт в любой приёмник, реализующий трейт Write.
+    // Дополнительные поля всех записей становятся колонками после DESCRIPTION
     fn write_to<W: std::io::Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let extra = extra_columns(&self.csv_rows);
         let mut csv_writer = csv::Writer::from_writer(writer);
+        if !self.csv_rows.is_empty() {
+            csv_writer.write_record(header(&extra)?)?;
+        }
         for row in &self.csv_rows {
-            let _ = csv_writer.serialize(row);
+            csv_writer.write_record(fields(row, &extra))?;
         }
         csv_writer.flush()?;
         Ok(())
     }
 }
 
+/// Имена дополнительных колонок: объединение ключей `extra` по всем записям
+pub(crate) fn extra_columns<'a>(records: impl IntoIterator<Item = &'a Record>) -> Vec<String> {
+    let keys: BTreeSet<&String> = records
+        .into_iter()
+        .flat_map(|record| record.extra.keys())
+        .collect();
+    keys.into_iter().cloned().collect()
+}
+
+/// Строка заголовка: поля формата и дополнительные колонки.
+///
+/// Дополнительное поле с именем поля формата (такое приходит, например,
+/// из JSON) дало бы вторую колонку с тем же именем — это ошибка.
+pub(crate) fn header(extra: &[String]) -> Result<Vec<&str>, CustomError> {
+    if let Some(key) = extra
+        .iter()
+        .find(|key| Record::FIELDS.contains(&key.as_str()))
+    {
+        return Err(CustomError::InvalidData(format!(
+            "дополнительное поле {} совпадает с полем записи",
+            key
+        )));
+    }
+    Ok(Record::FIELDS
+        .into_iter()
+        .chain(extra.iter().map(String::as_str))
+        .collect())
+}
+
+/// Значения записи в порядке [`header`]; отсутствующие дополнительные поля пусты
+pub(crate) fn fields(record: &Record, extra: &[String]) -> Vec<String> {
+    let mut fields = vec![
+        record.tx_id.to_string(),
+        format!("{:?}", record.tx_type),
+        record.from_user_id.to_string(),
+        record.to_user_id.to_string(),
+        record.amount.to_string(),
+        record.timestamp.to_string(),
+        format!("{:?}", record.status),
+        record.description.clone(),
+    ];
+    fields.extend(
+        extra
+            .iter()
+            .map(|key| record.extra.get(key).cloned().unwrap_or_default()),
+    );
+    fields
+}
+
+/// Раскладка колонок CSV по заголовку: какие относятся к полям формата,
+/// а какие попадут в [`Record::extra`]
+pub(crate) struct CsvLayout {
+    headers: StringRecord,
+    known: Option<(StringRecord, Vec<usize>)>,
+    extra: Vec<(usize, String)>,
+}
+
+impl CsvLayout {
+    /// Разбирает строку заголовка
+    pub(crate) fn new(headers: StringRecord) -> Self {
+        let (known, extra): (Vec<_>, Vec<_>) = headers
+            .iter()
+            .enumerate()
+            .partition(|(_, name)| Record::FIELDS.contains(name));
+        let extra = extra
+            .into_iter()
+            .map(|(i, name)| (i, name.to_string()))
+            .collect();
+        let known = (known.len() != headers.len()).then(|| {
+            let names = known.iter().map(|(_, name)| *name).collect();
+            (names, known.into_iter().map(|(i, _)| i).collect())
+        });
+
+        Self {
+            headers,
+            known,
+            extra,
+        }
+    }
+
+    /// Собирает запись из строки данных; пустые дополнительные поля не сохраняются
+    pub(crate) fn record(&self, row: &StringRecord) -> Result<Record, CustomError> {
+        let mut record: Record = match &self.known {
+            None => row.deserialize(Some(&self.headers))?,
+            Some((names, indexes)) => {
+                let mut known: StringRecord =
+                    indexes.iter().map(|&i| row.get(i).unwrap_or("")).collect();
+                known.set_position(row.position().cloned());
+                known.deserialize(Some(names))?
+            }
+        };
+
+        for (i, name) in &self.extra {
+            if let Some(value) = row.get(*i).filter(|value| !value.is_empty()) {
+                record.extra.insert(name.clone(), value.to_string());
+            }
+        }
+        Ok(record)
+    }
+}
+
+/// Потоковое чтение CSV: отдаёт записи по одной, не загружая файл целиком.
+///
+/// Колонки, не входящие в формат, сохраняются в [`Record::extra`].
+pub struct CsvRecords<R: Read> {
+    rows: csv::StringRecordsIntoIter<R>,
+    layout: Result<CsvLayout, Option<CustomError>>,
+}
+
+impl<R: Read> CsvRecords<R> {
+    /// Создаёт итератор записей поверх источника с заголовком в первой строке
+    pub fn new(r: R) -> Self {
+        let mut reader = csv::Reader::from_reader(r);
+        let layout = match reader.headers() {
+            Ok(headers) => Ok(CsvLayout::new(headers.clone())),
+            Err(e) => Err(Some(e.into())),
+        };
+
+        Self {
+            rows: reader.into_records(),
+            layout,
+        }
+    }
+}
+
+impl<R: Read> Iterator for CsvRecords<R> {
+    type Item = Result<Record, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let layout = match &mut self.layout {
+            Ok(layout) => layout,
+            Err(e) => return e.take().map(Err),
+        };
+        match self.rows.next()? {
+            Ok(row) => Some(layout.record(&row)),
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
 impl From<CsvFormat> for Vec<Record> {
     fn from(format: CsvFormat) -> Self {
         format.csv_rows
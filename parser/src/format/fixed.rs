@@ -0,0 +1,206 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::csv::{CsvLayout, fields, header};
+use crate::layout::{FILLER, Layout};
+use crate::models::Record;
+use csv::StringRecord;
+use std::io::{BufRead, BufReader, Lines, Read, Write};
+
+/// Текстовый формат фиксированной ширины (выгрузки мейнфреймов)
+///
+/// Каждая строка — запись, значения занимают колонки, описанные
+/// [`Layout`]: позиция, ширина, выравнивание и символ заполнения.
+/// Колонки с именами полей `Record` разбираются так же, как колонки CSV,
+/// остальные (кроме `FILLER`) попадают в [`Record::extra`]. Поля записи,
+/// которых нет в раскладке, при записи не выводятся.
+#[derive(Debug)]
+pub struct FixedWidthFormat {
+    pub fixed_rows: Vec<Record>,
+    pub layout: Layout,
+}
+
+impl FixedWidthFormat {
+    /// Записи в раскладке `layout`
+    pub fn new(records: Vec<Record>, layout: Layout) -> Self {
+        FixedWidthFormat {
+            fixed_rows: records,
+            layout,
+        }
+    }
+
+    /// Читает все записи в раскладке `layout`
+    pub fn read<R: Read>(r: &mut R, layout: &Layout) -> Result<Self, CustomError> {
+        let records = FixedRecords::new(r, layout.clone()).collect::<Result<_, _>>()?;
+        Ok(FixedWidthFormat::new(records, layout.clone()))
+    }
+}
+
+impl DataFormat for FixedWidthFormat {
+    /// Без раскладки формат не читается, см. [`FixedWidthFormat::read`]
+    fn from_read<R: Read>(_r: &mut R) -> Result<Self, CustomError> {
+        Err(missing_layout())
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let columns = self.layout.columns();
+        let extra: Vec<String> = columns
+            .iter()
+            .map(|c| c.name.clone())
+            .filter(|name| name != FILLER && !Record::FIELDS.contains(&name.as_str()))
+            .collect();
+        let names = header(&extra)?;
+
+        for record in &self.fixed_rows {
+            let values = fields(record, &extra);
+            let mut line = String::with_capacity(self.layout.record_len());
+            for column in columns {
+                // промежутки между колонками заполняются пробелами
+                let len = line.chars().count();
+                line.extend(std::iter::repeat_n(' ', column.start - len));
+                let value = match names.iter().position(|name| *name == column.name) {
+                    Some(i) => values[i].as_str(),
+                    None => "",
+                };
+                if value.contains(['\n', '\r']) {
+                    return Err(CustomError::InvalidData(format!(
+                        "значение {} содержит перевод строки",
+                        column.name
+                    )));
+                }
+                line.push_str(&column.pad(value)?);
+            }
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ошибка формата фиксированной ширины без раскладки
+pub(crate) fn missing_layout() -> CustomError {
+    CustomError::InvalidData("для формата Fixed нужна раскладка (layout)".to_string())
+}
+
+/// Потоковое чтение записей фиксированной ширины, по строке на запись.
+///
+/// Пустые строки пропускаются; строка короче раскладки дополняется
+/// пустыми значениями.
+pub struct FixedRecords<R: Read> {
+    lines: Lines<BufReader<R>>,
+    layout: Layout,
+    csv: CsvLayout,
+    line: u64,
+}
+
+impl<R: Read> FixedRecords<R> {
+    pub fn new(r: R, layout: Layout) -> Self {
+        let headers: StringRecord = layout
+            .columns()
+            .iter()
+            .filter(|c| c.name != FILLER)
+            .map(|c| c.name.as_str())
+            .collect();
+        FixedRecords {
+            lines: BufReader::new(r).lines(),
+            layout,
+            csv: CsvLayout::new(headers),
+            line: 0,
+        }
+    }
+
+    fn parse(&self, line: &str) -> Result<Record, CustomError> {
+        let chars: Vec<char> = line.chars().collect();
+        let row: StringRecord = self
+            .layout
+            .columns()
+            .iter()
+            .filter(|c| c.name != FILLER)
+            .map(|c| {
+                let start = c.start.min(chars.len());
+                let end = (c.start + c.width).min(chars.len());
+                let value: String = chars[start..end].iter().collect();
+                c.trim(&value).to_string()
+            })
+            .collect();
+        self.csv
+            .record(&row)
+            .map_err(|e| CustomError::InvalidData(format!("строка {}: {}", self.line, e)))
+    }
+}
+
+impl<R: Read> Iterator for FixedRecords<R> {
+    type Item = Result<Record, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.line += 1;
+            if !line.trim().is_empty() {
+                return Some(self.parse(line.trim_end_matches('\r')));
+            }
+        }
+    }
+}
+
+impl From<FixedWidthFormat> for Vec<Record> {
+    fn from(fixed: FixedWidthFormat) -> Self {
+        fixed.fixed_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    const LAYOUT: &str = "FIELD,START,WIDTH,ALIGN,PAD\n\
+        TX_ID,1,6,right,0\n\
+        TX_TYPE,7,10\n\
+        FROM_USER_ID,17,4,right,0\n\
+        TO_USER_ID,21,4,right,0\n\
+        AMOUNT,25,10,right,0\n\
+        TIMESTAMP,35,13,right\n\
+        STATUS,48,7\n\
+        FILLER,55,2,,*\n\
+        CHANNEL,57,4\n\
+        DESCRIPTION,62,12\n";
+
+    #[test]
+    fn fixed_width_round_trip() -> Result<(), CustomError> {
+        let layout = Layout::from_read(LAYOUT.as_bytes())?;
+        let records = vec![Record {
+            tx_id: 42,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 7,
+            to_user_id: 0,
+            amount: 125_000,
+            timestamp: 1_633_036_860_000,
+            status: Status::PENDING,
+            description: "Rent, March".to_string(),
+            extra: BTreeMap::from([("CHANNEL".to_string(), "WEB".to_string())]),
+        }];
+
+        let mut buf = Vec::new();
+        FixedWidthFormat::new(records.clone(), layout.clone()).write_to(&mut buf)?;
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            "000042TRANSFER  0007000000001250001633036860000PENDING**WEB  Rent, March \n"
+        );
+
+        let parsed = FixedWidthFormat::read(&mut buf.as_slice(), &layout)?.fixed_rows;
+        assert_eq!(parsed, records);
+
+        let long = "Rent, March and April";
+        let mut wide = records;
+        wide[0].description = long.to_string();
+        assert!(
+            FixedWidthFormat::new(wide, layout)
+                .write_to(&mut Vec::new())
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::CustomError;
+use crate::format::{DataFormat, StreamRecords};
+use crate::models::Record;
+use std::io::{Read, Write};
+
+/// Поток записей MessagePack
+///
+/// Каждая запись — отдельный map с именами полей, как у serde-представления
+/// [`Record`]: `TX_ID`, …, `DESCRIPTION`, `TX_TYPE` и `STATUS` — строками;
+/// дополнительные поля — вложенный map `EXTRA`, если они есть. Записи идут
+/// подряд без заголовка и разделителей, поэтому файл можно дописывать и читать
+/// потоково любым распаковщиком MessagePack.
+#[derive(Debug)]
+pub struct MsgpackFormat {
+    pub msgpack_rows: Vec<Record>,
+}
+
+impl DataFormat for MsgpackFormat {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        let msgpack_rows = records(r).collect::<Result<Vec<Record>, _>>()?;
+
+        Ok(Self { msgpack_rows })
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        for record in &self.msgpack_rows {
+            rmp_serde::encode::write_named(writer, record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Потоковое чтение записей MessagePack по одной
+pub fn records<R: Read>(reader: R) -> StreamRecords<R> {
+    StreamRecords::new(reader, |reader| {
+        rmp_serde::decode::from_read(reader).map_err(CustomError::from)
+    })
+}
+
+impl From<Vec<Record>> for MsgpackFormat {
+    fn from(records: Vec<Record>) -> Self {
+        Self {
+            msgpack_rows: records,
+        }
+    }
+}
+
+impl From<MsgpackFormat> for Vec<Record> {
+    fn from(msgpack: MsgpackFormat) -> Self {
+        msgpack.msgpack_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msgpack_stream_of_named_maps() -> Result<(), CustomError> {
+        let records = crate::format::stream_records();
+
+        let mut buf = Vec::new();
+        MsgpackFormat::from(records.clone()).write_to(&mut buf)?;
+        // fixmap из 8 полей, первый ключ — строка TX_ID
+        assert_eq!(&buf[..7], b"\x88\xA5TX_ID");
+        assert!(buf.windows(7).any(|w| w == b"DEPOSIT"));
+
+        let parsed = super::records(buf.as_slice()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parsed, records);
+
+        let truncated = &buf[..buf.len() - 3];
+        let results: Vec<_> = super::records(truncated).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+        Ok(())
+    }
+}
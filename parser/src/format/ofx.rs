@@ -0,0 +1,360 @@
+use crate::error::CustomError;
+use crate::format::camt053::{currency, decimal};
+use crate::format::{DataFormat, utc_datetime};
+use crate::models::{Format, Record, Status, TxType};
+use quick_xml::escape::partial_escape;
+use std::io::{Read, Write};
+
+/// Наибольшая длина `MEMO` в символах
+const MEMO_LEN: usize = 255;
+
+/// Вариант OFX: SGML (OFX 1.0.2) или XML (OFX 2.1.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfxVersion {
+    ///OFX 1.0.2: заголовок `OFXHEADER:100`, листовые элементы без закрывающих тегов
+    Sgml,
+    ///OFX 2.1.1: XML с инструкцией `<?OFX OFXHEADER="200"?>`
+    Xml,
+}
+
+/// Банковская выписка OFX для одного пользователя
+///
+/// Формат только для записи. В выписку попадают проведённые (`SUCCESS`)
+/// записи пользователя `user_id`, суммы — с его точки зрения:
+/// поступления — `CREDIT` с положительной суммой, списания — `DEBIT`
+/// с отрицательной. `TX_ID` — `FITID`, `DESCRIPTION` — `MEMO` в одну строку
+/// не длиннее 255 символов, валюта `CURDEF` берётся из `CURRENCY` первой
+/// записи. `LEDGERBAL` — сумма выгруженных движений. Выписка всегда
+/// в UTF-8 (`ENCODING:UNICODE` в заголовке SGML).
+#[derive(Debug)]
+pub struct OfxFormat {
+    pub ofx_rows: Vec<Record>,
+    pub version: OfxVersion,
+    pub user_id: Option<u64>,
+}
+
+impl OfxFormat {
+    /// Выписка пользователя `user_id` из записей
+    pub fn for_user(records: Vec<Record>, version: OfxVersion, user_id: Option<u64>) -> Self {
+        OfxFormat {
+            ofx_rows: records,
+            version,
+            user_id,
+        }
+    }
+}
+
+impl DataFormat for OfxFormat {
+    fn from_read<R: Read>(_r: &mut R) -> Result<Self, CustomError> {
+        Err(super::write_only(&Format::Ofx))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let user_id = statement_user(&Format::Ofx, self.user_id)?;
+        let entries: Vec<(&Record, i128)> = self
+            .ofx_rows
+            .iter()
+            .filter(|r| r.status == Status::SUCCESS)
+            .filter_map(|r| user_amount(r, user_id).map(|amount| (r, amount)))
+            .collect();
+        let first = entries.iter().map(|(r, _)| r.timestamp).min().unwrap_or(0);
+        let last = entries.iter().map(|(r, _)| r.timestamp).max().unwrap_or(0);
+        let balance: i128 = entries.iter().map(|(_, amount)| amount).sum();
+
+        let mut ofx = OfxWriter {
+            writer,
+            version: self.version,
+            depth: 0,
+        };
+        match self.version {
+            OfxVersion::Sgml => {
+                for line in [
+                    "OFXHEADER:100",
+                    "DATA:OFXSGML",
+                    "VERSION:102",
+                    "SECURITY:NONE",
+                    "ENCODING:UNICODE",
+                    "CHARSET:NONE",
+                    "COMPRESSION:NONE",
+                    "OLDFILEUID:NONE",
+                    "NEWFILEUID:NONE",
+                    "",
+                ] {
+                    writeln!(ofx.writer, "{}", line)?;
+                }
+            }
+            OfxVersion::Xml => {
+                writeln!(
+                    ofx.writer,
+                    r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#
+                )?;
+                writeln!(
+                    ofx.writer,
+                    r#"<?OFX OFXHEADER="200" VERSION="211" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>"#
+                )?;
+            }
+        }
+
+        ofx.open("OFX")?;
+        ofx.open("SIGNONMSGSRSV1")?;
+        ofx.open("SONRS")?;
+        ofx.status()?;
+        ofx.leaf("DTSERVER", &ofx_datetime(last)?)?;
+        ofx.leaf("LANGUAGE", "ENG")?;
+        ofx.close("SONRS")?;
+        ofx.close("SIGNONMSGSRSV1")?;
+
+        ofx.open("BANKMSGSRSV1")?;
+        ofx.open("STMTTRNRS")?;
+        ofx.leaf("TRNUID", "1")?;
+        ofx.status()?;
+        ofx.open("STMTRS")?;
+        ofx.leaf(
+            "CURDEF",
+            entries.first().map_or("XXX", |(r, _)| currency(r)),
+        )?;
+        ofx.open("BANKACCTFROM")?;
+        ofx.leaf("BANKID", "0")?;
+        ofx.leaf("ACCTID", &user_id.to_string())?;
+        ofx.leaf("ACCTTYPE", "CHECKING")?;
+        ofx.close("BANKACCTFROM")?;
+
+        ofx.open("BANKTRANLIST")?;
+        ofx.leaf("DTSTART", &ofx_datetime(first)?)?;
+        ofx.leaf("DTEND", &ofx_datetime(last)?)?;
+        for (record, amount) in &entries {
+            ofx.open("STMTTRN")?;
+            ofx.leaf("TRNTYPE", if *amount < 0 { "DEBIT" } else { "CREDIT" })?;
+            ofx.leaf("DTPOSTED", &ofx_datetime(record.timestamp)?)?;
+            ofx.leaf("TRNAMT", &signed_decimal(*amount))?;
+            ofx.leaf("FITID", &record.tx_id.to_string())?;
+            if !record.description.is_empty() {
+                // в SGML значение элемента заканчивается на переводе строки
+                let memo: String = record
+                    .description
+                    .replace(['\r', '\n'], " ")
+                    .chars()
+                    .take(MEMO_LEN)
+                    .collect();
+                ofx.leaf("MEMO", &memo)?;
+            }
+            ofx.close("STMTTRN")?;
+        }
+        ofx.close("BANKTRANLIST")?;
+
+        ofx.open("LEDGERBAL")?;
+        ofx.leaf("BALAMT", &signed_decimal(balance))?;
+        ofx.leaf("DTASOF", &ofx_datetime(last)?)?;
+        ofx.close("LEDGERBAL")?;
+        ofx.close("STMTRS")?;
+        ofx.close("STMTTRNRS")?;
+        ofx.close("BANKMSGSRSV1")?;
+        ofx.close("OFX")?;
+        Ok(())
+    }
+}
+
+/// Пишет агрегаты и листовые элементы OFX с отступами
+struct OfxWriter<'a, W: Write> {
+    writer: &'a mut W,
+    version: OfxVersion,
+    depth: usize,
+}
+
+impl<W: Write> OfxWriter<'_, W> {
+    fn open(&mut self, name: &str) -> Result<(), CustomError> {
+        writeln!(
+            self.writer,
+            "{:indent$}<{}>",
+            "",
+            name,
+            indent = self.depth * 2
+        )?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn close(&mut self, name: &str) -> Result<(), CustomError> {
+        self.depth -= 1;
+        writeln!(
+            self.writer,
+            "{:indent$}</{}>",
+            "",
+            name,
+            indent = self.depth * 2
+        )?;
+        Ok(())
+    }
+
+    /// Элемент со значением: в SGML закрывающий тег не пишется
+    ///
+    /// Экранируются только `&`, `<` и `>`: сущностей `&apos;` и `&quot;`
+    /// в OFX 1.0.2 нет.
+    fn leaf(&mut self, name: &str, value: &str) -> Result<(), CustomError> {
+        let indent = self.depth * 2;
+        let value = partial_escape(value);
+        match self.version {
+            OfxVersion::Sgml => writeln!(self.writer, "{:indent$}<{name}>{value}", "")?,
+            OfxVersion::Xml => writeln!(self.writer, "{:indent$}<{name}>{value}</{name}>", "")?,
+        }
+        Ok(())
+    }
+
+    /// Успешный статус ответа
+    fn status(&mut self) -> Result<(), CustomError> {
+        self.open("STATUS")?;
+        self.leaf("CODE", "0")?;
+        self.leaf("SEVERITY", "INFO")?;
+        self.close("STATUS")
+    }
+}
+
+/// Сумма записи с точки зрения пользователя: положительная — поступление,
+/// отрицательная — списание.
+///
+/// `DEPOSIT` зачисляется получателю, `WITHDRAWAL` списывается с отправителя,
+/// `TRANSFER` — списание для отправителя и поступление для получателя.
+/// Возвращает `None`, если запись не меняет баланс пользователя
+/// (он не участвует в ней или переводит сам себе).
+pub(super) fn user_amount(record: &Record, user_id: u64) -> Option<i128> {
+    let amount = record.amount as i128;
+    let from = record.from_user_id == user_id;
+    let to = record.to_user_id == user_id;
+    match record.tx_type {
+        TxType::DEPOSIT if to => Some(amount),
+        TxType::WITHDRAWAL if from => Some(-amount),
+        TxType::TRANSFER if from != to => Some(if to { amount } else { -amount }),
+        _ => None,
+    }
+}
+
+/// Пользователь выписки: без него OFX и QIF не пишутся
+pub(super) fn statement_user(format: &Format, user_id: Option<u64>) -> Result<u64, CustomError> {
+    user_id.ok_or_else(|| {
+        CustomError::InvalidData(format!(
+            "формат {:?} выгружается для одного пользователя: укажите user_id",
+            format
+        ))
+    })
+}
+
+/// Сумма со знаком в виде `-123.45`
+pub(super) fn signed_decimal(amount: i128) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    format!("{}{}", sign, decimal(amount.unsigned_abs() as u64))
+}
+
+/// Время OFX `YYYYMMDDHHMMSS.XXX[0:GMT]`
+fn ofx_datetime(timestamp: u64) -> Result<String, CustomError> {
+    Ok(utc_datetime(timestamp)?
+        .format("%Y%m%d%H%M%S%.3f[0:GMT]")
+        .to_string())
+}
+
+impl From<Vec<Record>> for OfxFormat {
+    fn from(records: Vec<Record>) -> Self {
+        OfxFormat::for_user(records, OfxVersion::Sgml, None)
+    }
+}
+
+impl From<OfxFormat> for Vec<Record> {
+    fn from(ofx: OfxFormat) -> Self {
+        ofx.ofx_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn history() -> Vec<Record> {
+        [
+            (TxType::DEPOSIT, 0, 7, Status::SUCCESS),
+            (TxType::TRANSFER, 7, 8, Status::SUCCESS),
+            (TxType::TRANSFER, 8, 7, Status::SUCCESS),
+            (TxType::WITHDRAWAL, 7, 0, Status::FAILURE),
+            (TxType::WITHDRAWAL, 8, 0, Status::SUCCESS),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (tx_type, from_user_id, to_user_id, status))| Record {
+            tx_id: i as u64 + 1,
+            tx_type,
+            from_user_id,
+            to_user_id,
+            amount: 1_000 * (i as u64 + 1),
+            timestamp: 1_633_036_860_000 + i as u64 * 60_000,
+            status,
+            description: format!("Payment <{}>", i + 1),
+            extra: BTreeMap::new(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn ofx_sgml_and_xml_statements_for_user() -> Result<(), CustomError> {
+        let mut sgml = Vec::new();
+        OfxFormat::for_user(history(), OfxVersion::Sgml, Some(7)).write_to(&mut sgml)?;
+        let sgml = String::from_utf8_lossy(&sgml);
+
+        assert!(sgml.starts_with("OFXHEADER:100\nDATA:OFXSGML\n"));
+        assert!(sgml.contains("\nENCODING:UNICODE\n"));
+        assert_eq!(sgml.matches("<STMTTRN>").count(), 3);
+        assert_eq!(sgml.matches("<TRNTYPE>CREDIT\n").count(), 2);
+        assert!(sgml.contains("<DTPOSTED>20210930212100.000[0:GMT]\n"));
+        assert!(sgml.contains("<TRNAMT>-20.00\n"));
+        assert!(sgml.contains("<TRNAMT>30.00\n"));
+        assert!(sgml.contains("<MEMO>Payment &lt;3&gt;\n"));
+        assert!(sgml.contains("<BALAMT>20.00\n"));
+        assert!(!sgml.contains("<FITID>4"));
+
+        let mut xml = Vec::new();
+        OfxFormat::for_user(history(), OfxVersion::Xml, Some(7)).write_to(&mut xml)?;
+        let xml = String::from_utf8_lossy(&xml);
+
+        assert!(xml.contains(r#"<?OFX OFXHEADER="200" VERSION="211""#));
+        assert!(xml.contains("<TRNAMT>-20.00</TRNAMT>"));
+        assert!(xml.contains("<ACCTID>7</ACCTID>"));
+
+        assert!(
+            OfxFormat::from(history())
+                .write_to(&mut Vec::new())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ofx_memo_is_one_line_of_255_chars() -> Result<(), CustomError> {
+        let mut records = history();
+        records[0].description = format!("Rent\r\nMarch {}", "x".repeat(300));
+
+        let mut sgml = Vec::new();
+        OfxFormat::for_user(records, OfxVersion::Sgml, Some(7)).write_to(&mut sgml)?;
+        let sgml = String::from_utf8(sgml).unwrap();
+
+        let memo = sgml
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("<MEMO>Rent"))
+            .unwrap();
+        assert!(memo.starts_with("  March x"));
+        assert_eq!(memo.chars().count(), MEMO_LEN - "Rent".len());
+        Ok(())
+    }
+
+    #[test]
+    fn ofx_keeps_quotes_unescaped() -> Result<(), CustomError> {
+        let mut records = history();
+        records[0].description = r#"O'Brien "rent" & co"#.to_string();
+
+        for version in [OfxVersion::Sgml, OfxVersion::Xml] {
+            let mut buf = Vec::new();
+            OfxFormat::for_user(records.clone(), version, Some(7)).write_to(&mut buf)?;
+            let ofx = String::from_utf8(buf).unwrap();
+            assert!(ofx.contains(r#"<MEMO>O'Brien "rent" &amp; co"#));
+            assert!(!ofx.contains("&apos;") && !ofx.contains("&quot;"));
+        }
+        Ok(())
+    }
+}
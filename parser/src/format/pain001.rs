@@ -0,0 +1,373 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::camt053::{decimal, iso_datetime, max_text, message_id};
+use crate::models::{Format, Record, Status, TxType};
+use quick_xml::escape::escape;
+use std::io::{Read, Write};
+
+/// Пространство имён платёжного поручения, которое пишет [`Pain001Format`]
+pub const PAIN001_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:pain.001.001.03";
+/// Идентификатор банка плательщика, если BIC не задан
+const NOT_PROVIDED: &str = "NOTPROVIDED";
+
+/// Инициирование кредитовых переводов ISO 20022 pain.001 (CstmrCdtTrfInitn)
+///
+/// Формат только для записи. В документ попадают записи `TRANSFER` в статусе
+/// `PENDING`, остальные пропускаются:
+/// - `GrpHdr` содержит число переводов `NbOfTxs` и их сумму `CtrlSum`;
+/// - переводы группируются в `PmtInf` по плательщику, дате записи
+///   (`ReqdExctnDt`) и валюте, у каждой группы свои `NbOfTxs` и `CtrlSum`;
+/// - `TX_ID` — `InstrId` и `EndToEndId` (если нет поля `END_TO_END_ID`),
+///   `AMOUNT` — `InstdAmt` в валюте `CURRENCY`, `DESCRIPTION` — `RmtInf/Ustrd`
+///   (не длиннее 140 символов);
+/// - перевод без поля `CURRENCY` — ошибка: банк не примет сумму без валюты;
+/// - `MsgId` составлен из времени создания и хеша переводов, поэтому разные
+///   пакеты получают разные идентификаторы.
+///
+/// Документ всегда в UTF-8.
+///
+/// Счета плательщика и получателя берутся из полей `DEBTOR_ACCOUNT`,
+/// `DEBTOR_NAME`, `DEBTOR_BIC` и `CREDITOR_*`, которые заполняет
+/// [`AccountMap::apply`](crate::accounts::AccountMap::apply); без них счётом
+/// считается идентификатор пользователя.
+#[derive(Debug)]
+pub struct Pain001Format {
+    pub pain_rows: Vec<Record>,
+}
+
+impl DataFormat for Pain001Format {
+    fn from_read<R: Read>(_r: &mut R) -> Result<Self, CustomError> {
+        Err(super::write_only(&Format::Pain001))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let transfers: Vec<&Record> = self
+            .pain_rows
+            .iter()
+            .filter(|r| r.tx_type == TxType::TRANSFER && r.status == Status::PENDING)
+            .collect();
+        if transfers.is_empty() {
+            return Err(CustomError::InvalidData(
+                "нет записей TRANSFER в статусе PENDING".to_string(),
+            ));
+        }
+
+        let mut payments: Vec<(Party, String, &str, Vec<&Record>)> = Vec::new();
+        for record in &transfers {
+            let debtor = Party::new(record, "DEBTOR", record.from_user_id);
+            let date = iso_datetime(record.timestamp)?[..10].to_string();
+            let currency = transfer_currency(record)?;
+            match payments
+                .iter_mut()
+                .find(|(party, day, ccy, _)| *party == debtor && *day == date && *ccy == currency)
+            {
+                Some((_, _, _, records)) => records.push(record),
+                None => payments.push((debtor, date, currency, vec![record])),
+            }
+        }
+        let created = iso_datetime(transfers.iter().map(|r| r.timestamp).max().unwrap_or(0))?;
+        let initiator = &payments[0].0;
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<Document xmlns="{}">"#, PAIN001_NAMESPACE)?;
+        writeln!(writer, "  <CstmrCdtTrfInitn>")?;
+        writeln!(writer, "    <GrpHdr>")?;
+        writeln!(
+            writer,
+            "      <MsgId>{}</MsgId>",
+            message_id("PAIN001", &created, &transfers)?
+        )?;
+        writeln!(writer, "      <CreDtTm>{}</CreDtTm>", created)?;
+        writeln!(writer, "      <NbOfTxs>{}</NbOfTxs>", transfers.len())?;
+        writeln!(
+            writer,
+            "      <CtrlSum>{}</CtrlSum>",
+            control_sum(&transfers)?
+        )?;
+        writeln!(
+            writer,
+            "      <InitgPty><Nm>{}</Nm></InitgPty>",
+            escape(initiator.name.as_deref().unwrap_or(&initiator.account))
+        )?;
+        writeln!(writer, "    </GrpHdr>")?;
+
+        for (n, (debtor, date, currency, records)) in payments.iter().enumerate() {
+            writeln!(writer, "    <PmtInf>")?;
+            writeln!(writer, "      <PmtInfId>PMT-{}</PmtInfId>", n + 1)?;
+            writeln!(writer, "      <PmtMtd>TRF</PmtMtd>")?;
+            writeln!(writer, "      <NbOfTxs>{}</NbOfTxs>", records.len())?;
+            writeln!(writer, "      <CtrlSum>{}</CtrlSum>", control_sum(records)?)?;
+            writeln!(writer, "      <ReqdExctnDt>{}</ReqdExctnDt>", date)?;
+            debtor.write_party(writer, "Dbtr")?;
+            debtor.write_account(writer, "Dbtr")?;
+            writeln!(
+                writer,
+                "      <DbtrAgt>{}</DbtrAgt>",
+                institution(debtor.bic.as_deref().unwrap_or(NOT_PROVIDED))
+            )?;
+            for record in records {
+                write_transfer(writer, record, currency)?;
+            }
+            writeln!(writer, "    </PmtInf>")?;
+        }
+
+        writeln!(writer, "  </CstmrCdtTrfInitn>")?;
+        writeln!(writer, "</Document>")?;
+        Ok(())
+    }
+}
+
+/// Валюта перевода из поля `CURRENCY`
+fn transfer_currency(record: &Record) -> Result<&str, CustomError> {
+    record
+        .extra
+        .get("CURRENCY")
+        .map(String::as_str)
+        .filter(|ccy| !ccy.is_empty())
+        .ok_or_else(|| {
+            CustomError::InvalidData(format!(
+                "у перевода TX_ID {} нет поля CURRENCY",
+                record.tx_id
+            ))
+        })
+}
+
+fn write_transfer<W: Write>(
+    writer: &mut W,
+    record: &Record,
+    currency: &str,
+) -> Result<(), CustomError> {
+    let creditor = Party::new(record, "CREDITOR", record.to_user_id);
+    let end_to_end = match record.extra.get("END_TO_END_ID") {
+        Some(id) => escape(id.as_str()).into_owned(),
+        None => record.tx_id.to_string(),
+    };
+
+    writeln!(writer, "      <CdtTrfTxInf>")?;
+    writeln!(
+        writer,
+        "        <PmtId><InstrId>{}</InstrId><EndToEndId>{}</EndToEndId></PmtId>",
+        record.tx_id, end_to_end
+    )?;
+    writeln!(
+        writer,
+        r#"        <Amt><InstdAmt Ccy="{}">{}</InstdAmt></Amt>"#,
+        escape(currency),
+        decimal(record.amount)
+    )?;
+    if let Some(bic) = &creditor.bic {
+        writeln!(writer, "        <CdtrAgt>{}</CdtrAgt>", institution(bic))?;
+    }
+    creditor.write_party(writer, "Cdtr")?;
+    creditor.write_account(writer, "Cdtr")?;
+    if !record.description.is_empty() {
+        writeln!(
+            writer,
+            "        <RmtInf><Ustrd>{}</Ustrd></RmtInf>",
+            escape(max_text(&record.description))
+        )?;
+    }
+    writeln!(writer, "      </CdtTrfTxInf>")?;
+    Ok(())
+}
+
+/// Плательщик или получатель перевода
+#[derive(PartialEq)]
+struct Party {
+    user_id: u64,
+    account: String,
+    name: Option<String>,
+    bic: Option<String>,
+}
+
+impl Party {
+    /// Реквизиты из полей `<prefix>_ACCOUNT`, `<prefix>_NAME`, `<prefix>_BIC`
+    fn new(record: &Record, prefix: &str, user_id: u64) -> Party {
+        let extra = |key: &str| record.extra.get(&format!("{}_{}", prefix, key)).cloned();
+        Party {
+            user_id,
+            account: extra("ACCOUNT").unwrap_or_else(|| user_id.to_string()),
+            name: extra("NAME"),
+            bic: extra("BIC"),
+        }
+    }
+
+    fn write_party<W: Write>(&self, writer: &mut W, tag: &str) -> Result<(), CustomError> {
+        let indent = if tag == "Dbtr" { "      " } else { "        " };
+        let name = match &self.name {
+            Some(name) => format!("<Nm>{}</Nm>", escape(name.as_str())),
+            None => String::new(),
+        };
+        writeln!(
+            writer,
+            "{indent}<{tag}>{name}<Id><PrvtId><Othr><Id>{}</Id></Othr></PrvtId></Id></{tag}>",
+            self.user_id
+        )?;
+        Ok(())
+    }
+
+    fn write_account<W: Write>(&self, writer: &mut W, tag: &str) -> Result<(), CustomError> {
+        let indent = if tag == "Dbtr" { "      " } else { "        " };
+        let account = escape(self.account.as_str());
+        let id = match is_iban(&self.account) {
+            true => format!("<IBAN>{}</IBAN>", account),
+            false => format!("<Othr><Id>{}</Id></Othr>", account),
+        };
+        writeln!(writer, "{indent}<{tag}Acct><Id>{id}</Id></{tag}Acct>")?;
+        Ok(())
+    }
+}
+
+/// Банк `<FinInstnId>`: BIC или `NOTPROVIDED`
+fn institution(bic: &str) -> String {
+    match bic {
+        NOT_PROVIDED => format!("<FinInstnId><Othr><Id>{}</Id></Othr></FinInstnId>", bic),
+        bic => format!("<FinInstnId><BIC>{}</BIC></FinInstnId>", escape(bic)),
+    }
+}
+
+/// Похоже ли значение на IBAN: код страны, две цифры и до 30 букв и цифр
+fn is_iban(account: &str) -> bool {
+    let bytes = account.as_bytes();
+    (15..=34).contains(&bytes.len())
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..4].iter().all(u8::is_ascii_digit)
+        && bytes[4..].iter().all(u8::is_ascii_alphanumeric)
+}
+
+/// Сумма переводов `CtrlSum`
+fn control_sum(records: &[&Record]) -> Result<String, CustomError> {
+    records
+        .iter()
+        .try_fold(0u64, |sum, r| sum.checked_add(r.amount))
+        .map(decimal)
+        .ok_or_else(|| CustomError::InvalidNumber("CtrlSum (переполнение)".to_string()))
+}
+
+impl From<Vec<Record>> for Pain001Format {
+    fn from(records: Vec<Record>) -> Self {
+        Pain001Format { pain_rows: records }
+    }
+}
+
+impl From<Pain001Format> for Vec<Record> {
+    fn from(pain: Pain001Format) -> Self {
+        pain.pain_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::AccountMap;
+    use std::collections::BTreeMap;
+
+    fn record(tx_id: u64, tx_type: TxType, status: Status, amount: u64) -> Record {
+        Record {
+            tx_id,
+            tx_type,
+            from_user_id: 1,
+            to_user_id: 2 + tx_id,
+            amount,
+            timestamp: 1_700_000_000_000 + tx_id,
+            status,
+            description: "rent & fees".to_string(),
+            extra: BTreeMap::from([("CURRENCY".to_string(), "EUR".to_string())]),
+        }
+    }
+
+    #[test]
+    fn pain001_writes_pending_transfers_with_totals() -> Result<(), CustomError> {
+        let mut records = vec![
+            record(1, TxType::TRANSFER, Status::PENDING, 1_050),
+            record(2, TxType::TRANSFER, Status::SUCCESS, 700),
+            record(3, TxType::DEPOSIT, Status::PENDING, 300),
+            record(4, TxType::TRANSFER, Status::PENDING, 25),
+        ];
+        let accounts = AccountMap::from_read(
+            "USER_ID,ACCOUNT,NAME,BIC\n\
+             1,DE89370400440532013000,Alice,COBADEFFXXX\n\
+             3,40817810099910004312,,\n"
+                .as_bytes(),
+        )?;
+        accounts.apply(&mut records);
+
+        let mut buf = Vec::new();
+        Pain001Format::from(records).write_to(&mut buf)?;
+        let xml = String::from_utf8_lossy(&buf);
+
+        assert_eq!(xml.matches("<NbOfTxs>2</NbOfTxs>").count(), 2);
+        assert_eq!(xml.matches("<CtrlSum>10.75</CtrlSum>").count(), 2);
+        assert_eq!(xml.matches("<CdtTrfTxInf>").count(), 2);
+        assert!(xml.contains("<DbtrAcct><Id><IBAN>DE89370400440532013000</IBAN></Id></DbtrAcct>"));
+        assert!(xml.contains("<DbtrAgt><FinInstnId><BIC>COBADEFFXXX</BIC></FinInstnId></DbtrAgt>"));
+        assert!(xml.contains("<Dbtr><Nm>Alice</Nm>"));
+        assert!(
+            xml.contains(
+                "<CdtrAcct><Id><Othr><Id>40817810099910004312</Id></Othr></Id></CdtrAcct>"
+            )
+        );
+        assert!(xml.contains("<CdtrAcct><Id><Othr><Id>6</Id></Othr></Id></CdtrAcct>"));
+        assert!(xml.contains(r#"<InstdAmt Ccy="EUR">10.50</InstdAmt>"#));
+        assert!(xml.contains("<Ustrd>rent &amp; fees</Ustrd>"));
+        assert!(!xml.contains("<InstrId>2</InstrId>"));
+        assert!(xml.contains("<MsgId>PAIN001-20231114221320-"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pain001_is_write_only_and_needs_transfers() {
+        let records = vec![record(1, TxType::DEPOSIT, Status::PENDING, 100)];
+
+        assert!(
+            Pain001Format::from(records)
+                .write_to(&mut Vec::new())
+                .is_err()
+        );
+        assert!(Pain001Format::from_read(&mut "<Document/>".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn pain001_groups_by_currency_and_requires_it() -> Result<(), CustomError> {
+        let mut usd = record(2, TxType::TRANSFER, Status::PENDING, 200);
+        usd.timestamp = 1_700_000_000_001;
+        usd.extra.insert("CURRENCY".to_string(), "USD".to_string());
+        let records = vec![record(1, TxType::TRANSFER, Status::PENDING, 100), usd];
+
+        let mut buf = Vec::new();
+        Pain001Format::from(records).write_to(&mut buf)?;
+        let xml = String::from_utf8_lossy(&buf);
+        assert_eq!(xml.matches("<PmtInf>").count(), 2);
+        assert!(xml.contains("<CtrlSum>1.00</CtrlSum>"));
+        assert!(xml.contains(r#"<InstdAmt Ccy="USD">2.00</InstdAmt>"#));
+
+        let mut bare = record(3, TxType::TRANSFER, Status::PENDING, 100);
+        bare.extra.clear();
+        assert!(
+            Pain001Format::from(vec![bare])
+                .write_to(&mut Vec::new())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pain001_message_id_depends_on_transfers() -> Result<(), CustomError> {
+        let batch = |amount| {
+            let mut buf = Vec::new();
+            let records = vec![record(1, TxType::TRANSFER, Status::PENDING, amount)];
+            Pain001Format::from(records).write_to(&mut buf)?;
+            let xml = String::from_utf8(buf).unwrap();
+            let start = xml.find("<MsgId>").unwrap() + "<MsgId>".len();
+            let end = xml.find("</MsgId>").unwrap();
+            Ok::<_, CustomError>(xml[start..end].to_string())
+        };
+
+        let (first, second) = (batch(100)?, batch(200)?);
+        assert_ne!(first, second);
+        assert_eq!(first, batch(100)?);
+        assert!(first.len() <= 35);
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::csv::extra_columns;
+use crate::models::{Format, Record};
+use chrono::DateTime;
+use std::io::{Read, Write};
+
+/// Подпись, флаги и длина расширения заголовка бинарного COPY
+const BINARY_HEADER: &[u8; 19] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+/// Миллисекунд от 1970-01-01 до 2000-01-01 — начала отсчёта времени PostgreSQL
+const POSTGRES_EPOCH_MS: i64 = 946_684_800_000;
+
+/// Вариант COPY: текстовый или бинарный
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMode {
+    ///`COPY ... FROM STDIN`: строки с полями через табуляцию
+    Text,
+    ///`COPY ... FROM STDIN (FORMAT binary)`
+    Binary,
+}
+
+/// Данные для команды PostgreSQL `COPY ... FROM STDIN`
+///
+/// Формат только для записи. Колонки повторяют [`Record`] в нижнем регистре:
+/// числовые поля — `BIGINT`, `timestamp` — `TIMESTAMPTZ`, `tx_type`, `status`,
+/// описание и дополнительные поля — `TEXT`; отсутствующее дополнительное поле —
+/// `NULL`. Числа больше `i64::MAX` в `BIGINT` не помещаются и считаются ошибкой.
+/// Колонки идут в порядке полей [`Record`], затем дополнительные поля
+/// по алфавиту.
+#[derive(Debug)]
+pub struct PgCopyFormat {
+    pub pgcopy_rows: Vec<Record>,
+    pub mode: CopyMode,
+}
+
+impl PgCopyFormat {
+    /// Записи в текстовом или бинарном COPY
+    pub fn new(records: Vec<Record>, mode: CopyMode) -> Self {
+        PgCopyFormat {
+            pgcopy_rows: records,
+            mode,
+        }
+    }
+}
+
+impl DataFormat for PgCopyFormat {
+    fn from_read<R: Read>(_r: &mut R) -> Result<Self, CustomError> {
+        Err(super::write_only(&Format::PgCopy))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let extra = extra_columns(&self.pgcopy_rows);
+        if self.mode == CopyMode::Binary {
+            writer.write_all(BINARY_HEADER)?;
+        }
+        for record in &self.pgcopy_rows {
+            let values = values(record, &extra)?;
+            match self.mode {
+                CopyMode::Text => write_text(writer, &values)?,
+                CopyMode::Binary => write_binary(writer, &values)?,
+            }
+        }
+        if self.mode == CopyMode::Binary {
+            writer.write_all(&(-1i16).to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Значение колонки COPY
+enum Value {
+    BigInt(i64),
+    ///Миллисекунды Unix
+    Timestamp(i64),
+    Text(String),
+    Null,
+}
+
+/// Значения колонок: поля записи в порядке [`Record::FIELDS`], затем `extra`
+fn values(record: &Record, extra: &[String]) -> Result<Vec<Value>, CustomError> {
+    let mut values = vec![
+        bigint(record.tx_id, "TX_ID")?,
+        Value::Text(format!("{:?}", record.tx_type)),
+        bigint(record.from_user_id, "FROM_USER_ID")?,
+        bigint(record.to_user_id, "TO_USER_ID")?,
+        bigint(record.amount, "AMOUNT")?,
+        match bigint(record.timestamp, "TIMESTAMP")? {
+            Value::BigInt(ms) => Value::Timestamp(ms),
+            value => value,
+        },
+        Value::Text(format!("{:?}", record.status)),
+        Value::Text(record.description.clone()),
+    ];
+    values.extend(extra.iter().map(|key| match record.extra.get(key) {
+        Some(value) => Value::Text(value.clone()),
+        None => Value::Null,
+    }));
+    Ok(values)
+}
+
+/// Строка текстового COPY: поля через табуляцию, `NULL` — `\N`
+fn write_text<W: Write>(writer: &mut W, values: &[Value]) -> Result<(), CustomError> {
+    let fields = values
+        .iter()
+        .map(|value| {
+            Ok(match value {
+                Value::BigInt(number) => number.to_string(),
+                Value::Timestamp(ms) => DateTime::from_timestamp_millis(*ms)
+                    .ok_or_else(|| out_of_range(*ms))?
+                    .format("%Y-%m-%d %H:%M:%S%.3f+00")
+                    .to_string(),
+                Value::Text(text) => escape(text),
+                Value::Null => "\\N".to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, CustomError>>()?;
+    writeln!(writer, "{}", fields.join("\t"))?;
+    Ok(())
+}
+
+/// Кортеж бинарного COPY: число полей, затем длина и значение каждого
+/// в сетевом порядке байт; `NULL` — длина `-1`
+fn write_binary<W: Write>(writer: &mut W, values: &[Value]) -> Result<(), CustomError> {
+    let count = i16::try_from(values.len()).map_err(|_| {
+        CustomError::InvalidData(format!("{} колонок не помещаются в COPY", values.len()))
+    })?;
+    writer.write_all(&count.to_be_bytes())?;
+    for value in values {
+        let bytes = match value {
+            Value::BigInt(number) => number.to_be_bytes().to_vec(),
+            Value::Timestamp(ms) => (ms - POSTGRES_EPOCH_MS)
+                .checked_mul(1000)
+                .ok_or_else(|| out_of_range(*ms))?
+                .to_be_bytes()
+                .to_vec(),
+            Value::Text(text) => text.as_bytes().to_vec(),
+            Value::Null => {
+                writer.write_all(&(-1i32).to_be_bytes())?;
+                continue;
+            }
+        };
+        let len = i32::try_from(bytes.len())
+            .map_err(|_| CustomError::InvalidData("поле длиннее 2 ГБ".to_string()))?;
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Экранирует обратную косую черту, табуляцию и переводы строк
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Число для колонки `BIGINT`: PostgreSQL хранит только знаковые 64-битные целые
+fn bigint(value: u64, field: &str) -> Result<Value, CustomError> {
+    i64::try_from(value).map(Value::BigInt).map_err(|_| {
+        CustomError::InvalidData(format!(
+            "{} {} не помещается в BIGINT PostgreSQL",
+            field, value
+        ))
+    })
+}
+
+fn out_of_range(ms: i64) -> CustomError {
+    CustomError::InvalidData(format!(
+        "TIMESTAMP {} не помещается в TIMESTAMPTZ PostgreSQL",
+        ms
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn records() -> Vec<Record> {
+        let record = |tx_id, description: &str, extra: &[(&str, &str)]| Record {
+            tx_id,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 7,
+            to_user_id: 8,
+            amount: 250,
+            timestamp: 1_633_036_860_123,
+            status: Status::SUCCESS,
+            description: description.to_string(),
+            extra: extra
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+        };
+        vec![
+            record(1, "Rent\tand\\bills\nMay", &[("CHANNEL", "WEB")]),
+            record(2, "", &[]),
+        ]
+    }
+
+    #[test]
+    fn pgcopy_writes_text_rows() -> Result<(), CustomError> {
+        let mut buf = Vec::new();
+        PgCopyFormat::new(records(), CopyMode::Text).write_to(&mut buf)?;
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "1\tTRANSFER\t7\t8\t250\t2021-09-30 21:21:00.123+00\tSUCCESS\tRent\\tand\\\\bills\\nMay\tWEB\n\
+             2\tTRANSFER\t7\t8\t250\t2021-09-30 21:21:00.123+00\tSUCCESS\t\t\\N\n"
+        );
+
+        let mut overflow = records();
+        overflow[1].amount = u64::MAX;
+        let err = PgCopyFormat::new(overflow, CopyMode::Text).write_to(&mut Vec::new());
+        assert!(matches!(err, Err(CustomError::InvalidData(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn pgcopy_writes_binary_tuples() -> Result<(), CustomError> {
+        let mut buf = Vec::new();
+        PgCopyFormat::new(records(), CopyMode::Binary).write_to(&mut buf)?;
+
+        assert_eq!(&buf[..19], BINARY_HEADER);
+        assert_eq!(&buf[buf.len() - 2..], &[0xff, 0xff]);
+
+        // второй кортеж: 9 полей, пустое описание и NULL вместо CHANNEL
+        let micros = (1_633_036_860_123 - POSTGRES_EPOCH_MS) * 1000;
+        let fields: [&[u8]; 8] = [
+            &2i64.to_be_bytes(),
+            b"TRANSFER",
+            &7i64.to_be_bytes(),
+            &8i64.to_be_bytes(),
+            &250i64.to_be_bytes(),
+            &micros.to_be_bytes(),
+            b"SUCCESS",
+            b"",
+        ];
+        let mut tuple = 9i16.to_be_bytes().to_vec();
+        for field in fields {
+            tuple.extend((field.len() as i32).to_be_bytes());
+            tuple.extend(field);
+        }
+        tuple.extend((-1i32).to_be_bytes());
+        assert!(buf[..buf.len() - 2].ends_with(&tuple));
+        Ok(())
+    }
+}
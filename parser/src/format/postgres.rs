@@ -0,0 +1,227 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::csv::extra_columns;
+use crate::format::pgcopy::{CopyMode, PgCopyFormat};
+use crate::models::Record;
+use ::postgres::{Client, NoTls};
+
+/// Таблица записей по умолчанию
+pub const DEFAULT_TABLE: &str = "transactions";
+
+/// Колонки полей записи: имя и тип PostgreSQL
+const COLUMNS: [(&str, &str); 8] = [
+    ("tx_id", "BIGINT"),
+    ("tx_type", "TEXT"),
+    ("from_user_id", "BIGINT"),
+    ("to_user_id", "BIGINT"),
+    ("amount", "BIGINT"),
+    ("timestamp", "TIMESTAMPTZ"),
+    ("status", "TEXT"),
+    ("description", "TEXT"),
+];
+
+/// Индексируемые колонки, кроме первичного ключа `tx_id`
+const INDEXED: [&str; 3] = ["timestamp", "from_user_id", "to_user_id"];
+
+/// Загружает записи в таблицу PostgreSQL по строке подключения `url`.
+///
+/// Таблица создаётся, если её нет, и дополняется колонками новых полей;
+/// записи дописываются командой COPY в одной транзакции, повтор `tx_id`
+/// откатывает всю загрузку. Подключение без TLS. Возвращает число строк.
+pub fn load(
+    url: &str,
+    table: Option<&str>,
+    mode: CopyMode,
+    records: Vec<Record>,
+) -> Result<u64, CustomError> {
+    let table = table.unwrap_or(DEFAULT_TABLE);
+    let extra = extra_names(&extra_columns(&records))?;
+
+    let mut client = Client::connect(url, NoTls)?;
+    let mut tx = client.transaction()?;
+    tx.batch_execute(&create_table(table, &extra))?;
+    let mut writer = tx.copy_in(&copy_statement(table, &extra, mode))?;
+    PgCopyFormat::new(records, mode).write_to(&mut writer)?;
+    let rows = writer.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+/// Колонки дополнительных полей: имена в нижнем регистре.
+///
+/// Поля, которые совпадают без учёта регистра друг с другом (`CHANNEL`
+/// и `channel`) или с колонкой записи (`tx_id`), в одну таблицу не
+/// загрузить — это ошибка.
+fn extra_names(extra: &[String]) -> Result<Vec<String>, CustomError> {
+    let mut names: Vec<String> = Vec::new();
+    for key in extra {
+        let name = key.to_lowercase();
+        if COLUMNS.iter().any(|(column, _)| *column == name) || names.contains(&name) {
+            return Err(CustomError::InvalidData(format!(
+                "дополнительное поле {} совпадает с другой колонкой PostgreSQL {}",
+                key, name
+            )));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Команды, создающие таблицу с первичным ключом `tx_id`, индексами и
+/// колонками дополнительных полей `extra`, если их ещё нет
+fn create_table(table: &str, extra: &[String]) -> String {
+    let name = quote(table);
+    let columns: Vec<String> = COLUMNS
+        .iter()
+        .map(|(column, kind)| format!("{} {} NOT NULL", column, kind))
+        .collect();
+    let mut sql = vec![format!(
+        "CREATE TABLE IF NOT EXISTS {} ({}, PRIMARY KEY (tx_id))",
+        name,
+        columns.join(", ")
+    )];
+    sql.extend(INDEXED.iter().map(|column| {
+        format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+            quote(&format!("{}_{}", table, column)),
+            name,
+            column
+        )
+    }));
+    sql.extend(extra.iter().map(|column| {
+        format!(
+            "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} TEXT",
+            name,
+            quote(column)
+        )
+    }));
+    sql.join(";\n") + ";"
+}
+
+/// Команда `COPY` со списком колонок в порядке записи [`PgCopyFormat`]:
+/// поля, затем дополнительные поля
+fn copy_statement(table: &str, extra: &[String], mode: CopyMode) -> String {
+    let columns: Vec<String> = COLUMNS
+        .iter()
+        .map(|(column, _)| column.to_string())
+        .chain(extra.iter().map(|column| quote(column)))
+        .collect();
+    let format = match mode {
+        CopyMode::Text => "",
+        CopyMode::Binary => " (FORMAT binary)",
+    };
+    format!(
+        "COPY {} ({}) FROM STDIN{}",
+        quote(table),
+        columns.join(", "),
+        format
+    )
+}
+
+/// Идентификатор SQL в двойных кавычках
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn postgres_statements_list_extra_columns() -> Result<(), CustomError> {
+        let extra = extra_names(&["CHANNEL".to_string()])?;
+        assert_eq!(
+            copy_statement("transactions", &extra, CopyMode::Binary),
+            "COPY \"transactions\" (tx_id, tx_type, from_user_id, to_user_id, amount, \
+             timestamp, status, description, \"channel\") FROM STDIN (FORMAT binary)"
+        );
+
+        let ddl = create_table("tx", &extra);
+        assert!(ddl.starts_with(
+            "CREATE TABLE IF NOT EXISTS \"tx\" (tx_id BIGINT NOT NULL, tx_type TEXT NOT NULL,"
+        ));
+        assert!(ddl.contains("CREATE INDEX IF NOT EXISTS \"tx_timestamp\" ON \"tx\" (timestamp)"));
+        assert!(ddl.ends_with("ALTER TABLE \"tx\" ADD COLUMN IF NOT EXISTS \"channel\" TEXT;"));
+
+        for keys in [["CHANNEL", "channel"], ["tx_id", "CHANNEL"]] {
+            let keys = keys.map(str::to_string);
+            assert!(matches!(
+                extra_names(&keys),
+                Err(CustomError::InvalidData(_))
+            ));
+        }
+        Ok(())
+    }
+
+    /// Загружает записи в обоих вариантах COPY и читает их обратно;
+    /// нужен PostgreSQL: `PG_URL=postgres://... cargo test --features postgres -- --ignored`
+    #[test]
+    #[ignore = "нужен PostgreSQL по адресу из PG_URL или DATABASE_URL"]
+    fn postgres_loads_and_reads_back() -> Result<(), CustomError> {
+        let url = std::env::var("PG_URL")
+            .or_else(|_| std::env::var("DATABASE_URL"))
+            .expect("PG_URL или DATABASE_URL");
+        let record = |tx_id, description: &str, channel: Option<&str>| Record {
+            tx_id,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 7,
+            to_user_id: 8,
+            amount: 250,
+            timestamp: 1_633_036_860_123,
+            status: Status::PENDING,
+            description: description.to_string(),
+            extra: channel
+                .map(|channel| BTreeMap::from([("CHANNEL".to_string(), channel.to_string())]))
+                .unwrap_or_default(),
+        };
+        let records = vec![
+            record(1, "Rent\tand\\bills\nПополнение", Some("WEB")),
+            record(2, "", None),
+        ];
+
+        let mut client = Client::connect(&url, NoTls)?;
+        for (table, mode) in [
+            ("parser_copy_text", CopyMode::Text),
+            ("parser_copy_binary", CopyMode::Binary),
+        ] {
+            client.batch_execute(&format!("DROP TABLE IF EXISTS {}", quote(table)))?;
+            assert_eq!(load(&url, Some(table), mode, records.clone())?, 2);
+            // повтор tx_id откатывает загрузку целиком
+            assert!(load(&url, Some(table), mode, records.clone()).is_err());
+
+            let rows = client.query(
+                &format!(
+                    "SELECT tx_id, tx_type, from_user_id, to_user_id, amount, \
+                     (EXTRACT(EPOCH FROM timestamp) * 1000)::BIGINT, status, description, \
+                     channel FROM {} ORDER BY tx_id",
+                    quote(table)
+                ),
+                &[],
+            )?;
+            let parsed: Vec<Record> = rows
+                .iter()
+                .map(|row| Record {
+                    tx_id: row.get::<_, i64>(0) as u64,
+                    tx_type: TxType::from_str(row.get(1)).unwrap(),
+                    from_user_id: row.get::<_, i64>(2) as u64,
+                    to_user_id: row.get::<_, i64>(3) as u64,
+                    amount: row.get::<_, i64>(4) as u64,
+                    timestamp: row.get::<_, i64>(5) as u64,
+                    status: Status::from_str(row.get(6)).unwrap(),
+                    description: row.get(7),
+                    extra: row
+                        .get::<_, Option<String>>(8)
+                        .map(|channel| BTreeMap::from([("CHANNEL".to_string(), channel)]))
+                        .unwrap_or_default(),
+                })
+                .collect();
+            assert_eq!(parsed, records, "{:?}", mode);
+
+            client.batch_execute(&format!("DROP TABLE {}", quote(table)))?;
+        }
+        Ok(())
+    }
+}
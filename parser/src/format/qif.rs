@@ -0,0 +1,113 @@
+use crate::error::CustomError;
+use crate::format::ofx::{signed_decimal, statement_user, user_amount};
+use crate::format::{DataFormat, utc_datetime};
+use crate::models::{Format, Record, Status};
+use std::io::{Read, Write};
+
+/// Выписка QIF (`!Type:Bank`) для одного пользователя
+///
+/// Формат только для записи. В выписку попадают записи пользователя `user_id`
+/// в статусах `SUCCESS` и `PENDING`, суммы `T` — с его точки зрения, как в
+/// OFX: поступления положительные, списания отрицательные. Проведённые записи
+/// отмечаются как сверенные (`CX`), `TX_ID` — номер `N`, `DESCRIPTION` —
+/// получатель платежа `P`. Дата `D` пишется как `MM/DD/YYYY`.
+#[derive(Debug)]
+pub struct QifFormat {
+    pub qif_rows: Vec<Record>,
+    pub user_id: Option<u64>,
+}
+
+impl QifFormat {
+    /// Выписка пользователя `user_id` из записей
+    pub fn for_user(records: Vec<Record>, user_id: Option<u64>) -> Self {
+        QifFormat {
+            qif_rows: records,
+            user_id,
+        }
+    }
+}
+
+impl DataFormat for QifFormat {
+    fn from_read<R: Read>(_r: &mut R) -> Result<Self, CustomError> {
+        Err(super::write_only(&Format::Qif))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        let user_id = statement_user(&Format::Qif, self.user_id)?;
+
+        writeln!(writer, "!Type:Bank")?;
+        for record in &self.qif_rows {
+            if record.status == Status::FAILURE {
+                continue;
+            }
+            let Some(amount) = user_amount(record, user_id) else {
+                continue;
+            };
+            let date = utc_datetime(record.timestamp)?;
+
+            writeln!(writer, "D{}", date.format("%m/%d/%Y"))?;
+            writeln!(writer, "T{}", signed_decimal(amount))?;
+            if record.status == Status::SUCCESS {
+                writeln!(writer, "CX")?;
+            }
+            writeln!(writer, "N{}", record.tx_id)?;
+            if !record.description.is_empty() {
+                // перевод строки начал бы новое поле
+                writeln!(writer, "P{}", record.description.replace(['\r', '\n'], " "))?;
+            }
+            writeln!(writer, "^")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<Record>> for QifFormat {
+    fn from(records: Vec<Record>) -> Self {
+        QifFormat::for_user(records, None)
+    }
+}
+
+impl From<QifFormat> for Vec<Record> {
+    fn from(qif: QifFormat) -> Self {
+        qif.qif_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TxType;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn qif_lists_user_transactions_from_their_side() -> Result<(), CustomError> {
+        let record = |tx_id, tx_type, from_user_id, to_user_id, status| Record {
+            tx_id,
+            tx_type,
+            from_user_id,
+            to_user_id,
+            amount: 1_250,
+            timestamp: 1_633_036_860_000,
+            status,
+            description: "Rent\nMarch".to_string(),
+            extra: BTreeMap::new(),
+        };
+        let records = vec![
+            record(1, TxType::TRANSFER, 7, 8, Status::SUCCESS),
+            record(2, TxType::TRANSFER, 8, 7, Status::PENDING),
+            record(3, TxType::DEPOSIT, 0, 8, Status::SUCCESS),
+            record(4, TxType::WITHDRAWAL, 7, 0, Status::FAILURE),
+        ];
+
+        let mut buf = Vec::new();
+        QifFormat::for_user(records, Some(7)).write_to(&mut buf)?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            "!Type:Bank\n\
+             D09/30/2021\nT-12.50\nCX\nN1\nPRent March\n^\n\
+             D09/30/2021\nT12.50\nN2\nPRent March\n^\n"
+        );
+        Ok(())
+    }
+}
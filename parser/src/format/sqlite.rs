@@ -0,0 +1,459 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::csv::extra_columns;
+use crate::models::{FormatOptions, Record, SqliteMode, Status, TxType};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, MAIN_DB, Row};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Таблица записей по умолчанию
+pub const DEFAULT_TABLE: &str = "transactions";
+
+/// Колонки полей записи: имя и тип SQLite
+const COLUMNS: [(&str, &str); 8] = [
+    ("TX_ID", "INTEGER"),
+    ("TX_TYPE", "TEXT"),
+    ("FROM_USER_ID", "INTEGER"),
+    ("TO_USER_ID", "INTEGER"),
+    ("AMOUNT", "INTEGER"),
+    ("TIMESTAMP", "INTEGER"),
+    ("STATUS", "TEXT"),
+    ("DESCRIPTION", "TEXT"),
+];
+
+/// Индексируемые колонки; индекс по `TX_ID` уникальный
+const INDEXED: [&str; 4] = ["TX_ID", "TIMESTAMP", "FROM_USER_ID", "TO_USER_ID"];
+
+/// База SQLite с таблицей записей
+///
+/// Таблица (по умолчанию `transactions`) повторяет [`Record`]: числовые поля —
+/// `INTEGER`, `TX_TYPE`, `STATUS` и описание — `TEXT`, дополнительные поля —
+/// `TEXT`-колонки, которые добавляются по мере появления. `TX_ID` уникален,
+/// по `TIMESTAMP` и пользователям построены индексы. Числа больше
+/// `i64::MAX` в `INTEGER` SQLite не помещаются и считаются ошибкой.
+///
+/// Читаются записи всей таблицы или результат запроса `query`; колонки
+/// сопоставляются с полями по имени без учёта регистра, остальные колонки
+/// становятся дополнительными полями.
+#[derive(Debug)]
+pub struct SqliteFormat {
+    pub sqlite_rows: Vec<Record>,
+    pub table: Option<String>,
+    pub mode: SqliteMode,
+}
+
+impl SqliteFormat {
+    /// Записи с таблицей и режимом записи из `options`
+    pub fn with_options(records: Vec<Record>, options: &FormatOptions) -> Self {
+        SqliteFormat {
+            sqlite_rows: records,
+            table: options.table.clone(),
+            mode: options.sqlite_mode,
+        }
+    }
+
+    /// Читает записи из образа базы: таблицу `options.table` или запрос `options.query`
+    pub fn read<R: Read>(r: &mut R, options: &FormatOptions) -> Result<Self, CustomError> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+
+        let mut conn = Connection::open_in_memory()?;
+        conn.deserialize_read_exact(MAIN_DB, data.as_slice(), data.len(), true)?;
+        let records = query(&conn, options)?;
+        Ok(SqliteFormat::with_options(records, options))
+    }
+
+    fn table(&self) -> &str {
+        self.table.as_deref().unwrap_or(DEFAULT_TABLE)
+    }
+}
+
+impl DataFormat for SqliteFormat {
+    fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
+        SqliteFormat::read(r, &FormatOptions::default())
+    }
+
+    /// Пишет образ новой базы; дописать в существующую можно только через
+    /// [`write_database`]
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        if !matches!(self.mode, SqliteMode::Create | SqliteMode::Replace) {
+            return Err(CustomError::InvalidData(format!(
+                "режим {:?} работает только с несжатым файлом базы SQLite",
+                self.mode
+            )));
+        }
+        let mut conn = Connection::open_in_memory()?;
+        store(&mut conn, &self.sqlite_rows, self.table(), self.mode)?;
+        writer.write_all(&conn.serialize(MAIN_DB)?)?;
+        Ok(())
+    }
+}
+
+/// Записывает записи в файл базы SQLite по пути `path`.
+///
+/// Файл создаётся, если его нет; остальные таблицы базы не затрагиваются.
+/// `options.sqlite_mode` определяет, что делать с существующей таблицей:
+/// отказаться от записи (по умолчанию), пересоздать её, дописать записи
+/// или обновить их по `TX_ID`.
+pub fn write_database(
+    path: &Path,
+    records: &[Record],
+    options: &FormatOptions,
+) -> Result<(), CustomError> {
+    let mut conn = Connection::open(path)?;
+    let table = options.table.as_deref().unwrap_or(DEFAULT_TABLE);
+    store(&mut conn, records, table, options.sqlite_mode)
+}
+
+/// Создаёт таблицу с индексами и вставляет записи в одной транзакции
+fn store(
+    conn: &mut Connection,
+    records: &[Record],
+    table: &str,
+    mode: SqliteMode,
+) -> Result<(), CustomError> {
+    let tx = conn.transaction()?;
+    let name = quote(table);
+    match mode {
+        SqliteMode::Replace => {
+            tx.execute(&format!("DROP TABLE IF EXISTS {}", name), [])?;
+        }
+        SqliteMode::Create => {
+            let exists: bool = tx.query_row(
+                "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |row| row.get(0),
+            )?;
+            if exists {
+                return Err(CustomError::InvalidData(format!(
+                    "таблица {} уже есть; укажите режим replace, append или upsert",
+                    table
+                )));
+            }
+        }
+        SqliteMode::Append | SqliteMode::Upsert => {}
+    }
+
+    let columns: Vec<String> = COLUMNS
+        .iter()
+        .map(|(column, kind)| format!("{} {} NOT NULL", column, kind))
+        .collect();
+    tx.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            name,
+            columns.join(", ")
+        ),
+        [],
+    )?;
+    let existing: BTreeSet<String> = tx
+        .prepare(&format!(
+            "SELECT name FROM pragma_table_info({})",
+            literal(table)
+        ))?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .map(|column| column.map(|c| c.to_uppercase()))
+        .collect::<Result<_, _>>()?;
+    if let Some((missing, _)) = COLUMNS.iter().find(|(c, _)| !existing.contains(*c)) {
+        return Err(CustomError::InvalidData(format!(
+            "в таблице {} нет колонки {}",
+            table, missing
+        )));
+    }
+    for column in INDEXED {
+        let unique = if column == "TX_ID" { "UNIQUE " } else { "" };
+        tx.execute(
+            &format!(
+                "CREATE {}INDEX IF NOT EXISTS {} ON {} ({})",
+                unique,
+                quote(&format!("{}_{}", table, column.to_lowercase())),
+                name,
+                column
+            ),
+            [],
+        )?;
+    }
+
+    let extra = extra_columns(records);
+    for column in &extra {
+        if !existing.contains(&column.to_uppercase()) {
+            tx.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} TEXT", name, quote(column)),
+                [],
+            )?;
+        }
+    }
+
+    let names: Vec<String> = COLUMNS
+        .iter()
+        .map(|(column, _)| column.to_string())
+        .chain(extra.iter().map(|column| quote(column)))
+        .collect();
+    let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+    let mut sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        name,
+        names.join(", "),
+        placeholders.join(", ")
+    );
+    if mode == SqliteMode::Upsert {
+        let updates: Vec<String> = names[1..]
+            .iter()
+            .map(|column| format!("{0} = excluded.{0}", column))
+            .collect();
+        sql.push_str(&format!(
+            " ON CONFLICT (TX_ID) DO UPDATE SET {}",
+            updates.join(", ")
+        ));
+    }
+
+    {
+        let mut insert = tx.prepare(&sql)?;
+        for record in records {
+            let mut values = vec![
+                integer(record.tx_id, "TX_ID")?,
+                Value::Text(format!("{:?}", record.tx_type)),
+                integer(record.from_user_id, "FROM_USER_ID")?,
+                integer(record.to_user_id, "TO_USER_ID")?,
+                integer(record.amount, "AMOUNT")?,
+                integer(record.timestamp, "TIMESTAMP")?,
+                Value::Text(format!("{:?}", record.status)),
+                Value::Text(record.description.clone()),
+            ];
+            values.extend(extra.iter().map(|key| match record.extra.get(key) {
+                Some(value) => Value::Text(value.clone()),
+                None => Value::Null,
+            }));
+            insert.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Выполняет запрос или читает всю таблицу
+fn query(conn: &Connection, options: &FormatOptions) -> Result<Vec<Record>, CustomError> {
+    let sql = match &options.query {
+        Some(query) => query.clone(),
+        None => format!(
+            "SELECT * FROM {}",
+            quote(options.table.as_deref().unwrap_or(DEFAULT_TABLE))
+        ),
+    };
+    let mut stmt = conn.prepare(&sql)?;
+    let names: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let index = |field: &str| names.iter().position(|n| n.eq_ignore_ascii_case(field));
+    let required = |field: &'static str| {
+        index(field)
+            .map(|i| (i, field))
+            .ok_or_else(|| CustomError::MissingField(field.to_string()))
+    };
+    let tx_id = required("TX_ID")?;
+    let tx_type = required("TX_TYPE")?;
+    let from_user_id = required("FROM_USER_ID")?;
+    let to_user_id = required("TO_USER_ID")?;
+    let amount = required("AMOUNT")?;
+    let timestamp = required("TIMESTAMP")?;
+    let status = required("STATUS")?;
+    let description = index("DESCRIPTION");
+    let extra: Vec<(usize, &String)> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| {
+            !Record::FIELDS
+                .iter()
+                .any(|field| field.eq_ignore_ascii_case(name))
+        })
+        .collect();
+
+    let mut rows = stmt.query([])?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next()? {
+        let tx_type = text(row, tx_type.0)?.unwrap_or_default();
+        let status = text(row, status.0)?.unwrap_or_default();
+
+        records.push(Record {
+            tx_id: number(row, tx_id)?,
+            tx_type: TxType::from_str(&tx_type)
+                .map_err(|e| CustomError::InvalidEnum(format!("TX_TYPE: {}", e)))?,
+            from_user_id: number(row, from_user_id)?,
+            to_user_id: number(row, to_user_id)?,
+            amount: number(row, amount)?,
+            timestamp: number(row, timestamp)?,
+            status: Status::from_str(&status)
+                .map_err(|e| CustomError::InvalidEnum(format!("STATUS: {}", e)))?,
+            description: match description {
+                Some(i) => text(row, i)?.unwrap_or_default(),
+                None => String::new(),
+            },
+            extra: extra
+                .iter()
+                .filter_map(|(i, name)| match text(row, *i) {
+                    Ok(value) => value.map(|value| Ok((name.to_string(), value))),
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()?,
+        });
+    }
+    Ok(records)
+}
+
+/// Значение числовой колонки `(номер, поле)`; число может быть записано текстом
+fn number(row: &Row, (i, field): (usize, &str)) -> Result<u64, CustomError> {
+    match row.get_ref(i)? {
+        ValueRef::Integer(value) => {
+            u64::try_from(value).map_err(|_| CustomError::InvalidNumber(field.to_string()))
+        }
+        ValueRef::Text(text) => std::str::from_utf8(text)
+            .ok()
+            .and_then(|text| text.trim().parse().ok())
+            .ok_or_else(|| CustomError::InvalidNumber(field.to_string())),
+        ValueRef::Null => Err(CustomError::MissingField(field.to_string())),
+        _ => Err(CustomError::InvalidNumber(field.to_string())),
+    }
+}
+
+/// Значение колонки текстом; `NULL` — `None`
+fn text(row: &Row, i: usize) -> Result<Option<String>, CustomError> {
+    Ok(match row.get_ref(i)? {
+        ValueRef::Null => None,
+        ValueRef::Integer(value) => Some(value.to_string()),
+        ValueRef::Real(value) => Some(value.to_string()),
+        ValueRef::Text(text) | ValueRef::Blob(text) => {
+            Some(String::from_utf8_lossy(text).into_owned())
+        }
+    })
+}
+
+/// Число для колонки `INTEGER`: SQLite хранит только знаковые 64-битные целые
+fn integer(value: u64, field: &str) -> Result<Value, CustomError> {
+    i64::try_from(value).map(Value::Integer).map_err(|_| {
+        CustomError::InvalidData(format!(
+            "{} {} не помещается в INTEGER SQLite",
+            field, value
+        ))
+    })
+}
+
+/// Идентификатор SQL в двойных кавычках
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Строковый литерал SQL в одинарных кавычках
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl From<Vec<Record>> for SqliteFormat {
+    fn from(records: Vec<Record>) -> Self {
+        SqliteFormat::with_options(records, &FormatOptions::default())
+    }
+}
+
+impl From<SqliteFormat> for Vec<Record> {
+    fn from(sqlite: SqliteFormat) -> Self {
+        sqlite.sqlite_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(tx_id: u64, amount: u64, extra: &[(&str, &str)]) -> Record {
+        Record {
+            tx_id,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 7,
+            to_user_id: i64::MAX as u64,
+            amount,
+            timestamp: 1_633_036_860_000 + tx_id,
+            status: Status::PENDING,
+            description: format!("Record '{}'", tx_id),
+            extra: extra
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn sqlite_image_round_trip_and_query() -> Result<(), CustomError> {
+        let records = vec![record(1, 100, &[("CHANNEL", "WEB")]), record(2, 200, &[])];
+        let mut buf = Vec::new();
+        SqliteFormat::from(records.clone()).write_to(&mut buf)?;
+        assert!(buf.starts_with(b"SQLite format 3\0"));
+
+        assert_eq!(
+            SqliteFormat::from_read(&mut buf.as_slice())?.sqlite_rows,
+            records
+        );
+
+        let options = FormatOptions {
+            query: Some("SELECT tx_id, tx_type, from_user_id, to_user_id, amount * 2 AS amount, timestamp, status FROM transactions WHERE tx_id > 1".to_string()),
+            ..FormatOptions::default()
+        };
+        let queried = SqliteFormat::read(&mut buf.as_slice(), &options)?.sqlite_rows;
+        assert_eq!(queried.len(), 1);
+        assert_eq!(queried[0].amount, 400);
+        assert_eq!(queried[0].description, "");
+
+        assert!(
+            SqliteFormat::from(vec![record(3, u64::MAX, &[])])
+                .write_to(&mut Vec::new())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sqlite_file_append_and_upsert_by_tx_id() -> Result<(), CustomError> {
+        std::fs::create_dir_all("static")?;
+        let path = Path::new("static").join("sqlite_modes_test.sqlite");
+        let _ = std::fs::remove_file(&path);
+        let mode = |sqlite_mode| FormatOptions {
+            table: Some("payments".to_string()),
+            sqlite_mode,
+            ..FormatOptions::default()
+        };
+
+        write_database(&path, &[record(1, 100, &[])], &mode(SqliteMode::Create))?;
+        assert!(write_database(&path, &[record(1, 100, &[])], &mode(SqliteMode::Create)).is_err());
+        write_database(
+            &path,
+            &[record(2, 200, &[("CHANNEL", "WEB")])],
+            &mode(SqliteMode::Append),
+        )?;
+        assert!(write_database(&path, &[record(2, 1, &[])], &mode(SqliteMode::Append)).is_err());
+        write_database(
+            &path,
+            &[record(1, 150, &[]), record(3, 300, &[])],
+            &mode(SqliteMode::Upsert),
+        )?;
+
+        let conn = Connection::open(&path)?;
+        let indexes: i64 = conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type = 'index' AND tbl_name = 'payments'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(indexes, 4);
+        let records = query(&conn, &mode(SqliteMode::Create))?;
+        drop(conn);
+        std::fs::remove_file(&path)?;
+
+        let amounts: Vec<_> = records.iter().map(|r| (r.tx_id, r.amount)).collect();
+        assert_eq!(amounts, vec![(1, 150), (2, 200), (3, 300)]);
+        assert_eq!(records[1].extra["CHANNEL"], "WEB");
+        assert!(records[0].extra.is_empty());
+        Ok(())
+    }
+}
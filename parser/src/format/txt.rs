@@ -2,8 +2,8 @@ use crate::error::CustomError;
 use crate::format::DataFormat;
 use crate::models::Record;
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::{fmt, mem};
+use std::fmt;
+use std::io::{BufRead, BufReader, Lines, Read, Write};
 
 ///  Текстовый формат
 /// Чтение и создание текстового формата
@@ -14,39 +14,58 @@ pub struct TxtFormat {
 
 impl DataFormat for TxtFormat {
     fn from_read<R: Read>(r: &mut R) -> Result<Self, CustomError> {
-        let reader = BufReader::new(r);
-        let mut txt_rows: Vec<Record> = Vec::new();
+        let txt_rows = TxtRecords::new(r).collect::<Result<Vec<Record>, _>>()?;
 
-        let mut current_block_data: HashMap<String, String> = HashMap::new();
+        Ok(Self { txt_rows })
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        for (i, record) in self.txt_rows.iter().enumerate() {
+            check_extra(record)?;
+            writeln!(writer, "# Record {} ({:?})", i + 1, record.tx_type)?;
+            writeln!(writer, "{}", record)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Потоковое чтение TXT: отдаёт записи по одной по мере чтения блоков
+pub struct TxtRecords<R: Read> {
+    lines: Lines<BufReader<R>>,
+}
+
+impl<R: Read> TxtRecords<R> {
+    /// Создаёт итератор записей поверх источника
+    pub fn new(r: R) -> Self {
+        Self {
+            lines: BufReader::new(r).lines(),
+        }
+    }
+}
+
+impl<R: Read> Iterator for TxtRecords<R> {
+    type Item = Result<Record, CustomError>;
 
-        let items: Vec<_> = reader.lines().collect();
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut current_block_data: HashMap<String, String> = HashMap::new();
 
-        for item in items {
-            match item {
-                Ok(line) => {
+        loop {
+            match self.lines.next() {
+                Some(Ok(line)) => {
                     if !line.starts_with('#') && !line.is_empty() {
                         if let Some((k, v)) = line.split_once(':') {
                             current_block_data.insert(k.trim().to_string(), v.trim().to_string());
                         }
                     } else if !current_block_data.is_empty() {
-                        let record = TxtFormat::created_record(mem::take(&mut current_block_data));
-                        txt_rows.push(record?);
+                        return Some(TxtFormat::created_record(current_block_data));
                     }
                 }
-                Err(e) => return Err(e.into()),
+                Some(Err(e)) => return Some(Err(e.into())),
+                None if current_block_data.is_empty() => return None,
+                None => return Some(TxtFormat::created_record(current_block_data)),
             }
         }
-
-        Ok(Self { txt_rows })
-    }
-
-    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
-        for (i, record) in self.txt_rows.iter().enumerate() {
-            writeln!(writer, "# Record {} ({:?})", i + 1, record.tx_type)?;
-            writeln!(writer, "{}", record)?;
-        }
-
-        Ok(())
     }
 }
 
@@ -58,7 +77,7 @@ fn get_num(map: &HashMap<String, String>, key: &str) -> Result<u64, CustomError>
 }
 
 impl TxtFormat {
-    fn created_record(payload: HashMap<String, String>) -> Result<Record, CustomError> {
+    pub(crate) fn created_record(payload: HashMap<String, String>) -> Result<Record, CustomError> {
         Ok(Record {
             tx_id: get_num(&payload, "TX_ID")?,
             tx_type: payload
@@ -90,10 +109,49 @@ impl TxtFormat {
                 .trim_start_matches('"')
                 .trim_end_matches('"')
                 .to_string(),
+            extra: payload
+                .into_iter()
+                .filter(|(key, _)| !Record::FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key, unquote(&value).to_string()))
+                .collect(),
         })
     }
 }
 
+/// Снимает одну пару двойных кавычек вокруг значения, если она есть
+pub(crate) fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Проверяет, что дополнительные поля записи выражаются строками
+/// `КЛЮЧ: "значение"`: ключ без `:`, переводов строк и пробелов по краям,
+/// не начинается с `#` и не совпадает с полем записи, значение — в одну строку
+pub(crate) fn check_extra(record: &Record) -> Result<(), CustomError> {
+    for (key, value) in &record.extra {
+        let bad_key = key.is_empty()
+            || key.trim() != key
+            || key.starts_with('#')
+            || key.contains([':', '\n', '\r'])
+            || Record::FIELDS.contains(&key.as_str());
+        if bad_key {
+            return Err(CustomError::InvalidData(format!(
+                "TX_ID {}: ключ дополнительного поля {:?} не записать в TXT",
+                record.tx_id, key
+            )));
+        }
+        if value.contains(['\n', '\r']) {
+            return Err(CustomError::InvalidData(format!(
+                "TX_ID {}: значение поля {} с переводом строки не записать в TXT",
+                record.tx_id, key
+            )));
+        }
+    }
+    Ok(())
+}
+
 impl fmt::Display for Record {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "TX_ID: {:?}", self.tx_id)?;
@@ -104,6 +162,9 @@ impl fmt::Display for Record {
         writeln!(f, "TIMESTAMP: {:?}", self.timestamp)?;
         writeln!(f, "STATUS: {:?}", self.status)?;
         writeln!(f, "DESCRIPTION: \"{}\"", self.description)?;
+        for (key, value) in &self.extra {
+            writeln!(f, "{}: \"{}\"", key, value)?;
+        }
 
         Ok(())
     }
@@ -125,6 +186,7 @@ impl From<TxtFormat> for Vec<Record> {
 mod tests {
     use super::*;
     use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
     use std::io::Cursor;
 
     fn txt_record() -> Record {
@@ -137,12 +199,14 @@ mod tests {
             tx_type: TxType::DEPOSIT,
             status: Status::FAILURE,
             description: "Record number 1".to_string(),
+            extra: BTreeMap::new(),
         }
     }
 
     #[test]
     fn txt_write_then_read() -> Result<(), CustomError> {
-        let rec = txt_record();
+        let mut rec = txt_record();
+        rec.extra.insert("CHANNEL".to_string(), "web".to_string());
 
         let mut txt = TxtFormat {
             txt_rows: vec![rec.clone()],
@@ -164,4 +228,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn txt_extra_values_are_quoted() -> Result<(), CustomError> {
+        let mut rec = txt_record();
+        rec.extra
+            .insert("NOTE".to_string(), "  a: \"b\"  ".to_string());
+        rec.extra.insert("EMPTY".to_string(), String::new());
+
+        let mut buf = Vec::new();
+        TxtFormat::from(vec![rec.clone()]).write_to(&mut buf)?;
+        assert!(String::from_utf8_lossy(&buf).contains("\nNOTE: \"  a: \"b\"  \"\n"));
+        assert_eq!(
+            TxtFormat::from_read(&mut buf.as_slice())?.txt_rows,
+            vec![rec]
+        );
+
+        for (key, value) in [
+            ("NOTE", "two\nlines"),
+            ("A:B", "x"),
+            ("#TAG", "x"),
+            (" PAD", "x"),
+            ("TX_ID", "7"),
+        ] {
+            let mut bad = txt_record();
+            bad.extra.insert(key.to_string(), value.to_string());
+            let err = TxtFormat::from(vec![bad]).write_to(&mut Vec::new());
+            assert!(matches!(err, Err(CustomError::InvalidData(_))), "{:?}", key);
+        }
+        Ok(())
+    }
 }
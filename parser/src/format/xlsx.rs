@@ -0,0 +1,261 @@
+use crate::error::CustomError;
+use crate::format::DataFormat;
+use crate::format::csv::{extra_columns, header};
+use crate::models::{Format, Record, Status, TxType};
+use rust_xlsxwriter::{Format as CellFormat, Workbook, Worksheet};
+use std::io::{Read, Write};
+
+/// Наибольшее целое, которое Excel хранит без потери точности (15 цифр)
+const EXCEL_EXACT: u128 = 999_999_999_999_999;
+/// Строк на листе Excel, включая заголовок
+const EXCEL_ROWS: usize = 1_048_576;
+/// Дней от 1899-12-30 (начало отсчёта дат Excel) до 1970-01-01
+const UNIX_EPOCH_DAYS: f64 = 25_569.0;
+/// Миллисекунд в сутках
+const DAY_MS: f64 = 86_400_000.0;
+
+/// Книга Excel: лист записей и лист итогов
+///
+/// Формат только для записи. На листе `Transactions` — по строке на запись:
+/// числовые поля — числа, `TIMESTAMP` — дата и время UTC, `TX_TYPE`, `STATUS`,
+/// описание и дополнительные поля — текст. Заголовок закреплён, на колонки
+/// установлен автофильтр. Числа длиннее 15 цифр Excel округляет, поэтому
+/// они пишутся текстом. На листе `Summary` — количество и сумма `AMOUNT`
+/// по `TX_TYPE` и по `STATUS`.
+#[derive(Debug)]
+pub struct XlsxFormat {
+    pub xlsx_rows: Vec<Record>,
+}
+
+impl DataFormat for XlsxFormat {
+    fn from_read<R: Read>(_r: &mut R) -> Result<Self, CustomError> {
+        Err(super::write_only(&Format::Xlsx))
+    }
+
+    fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<(), CustomError> {
+        if self.xlsx_rows.len() >= EXCEL_ROWS {
+            return Err(CustomError::InvalidData(format!(
+                "{} записей не помещаются на лист Excel (не больше {})",
+                self.xlsx_rows.len(),
+                EXCEL_ROWS - 1
+            )));
+        }
+
+        let mut workbook = Workbook::new();
+        let bold = CellFormat::new().set_bold();
+        write_transactions(workbook.add_worksheet(), &self.xlsx_rows, &bold)?;
+        write_summary(workbook.add_worksheet(), &self.xlsx_rows, &bold)?;
+
+        writer.write_all(&workbook.save_to_buffer()?)?;
+        Ok(())
+    }
+}
+
+/// Лист записей с закреплённым заголовком и автофильтром
+fn write_transactions(
+    sheet: &mut Worksheet,
+    records: &[Record],
+    bold: &CellFormat,
+) -> Result<(), CustomError> {
+    let datetime = CellFormat::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+    let extra = extra_columns(records);
+    let names = header(&extra)?;
+
+    sheet.set_name("Transactions")?;
+    for (col, name) in names.iter().enumerate() {
+        let width = match *name {
+            "TX_TYPE" | "STATUS" => 12.0,
+            "TIMESTAMP" => 20.0,
+            "DESCRIPTION" => 40.0,
+            _ => 18.0,
+        };
+        sheet.set_column_width(col as u16, width)?;
+        sheet.write_string_with_format(0, col as u16, *name, bold)?;
+    }
+
+    for (i, record) in records.iter().enumerate() {
+        let row = i as u32 + 1;
+        write_integer(sheet, row, 0, record.tx_id as u128)?;
+        sheet.write_string(row, 1, format!("{:?}", record.tx_type))?;
+        write_integer(sheet, row, 2, record.from_user_id as u128)?;
+        write_integer(sheet, row, 3, record.to_user_id as u128)?;
+        write_integer(sheet, row, 4, record.amount as u128)?;
+        let days = UNIX_EPOCH_DAYS + record.timestamp as f64 / DAY_MS;
+        sheet.write_number_with_format(row, 5, days, &datetime)?;
+        sheet.write_string(row, 6, format!("{:?}", record.status))?;
+        sheet.write_string(row, 7, &record.description)?;
+        for (offset, key) in extra.iter().enumerate() {
+            if let Some(value) = record.extra.get(key) {
+                sheet.write_string(row, (Record::FIELDS.len() + offset) as u16, value)?;
+            }
+        }
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, records.len() as u32, names.len() as u16 - 1)?;
+    Ok(())
+}
+
+/// Лист итогов: количество и сумма по типам и по статусам
+fn write_summary(
+    sheet: &mut Worksheet,
+    records: &[Record],
+    bold: &CellFormat,
+) -> Result<(), CustomError> {
+    let by_type = [TxType::DEPOSIT, TxType::TRANSFER, TxType::WITHDRAWAL]
+        .map(|tx_type| totals(records, |r| r.tx_type == tx_type, format!("{:?}", tx_type)));
+    let by_status = [Status::SUCCESS, Status::FAILURE, Status::PENDING]
+        .map(|status| totals(records, |r| r.status == status, format!("{:?}", status)));
+
+    sheet.set_name("Summary")?;
+    sheet.set_column_width(0, 14.0)?;
+    sheet.set_column_width(1, 12.0)?;
+    sheet.set_column_width(2, 22.0)?;
+
+    let mut row = 0;
+    for (title, groups) in [("TX_TYPE", by_type), ("STATUS", by_status)] {
+        for (col, name) in [title, "COUNT", "AMOUNT"].into_iter().enumerate() {
+            sheet.write_string_with_format(row, col as u16, name, bold)?;
+        }
+        row += 1;
+
+        let all = totals(records, |_| true, "TOTAL".to_string());
+        for (name, count, amount) in groups.iter().chain([&all]) {
+            let format = match name.as_str() {
+                "TOTAL" => bold.clone(),
+                _ => CellFormat::new(),
+            };
+            sheet.write_string_with_format(row, 0, name, &format)?;
+            sheet.write_number_with_format(row, 1, *count as f64, &format)?;
+            match *amount <= EXCEL_EXACT {
+                true => sheet.write_number_with_format(row, 2, *amount as f64, &format)?,
+                false => sheet.write_string_with_format(row, 2, amount.to_string(), &format)?,
+            };
+            row += 1;
+        }
+        row += 1;
+    }
+    Ok(())
+}
+
+/// Имя группы, число записей и сумма `AMOUNT` записей, подходящих под `filter`
+fn totals(
+    records: &[Record],
+    filter: impl Fn(&Record) -> bool,
+    name: String,
+) -> (String, u64, u128) {
+    records
+        .iter()
+        .filter(|r| filter(r))
+        .fold((name, 0, 0), |(name, count, amount), r| {
+            (name, count + 1, amount + r.amount as u128)
+        })
+}
+
+/// Целое числом, а если Excel его округлит — текстом
+fn write_integer(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: u128,
+) -> Result<(), CustomError> {
+    match value <= EXCEL_EXACT {
+        true => sheet.write_number(row, col, value as f64)?,
+        false => sheet.write_string(row, col, value.to_string())?,
+    };
+    Ok(())
+}
+
+impl From<Vec<Record>> for XlsxFormat {
+    fn from(records: Vec<Record>) -> Self {
+        XlsxFormat { xlsx_rows: records }
+    }
+}
+
+impl From<XlsxFormat> for Vec<Record> {
+    fn from(xlsx: XlsxFormat) -> Self {
+        xlsx.xlsx_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{EncodeWriter, Encoding};
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+    use zip::ZipArchive;
+
+    fn part(xlsx: &[u8], name: &str) -> String {
+        let mut archive = ZipArchive::new(Cursor::new(xlsx)).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn xlsx_writes_typed_sheet_and_summary() -> Result<(), CustomError> {
+        let record = |tx_id, tx_type, status, amount| Record {
+            tx_id,
+            tx_type,
+            from_user_id: 7,
+            to_user_id: u64::MAX,
+            amount,
+            timestamp: 1_633_036_860_000,
+            status,
+            description: "Rent & bills".to_string(),
+            extra: BTreeMap::from([("CHANNEL".to_string(), "WEB".to_string())]),
+        };
+        let records = vec![
+            record(1, TxType::DEPOSIT, Status::SUCCESS, 1_000),
+            record(2, TxType::TRANSFER, Status::SUCCESS, 250),
+            record(3, TxType::DEPOSIT, Status::FAILURE, 500),
+        ];
+
+        let mut buf = Vec::new();
+        XlsxFormat::from(records).write_to(&mut buf)?;
+
+        let workbook = part(&buf, "xl/workbook.xml");
+        assert!(workbook.contains(r#"<sheet name="Transactions""#));
+        assert!(workbook.contains(r#"<sheet name="Summary""#));
+
+        let sheet = part(&buf, "xl/worksheets/sheet1.xml");
+        assert!(sheet.contains(r#"<pane ySplit="1" topLeftCell="A2""#));
+        assert!(sheet.contains(r#"<autoFilter ref="A1:I4"/>"#));
+        // TIMESTAMP — дата Excel 2021-09-30 21:21:00
+        assert!(sheet.contains("<v>44469.889583333"));
+        // u64::MAX длиннее 15 цифр и пишется текстом
+        assert!(part(&buf, "xl/sharedStrings.xml").contains("18446744073709551615"));
+
+        let summary = part(&buf, "xl/worksheets/sheet2.xml");
+        // DEPOSIT: 2 записи на 1500, итог по статусам: 3 записи на 1750
+        assert!(summary.contains(r#"<c r="B2"><v>2</v></c><c r="C2"><v>1500</v></c>"#));
+        assert!(summary.contains("<v>1750</v>"));
+        Ok(())
+    }
+
+    #[test]
+    fn xlsx_ignores_output_encoding() -> Result<(), CustomError> {
+        let record = Record {
+            tx_id: 1,
+            tx_type: TxType::DEPOSIT,
+            from_user_id: 0,
+            to_user_id: 7,
+            amount: 100,
+            timestamp: 0,
+            status: Status::SUCCESS,
+            description: "Пополнение".to_string(),
+            extra: BTreeMap::new(),
+        };
+
+        let mut writer = EncodeWriter::new(Vec::new(), Format::Xlsx, Encoding::Windows1251);
+        crate::write_records(Format::Xlsx, vec![record], &mut writer)?;
+        let xlsx = writer.finish()?;
+        assert!(part(&xlsx, "xl/sharedStrings.xml").contains("Пополнение"));
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::CustomError;
+use serde::Deserialize;
+use std::io::Read;
+
+/// Имя колонки-заполнителя: при чтении пропускается, при записи заполняется `PAD`
+pub const FILLER: &str = "FILLER";
+
+/// Выравнивание значения в колонке
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    ///Значение прижато влево, заполнитель справа
+    #[default]
+    Left,
+    ///Значение прижато вправо, заполнитель слева
+    Right,
+}
+
+/// Колонка записи фиксированной ширины
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    ///Имя поля: `TX_ID`, …, `DESCRIPTION`, дополнительное поле или `FILLER`
+    pub name: String,
+    ///Позиция первого символа, с нуля
+    pub start: usize,
+    ///Ширина в символах
+    pub width: usize,
+    ///Выравнивание
+    pub align: Align,
+    ///Символ заполнения
+    pub pad: char,
+}
+
+impl Column {
+    /// Значение колонки без заполнителя.
+    ///
+    /// Заполнитель снимается со стороны, противоположной выравниванию;
+    /// колонка из одних цифр-заполнителей (`0000`) читается как `0`.
+    pub fn trim<'a>(&self, value: &'a str) -> &'a str {
+        let trimmed = match self.align {
+            Align::Left => value.trim_end_matches(self.pad),
+            Align::Right => value.trim_start_matches(self.pad),
+        };
+        match trimmed.is_empty() && self.pad.is_ascii_digit() && !value.is_empty() {
+            true => &value[value.len() - self.pad.len_utf8()..],
+            false => trimmed,
+        }
+    }
+
+    /// Дополняет значение заполнителем до ширины колонки
+    pub fn pad(&self, value: &str) -> Result<String, CustomError> {
+        let len = value.chars().count();
+        if len > self.width {
+            return Err(CustomError::InvalidData(format!(
+                "значение {} {:?} длиннее колонки ({} символов)",
+                self.name, value, self.width
+            )));
+        }
+        let fill: String = std::iter::repeat_n(self.pad, self.width - len).collect();
+        Ok(match self.align {
+            Align::Left => format!("{}{}", value, fill),
+            Align::Right => format!("{}{}", fill, value),
+        })
+    }
+}
+
+/// Раскладка записи фиксированной ширины: позиции, ширины, выравнивание
+/// и заполнитель колонок
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    columns: Vec<Column>,
+}
+
+/// Строка файла раскладки
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct LayoutRow {
+    field: String,
+    start: usize,
+    width: usize,
+    #[serde(default)]
+    align: Option<Align>,
+    #[serde(default)]
+    pad: Option<String>,
+}
+
+impl Layout {
+    /// Раскладка из колонок; колонки не должны пересекаться
+    pub fn new(mut columns: Vec<Column>) -> Result<Self, CustomError> {
+        columns.sort_by_key(|c| c.start);
+        for pair in columns.windows(2) {
+            if pair[0].start + pair[0].width > pair[1].start {
+                return Err(CustomError::InvalidData(format!(
+                    "колонки {} и {} раскладки пересекаются",
+                    pair[0].name, pair[1].name
+                )));
+            }
+        }
+        if let Some(column) = columns.iter().find(|c| c.width == 0) {
+            return Err(CustomError::InvalidData(format!(
+                "колонка {} раскладки нулевой ширины",
+                column.name
+            )));
+        }
+        Ok(Layout { columns })
+    }
+
+    /// Читает файл раскладки CSV с заголовком `FIELD,START,WIDTH,ALIGN,PAD`.
+    ///
+    /// `START` отсчитывается с 1, `ALIGN` — `left` (по умолчанию) или `right`,
+    /// `PAD` — один символ, по умолчанию пробел.
+    pub fn from_read<R: Read>(r: R) -> Result<Self, CustomError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(r);
+        let mut columns = Vec::new();
+        for row in reader.deserialize() {
+            let row: LayoutRow = row?;
+            let start = row.start.checked_sub(1).ok_or_else(|| {
+                CustomError::InvalidData(format!("START колонки {} начинается с 1", row.field))
+            })?;
+            let mut pad = row.pad.as_deref().unwrap_or(" ").chars();
+            let pad = match (pad.next(), pad.next()) {
+                (None, _) => ' ',
+                (Some(c), None) => c,
+                (Some(_), Some(_)) => {
+                    return Err(CustomError::InvalidData(format!(
+                        "PAD колонки {} должен быть одним символом",
+                        row.field
+                    )));
+                }
+            };
+            columns.push(Column {
+                name: row.field,
+                start,
+                width: row.width,
+                align: row.align.unwrap_or_default(),
+                pad,
+            });
+        }
+        Layout::new(columns)
+    }
+
+    /// Колонки по возрастанию позиции
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Длина записи: конец последней колонки
+    pub fn record_len(&self) -> usize {
+        self.columns.last().map_or(0, |c| c.start + c.width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_reads_columns_and_pads_values() -> Result<(), CustomError> {
+        let layout = Layout::from_read(
+            "FIELD,START,WIDTH,ALIGN,PAD\n\
+             AMOUNT,6,8,right,0\n\
+             TX_ID,1,5,,\n"
+                .as_bytes(),
+        )?;
+
+        let columns = layout.columns();
+        assert_eq!(columns[0].name, "TX_ID");
+        assert_eq!(columns[1].start, 5);
+        assert_eq!(layout.record_len(), 13);
+        assert_eq!(columns[1].pad("1250")?, "00001250");
+        assert_eq!(columns[1].trim("00001250"), "1250");
+        assert_eq!(columns[1].trim("00000000"), "0");
+        assert_eq!(columns[0].trim("7    "), "7");
+        assert!(columns[0].pad("123456").is_err());
+
+        assert!(Layout::from_read("FIELD,START,WIDTH\nA,1,5\nB,3,2\n".as_bytes()).is_err());
+        Ok(())
+    }
+}
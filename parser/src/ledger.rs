@@ -0,0 +1,273 @@
+use crate::error::CustomError;
+use crate::models::{Record, Status, TxType};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Формат выгрузки балансов и проводок
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum ExportFormat {
+    ///Формат CSV
+    #[value(name = "csv")]
+    Csv,
+    ///Формат JSON
+    #[value(name = "json")]
+    Json,
+}
+
+/// Итоговый баланс пользователя
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct Balance {
+    ///идентификатор пользователя
+    pub user_id: u64,
+    ///остаток: сумма поступлений минус сумма списаний
+    pub balance: i128,
+    ///сумма всех поступлений
+    pub credited: u128,
+    ///сумма всех списаний
+    pub debited: u128,
+    ///количество транзакций, затронувших пользователя
+    pub tx_count: u64,
+}
+
+/// Одна проводка: изменение баланса пользователя транзакцией
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct LedgerEntry {
+    ///транзакция, породившая проводку
+    pub tx_id: u64,
+    ///время транзакции
+    pub timestamp: u64,
+    ///пользователь, чей баланс изменился
+    pub user_id: u64,
+    ///изменение баланса (отрицательное для списаний)
+    pub delta: i128,
+    ///нарастающий итог после проводки
+    pub balance: i128,
+}
+
+/// Уход баланса пользователя в минус после списания
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct Overdraft {
+    ///транзакция, вызвавшая овердрафт
+    pub tx_id: u64,
+    ///время транзакции
+    pub timestamp: u64,
+    ///пользователь с отрицательным балансом
+    pub user_id: u64,
+    ///баланс после списания
+    pub balance: i128,
+}
+
+/// Бухгалтерская книга по транзакциям.
+///
+/// Учитываются только транзакции со статусом `SUCCESS`:
+/// - `DEPOSIT` зачисляет `amount` на `to_user_id`,
+/// - `WITHDRAWAL` списывает `amount` с `from_user_id`,
+/// - `TRANSFER` списывает с `from_user_id` и зачисляет на `to_user_id`;
+///   перевод самому себе баланс не меняет и не учитывается.
+#[derive(Debug, Default)]
+pub struct Ledger {
+    balances: BTreeMap<u64, Balance>,
+    entries: Vec<LedgerEntry>,
+    overdrafts: Vec<Overdraft>,
+}
+
+impl Ledger {
+    /// Строит книгу, применяя успешные транзакции в порядке `timestamp`
+    /// (при равных `timestamp` — в порядке `tx_id`).
+    pub fn from_records(records: &[Record]) -> Self {
+        let mut ordered: Vec<&Record> = records
+            .iter()
+            .filter(|r| r.status == Status::SUCCESS)
+            .collect();
+        ordered.sort_by_key(|r| (r.timestamp, r.tx_id));
+
+        let mut ledger = Ledger::default();
+        for record in ordered {
+            ledger.apply(record);
+        }
+        ledger
+    }
+
+    /// Применяет одну транзакцию. Неуспешные транзакции игнорируются.
+    pub fn apply(&mut self, record: &Record) {
+        if record.status != Status::SUCCESS {
+            return;
+        }
+
+        let amount = record.amount as i128;
+        match record.tx_type {
+            TxType::DEPOSIT => self.post(record, record.to_user_id, amount),
+            TxType::WITHDRAWAL => self.post(record, record.from_user_id, -amount),
+            TxType::TRANSFER if record.from_user_id == record.to_user_id => {}
+            TxType::TRANSFER => {
+                self.post(record, record.from_user_id, -amount);
+                self.post(record, record.to_user_id, amount);
+            }
+        }
+    }
+
+    fn post(&mut self, record: &Record, user_id: u64, delta: i128) {
+        let balance = self.balances.entry(user_id).or_insert(Balance {
+            user_id,
+            balance: 0,
+            credited: 0,
+            debited: 0,
+            tx_count: 0,
+        });
+
+        balance.balance += delta;
+        balance.tx_count += 1;
+        if delta >= 0 {
+            balance.credited += delta as u128;
+        } else {
+            balance.debited += delta.unsigned_abs();
+        }
+
+        self.entries.push(LedgerEntry {
+            tx_id: record.tx_id,
+            timestamp: record.timestamp,
+            user_id,
+            delta,
+            balance: balance.balance,
+        });
+
+        if delta < 0 && balance.balance < 0 {
+            self.overdrafts.push(Overdraft {
+                tx_id: record.tx_id,
+                timestamp: record.timestamp,
+                user_id,
+                balance: balance.balance,
+            });
+        }
+    }
+
+    /// Балансы пользователей, упорядоченные по `user_id`
+    pub fn balances(&self) -> Vec<Balance> {
+        self.balances.values().cloned().collect()
+    }
+
+    /// Баланс конкретного пользователя, если у него были проводки
+    pub fn balance(&self, user_id: u64) -> Option<i128> {
+        self.balances.get(&user_id).map(|b| b.balance)
+    }
+
+    /// Все проводки с нарастающим итогом в порядке применения
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    /// Случаи ухода баланса в минус в порядке возникновения
+    pub fn overdrafts(&self) -> &[Overdraft] {
+        &self.overdrafts
+    }
+}
+
+/// Выгружает строки (балансы, проводки или овердрафты) в CSV или JSON
+pub fn export<T: Serialize, W: Write>(
+    rows: &[T],
+    format: ExportFormat,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    match format {
+        ExportFormat::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                csv_writer.serialize(row)?;
+            }
+            csv_writer.flush()?;
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, rows)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(tx_id: u64, tx_type: TxType, from: u64, to: u64, amount: u64, ts: u64) -> Record {
+        Record {
+            tx_id,
+            tx_type,
+            from_user_id: from,
+            to_user_id: to,
+            amount,
+            timestamp: ts,
+            status: Status::SUCCESS,
+            description: String::new(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn ledger_applies_in_timestamp_order() {
+        let mut failed = tx(4, TxType::DEPOSIT, 0, 2, 1000, 5);
+        failed.status = Status::FAILURE;
+
+        let records = vec![
+            tx(3, TxType::WITHDRAWAL, 1, 0, 30, 30),
+            tx(2, TxType::TRANSFER, 1, 2, 80, 20),
+            tx(1, TxType::DEPOSIT, 0, 1, 100, 10),
+            failed,
+        ];
+
+        let ledger = Ledger::from_records(&records);
+
+        assert_eq!(ledger.balance(1), Some(-10));
+        assert_eq!(ledger.balance(2), Some(80));
+        assert_eq!(ledger.balance(0), None);
+
+        let running: Vec<_> = ledger
+            .entries()
+            .iter()
+            .filter(|e| e.user_id == 1)
+            .map(|e| e.balance)
+            .collect();
+        assert_eq!(running, vec![100, 20, -10]);
+
+        assert_eq!(ledger.overdrafts().len(), 1);
+        assert_eq!(ledger.overdrafts()[0].tx_id, 3);
+        assert_eq!(ledger.overdrafts()[0].balance, -10);
+    }
+
+    #[test]
+    fn ledger_ignores_transfer_to_self() {
+        let ledger = Ledger::from_records(&[
+            tx(1, TxType::DEPOSIT, 0, 5, 10, 1),
+            tx(2, TxType::TRANSFER, 5, 5, 40, 2),
+        ]);
+
+        assert_eq!(ledger.balance(5), Some(10));
+        assert!(ledger.overdrafts().is_empty());
+        assert_eq!(ledger.entries().len(), 1);
+        assert_eq!(ledger.balances()[0].tx_count, 1);
+    }
+
+    #[test]
+    fn ledger_export_csv_and_json() -> Result<(), CustomError> {
+        let ledger = Ledger::from_records(&[tx(1, TxType::DEPOSIT, 0, 7, 50, 1)]);
+
+        let mut csv_buf = Vec::new();
+        export(&ledger.balances(), ExportFormat::Csv, &mut csv_buf)?;
+        assert_eq!(
+            String::from_utf8(csv_buf).unwrap(),
+            "USER_ID,BALANCE,CREDITED,DEBITED,TX_COUNT\n7,50,50,0,1\n"
+        );
+
+        let mut json_buf = Vec::new();
+        export(&ledger.balances(), ExportFormat::Json, &mut json_buf)?;
+        let value: serde_json::Value = serde_json::from_slice(&json_buf).unwrap();
+        assert_eq!(value[0]["USER_ID"], 7);
+        assert_eq!(value[0]["BALANCE"], 50);
+
+        Ok(())
+    }
+}
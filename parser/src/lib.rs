@@ -80,18 +80,70 @@
 //!     - `CsvFormat`
 //!     - `TxtFormat`
 //!     - `BinFormat`
+//!     - `Camt053Format` — выписки ISO 20022 camt.053 (XML)
+//!     - `Pain001Format` — платёжные поручения ISO 20022 pain.001 (XML, только запись)
+//!     - `Bai2Format` — файлы BAI2 банков США (только чтение)
+//!     - `OfxFormat`, `QifFormat` — выписки OFX (SGML и XML) и QIF для одного
+//!       пользователя (только запись)
+//!     - `PgCopyFormat` — текстовые и бинарные данные PostgreSQL `COPY`
+//!       (только запись); с feature `postgres` записи загружаются в базу
+//!       напрямую, см. `load_postgres`
+//!
+//! - [`accounts`](crate::accounts) — справочник счетов пользователей
+//!   (`USER_ID,ACCOUNT,NAME,BIC`) для выгрузки платёжных поручений pain.001.
+//!
+//! - [`merge`](crate::merge) — трёхстороннее сравнение двух копий с общей
+//!   базой и объединение изменений при отсутствии конфликтов.
+//!
+//! - [`parallel`](crate::parallel) — параллельный разбор CSV и BIN на
+//!   пуле потоков `rayon` с сохранением порядка записей.
+//!
+//! - [`partition`](crate::partition) — разбиение записей по дате, типу,
+//!   статусу или размеру на несколько файлов по шаблону пути.
+//!
+//! - `asynchronous` (feature `async`) — чтение CSV/TXT/BIN из `AsyncRead`
+//!   в виде `Stream<Item = Result<Record, CustomError>>` и запись в `AsyncWrite`.
+//!
+//! - [`compression`](crate::compression) — прозрачное сжатие gzip/zstd/bzip2:
+//!   при чтении определяется по магическим байтам или расширению файла.
+//!
+//! - [`encoding`](crate::encoding) — перекодирование входа и выхода из/в
+//!   Windows-1251, CP866 и Latin-1, автоопределение BOM UTF-8 и UTF-16.
+//!
+//! - [`compare`](crate::compare) — позиционное сравнение и сравнение по
+//!   `tx_id` с белым списком полей, исключениями и нормализацией значений.
+//!
+//! - [`ledger`](crate::ledger) — балансы пользователей, проводки с нарастающим
+//!   итогом и обнаружение овердрафтов.
+//!
+//! - [`reconcile`](crate::reconcile) — сверка двух источников с допусками
+//!   по времени, сумме и описанию, отчёты в CSV и HTML.
+//!
+//! - [`schema`](crate::schema) — чтение и запись CSV/TXT/BIN для любой
+//!   плоской `Serialize + DeserializeOwned`-структуры, не только `Record`
+//!   (например, записей с комиссией, валютой и каналом).
+//!
+//! - [`stats`](crate::stats) — сводная статистика (количество, суммы,
+//!   временной диапазон, активные пользователи) за один проход по файлу.
+//!
+//! - [`wire`](crate::wire) — serde `Serializer`/`Deserializer` для
+//!   big-endian кодирования BIN: любую `Serialize`-структуру можно записать
+//!   кадрами `magic` + длина + тело, как записи `Record`.
 //!
 //! - [`error`](crate::error) — перечисление [`CustomError`](crate::error::CustomError),
 //!   объединяющее все возможные ошибки чтения и записи.
 //!
 //! ## Использование вместе с CLI
 //!
-//! В проекте есть две утилиты, использующие эту библиотеку:
+//! В проекте есть утилиты, использующие эту библиотеку:
 //!
-//! - **data_parser** — конвертирует файлы между форматами.
+//! - **converter** — конвертирует файлы между форматами.
 //! - **comparer** — сравнивает два файла построчно на уровне [`Record`].
+//! - **ledger** — считает балансы пользователей и ищет овердрафты.
+//! - единый **data_parser** с подкомандами `convert`, `compare`, `validate`,
+//!   `stats`, `inspect` и `ledger`; прежние утилиты — обёртки над ним.
 //!
-//! Обе утилиты работают одинаково благодаря единому внутреннему формату данных.
+//! Все утилиты работают одинаково благодаря единому внутреннему формату данных.
 //!
 //! ## Ошибки
 //!
@@ -101,18 +153,64 @@
 mod error;
 mod format;
 
+/// Справочник счетов пользователей для платёжных поручений
+pub mod accounts;
+/// Асинхронное (tokio) чтение и запись форматов, feature `async`
+#[cfg(feature = "async")]
+pub mod asynchronous;
+/// Сравнение записей по выбранным полям с нормализацией
+pub mod compare;
+/// Прозрачное сжатие и распаковка файлов (gzip, zstd, bzip2)
+pub mod compression;
+/// Кодировки текста: Windows-1251, CP866, Latin-1 и определение BOM
+pub mod encoding;
+/// Раскладка колонок формата фиксированной ширины
+pub mod layout;
+/// Учёт балансов пользователей по успешным транзакциям
+pub mod ledger;
+/// Трёхстороннее сравнение и объединение копий общего файла
+pub mod merge;
 /// Файл содержит необходимые структуры данных
 pub mod models;
+/// Параллельный разбор больших файлов CSV и BIN
+pub mod parallel;
+/// Разбиение записей на несколько выходных файлов
+pub mod partition;
+/// Сверка двух источников с допусками
+pub mod reconcile;
+/// Чтение и запись CSV/TXT/BIN для произвольной serde-схемы
+pub mod schema;
+/// Сводная статистика по файлу транзакций
+pub mod stats;
+/// Serde-формат big-endian с префиксом длины, на котором построен BIN
+pub mod wire;
 
+use crate::compression::{Compression, Encoder};
+use crate::encoding::{EncodeWriter, Encoding};
 use crate::format::DataFormat;
 use crate::models::Record;
 use error::CustomError;
+use format::bai2::Bai2Format;
 use format::bin::BinFormat;
+use format::camt053::Camt053Format;
+use format::cbor::CborFormat;
+#[cfg(feature = "columnar")]
+use format::columnar::{ArrowFormat, ParquetFormat};
 use format::csv::CsvFormat;
+use format::fixed::{FixedWidthFormat, missing_layout};
+use format::msgpack::MsgpackFormat;
+use format::ofx::{OfxFormat, OfxVersion};
+use format::pain001::Pain001Format;
+use format::pgcopy::{CopyMode, PgCopyFormat};
+use format::qif::QifFormat;
+use format::sqlite::SqliteFormat;
 use format::txt::TxtFormat;
+use format::xlsx::XlsxFormat;
 use models::Format;
+use models::FormatOptions;
 use models::InputFormat;
 use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
 /// Читает файл из директории `static/` и парсит его в один из форматов.
@@ -125,27 +223,189 @@ use std::path::{Path, PathBuf};
 /// * `Ok(InputFormat)` — обёртка над конкретным форматом (Csv/Txt/Bin),
 ///   из которой потом можно достать `Vec<Record>` через `get_record()`
 /// * `Err(CustomError)` — если файл не удалось открыть/прочитать/распарсить.
+pub fn file_reader(filename: &str, file_type: Format) -> Result<InputFormat, CustomError> {
+    path_reader(
+        &Path::new("static").join(filename),
+        file_type,
+        Encoding::default(),
+        &FormatOptions::default(),
+    )
+}
+
+/// Открывает файл для чтения: распаковывает и перекодирует его в UTF-8
+fn open(path: &Path, file_type: &Format, encoding: Encoding) -> Result<Box<dyn Read>, CustomError> {
+    let file: File = File::open(path)?;
+    let file = compression::decoder(BufReader::new(file), Compression::from_path(path))?;
+    encoding::decoder(file, file_type, encoding)
+}
+
+/// Читает файл по произвольному пути и парсит его в один из форматов.
 ///
+/// То же, что [`file_reader`], но без привязки к директории `static/`.
+/// Сжатые файлы (gzip, zstd, bzip2) распаковываются на лету, см. [`compression`];
+/// текст перекодируется из `encoding`, см. [`encoding::decoder`];
+/// `options` — параметры отдельных форматов (раскладка фиксированной ширины).
+pub fn path_reader(
+    path: &Path,
+    file_type: Format,
+    encoding: Encoding,
+    options: &FormatOptions,
+) -> Result<InputFormat, CustomError> {
+    let mut file = open(path, &file_type, encoding)?;
 
-pub fn file_reader(filename: &str, file_type: Format) -> Result<InputFormat, CustomError> {
-    let path: PathBuf = Path::new("static").join(filename);
-    let mut file: File = File::open(path)?;
-
-    let file_format = if file_type == Format::Csv {
-        InputFormat::Csv(CsvFormat::from_read(&mut file)?)
-    } else if file_type == Format::Txt {
-        InputFormat::Txt(TxtFormat::from_read(&mut file)?)
-    } else if file_type == Format::Bin {
-        InputFormat::Bin(BinFormat::from_read(&mut file)?)
-    } else {
-        return Err(CustomError::InvalidEnum(format!(
-            "Неверный формат: {:?}",
-            file_type
-        )));
+    let file_format = match file_type {
+        Format::Csv => InputFormat::Csv(CsvFormat::from_read(&mut file)?),
+        Format::Txt => InputFormat::Txt(TxtFormat::from_read(&mut file)?),
+        Format::Bin => InputFormat::Bin(BinFormat::from_read(&mut file)?),
+        Format::Camt053 => InputFormat::Camt053(Camt053Format::from_read(&mut file)?),
+        Format::Bai2 => InputFormat::Bai2(Bai2Format::from_read(&mut file)?),
+        Format::Sqlite => InputFormat::Sqlite(SqliteFormat::read(&mut file, options)?),
+        Format::Msgpack => InputFormat::Msgpack(MsgpackFormat::from_read(&mut file)?),
+        Format::Cbor => InputFormat::Cbor(CborFormat::from_read(&mut file)?),
+        #[cfg(feature = "columnar")]
+        Format::Parquet => InputFormat::Parquet(ParquetFormat::from_read(&mut file)?),
+        #[cfg(feature = "columnar")]
+        Format::Arrow => InputFormat::Arrow(ArrowFormat::from_read(&mut file)?),
+        Format::Fixed => InputFormat::Fixed(FixedWidthFormat::read(
+            &mut file,
+            options.layout.as_ref().ok_or_else(missing_layout)?,
+        )?),
+        Format::Pain001
+        | Format::Ofx
+        | Format::OfxXml
+        | Format::Qif
+        | Format::Xlsx
+        | Format::PgCopy
+        | Format::PgCopyBinary => {
+            return Err(format::write_only(&file_type));
+        }
     };
     Ok(file_format)
 }
 
+/// То же, что [`path_reader`], но CSV и BIN разбираются параллельно
+/// в `threads` потоках (`0` — по числу ядер), см. [`parallel::read_records`].
+pub fn path_reader_parallel(
+    path: &Path,
+    file_type: Format,
+    encoding: Encoding,
+    options: &FormatOptions,
+    threads: usize,
+) -> Result<InputFormat, CustomError> {
+    let mut file = open(path, &file_type, encoding)?;
+    let records = parallel::read_records(&mut file, file_type.clone(), options, threads)?;
+
+    Ok(match file_type {
+        Format::Csv => InputFormat::Csv(CsvFormat::from(records)),
+        Format::Txt => InputFormat::Txt(TxtFormat::from(records)),
+        Format::Bin => InputFormat::Bin(BinFormat::from(records)),
+        Format::Camt053 => InputFormat::Camt053(Camt053Format::from(records)),
+        Format::Bai2 => InputFormat::Bai2(Bai2Format::from(records)),
+        Format::Sqlite => InputFormat::Sqlite(SqliteFormat::with_options(records, options)),
+        Format::Msgpack => InputFormat::Msgpack(MsgpackFormat::from(records)),
+        Format::Cbor => InputFormat::Cbor(CborFormat::from(records)),
+        #[cfg(feature = "columnar")]
+        Format::Parquet => InputFormat::Parquet(ParquetFormat::from(records)),
+        #[cfg(feature = "columnar")]
+        Format::Arrow => InputFormat::Arrow(ArrowFormat::from(records)),
+        Format::Fixed => InputFormat::Fixed(FixedWidthFormat::new(
+            records,
+            options.layout.clone().ok_or_else(missing_layout)?,
+        )),
+        Format::Pain001
+        | Format::Ofx
+        | Format::OfxXml
+        | Format::Qif
+        | Format::Xlsx
+        | Format::PgCopy
+        | Format::PgCopyBinary => {
+            return Err(format::write_only(&file_type));
+        }
+    })
+}
+
+/// Открывает файл из директории `static/` и читает его записи потоково.
+///
+/// В отличие от [`file_reader`] не загружает все записи в память:
+/// каждая запись разбирается при обращении к итератору, что позволяет
+/// обработать файл за один проход (см. [`stats::Stats`]).
+pub fn file_records(
+    filename: &str,
+    file_type: Format,
+) -> Result<impl Iterator<Item = Result<Record, CustomError>> + use<>, CustomError> {
+    path_records(
+        &Path::new("static").join(filename),
+        file_type,
+        Encoding::default(),
+        &FormatOptions::default(),
+    )
+}
+
+/// Потоковое чтение файла по произвольному пути, см. [`file_records`]
+pub fn path_records(
+    path: &Path,
+    file_type: Format,
+    encoding: Encoding,
+    options: &FormatOptions,
+) -> Result<impl Iterator<Item = Result<Record, CustomError>> + use<>, CustomError> {
+    let reader = open(path, &file_type, encoding)?;
+
+    Ok(format::records(file_type, reader, options))
+}
+
+/// Создаёт файл и записывает в него записи в указанном формате и
+/// кодировке, при необходимости сжимая их; `options` — параметры
+/// отдельных форматов, см. [`write_records_with`].
+///
+/// Путь используется как есть: расширение сжатия к нему не добавляется,
+/// для этого есть [`Compression::with_extension`]. Несжатая база SQLite
+/// открывается на месте, чтобы дописывать в неё записи.
+pub fn write_file(
+    path: &Path,
+    type_output: Format,
+    compression: Compression,
+    encoding: Encoding,
+    options: &FormatOptions,
+    records: Vec<Record>,
+) -> Result<(), CustomError> {
+    if type_output == Format::Sqlite && compression == Compression::None {
+        return format::sqlite::write_database(path, &records, options);
+    }
+
+    let file = File::create(path)?;
+    let encoder = Encoder::new(BufWriter::new(file), compression)?;
+    let mut writer = EncodeWriter::new(encoder, type_output.clone(), encoding);
+
+    write_records_with(type_output, records, &mut writer, options)?;
+    writer.finish()?.finish()?;
+    Ok(())
+}
+
+/// Загружает записи в таблицу PostgreSQL `options.table` (по умолчанию
+/// `transactions`) по строке подключения `url`, feature `postgres`.
+///
+/// `type_output` выбирает вариант COPY: `PgCopy` — текстовый, `PgCopyBinary` —
+/// бинарный. Возвращает число загруженных строк.
+#[cfg(feature = "postgres")]
+pub fn load_postgres(
+    url: &str,
+    type_output: Format,
+    options: &FormatOptions,
+    records: Vec<Record>,
+) -> Result<u64, CustomError> {
+    let mode = match type_output {
+        Format::PgCopy => CopyMode::Text,
+        Format::PgCopyBinary => CopyMode::Binary,
+        _ => {
+            return Err(CustomError::InvalidData(format!(
+                "в PostgreSQL загружаются только форматы pgcopy и pgcopy-binary, не {:?}",
+                type_output
+            )));
+        }
+    };
+    format::postgres::load(url, options.table.as_deref(), mode, records)
+}
+
 /// Конвертирует уже прочитанные данные (`InputFormat`) в указанный формат
 /// и записывает результат в файл `static/output.<type_output>`.
 ///
@@ -168,36 +428,71 @@ pub fn file_reader(filename: &str, file_type: Format) -> Result<InputFormat, Cus
 /// `"Input format not recognized"` и всё равно возвращает `Ok(())`.
 pub fn converter(type_output: Format, input_format: InputFormat) -> Result<(), CustomError> {
     let records: Vec<Record> = input_format.get_record();
-    let filename = match type_output {
-        Format::Csv => format!("output.csv"),
-        Format::Txt => format!("output.txt"),
-        Format::Bin => format!("output.bin"),
-    };
+    let filename = format!("output.{}", type_output.extension());
     let path: PathBuf = Path::new("static").join(filename);
     let mut file = File::create(path)?;
 
+    write_records(type_output, records, &mut file)
+}
+
+/// Записывает записи в любой приёмник в указанном формате.
+///
+/// Общая точка записи для [`converter`] и [`partition`]: выбирает реализацию
+/// `DataFormat` по `type_output` и вызывает её `write_to()`.
+pub fn write_records<W: Write>(
+    type_output: Format,
+    records: Vec<Record>,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    write_records_with(type_output, records, writer, &FormatOptions::default())
+}
+
+/// То же, что [`write_records`], с параметрами отдельных форматов:
+/// выписки OFX и QIF пишутся для пользователя `options.user_id`,
+/// формат фиксированной ширины — по раскладке `options.layout`, Parquet
+/// и Arrow — с размером группы строк и сжатием из `options`.
+pub fn write_records_with<W: Write>(
+    type_output: Format,
+    records: Vec<Record>,
+    writer: &mut W,
+    options: &FormatOptions,
+) -> Result<(), CustomError> {
     match type_output {
-        Format::Csv => {
-            let mut out: CsvFormat = CsvFormat::from(records);
-            out.write_to(&mut file)?;
+        Format::Csv => CsvFormat::from(records).write_to(writer),
+        Format::Txt => TxtFormat::from(records).write_to(writer),
+        Format::Bin => BinFormat::from(records).write_to(writer),
+        Format::Camt053 => Camt053Format::from(records).write_to(writer),
+        Format::Pain001 => Pain001Format::from(records).write_to(writer),
+        Format::Ofx => {
+            OfxFormat::for_user(records, OfxVersion::Sgml, options.user_id).write_to(writer)
         }
-        Format::Txt => {
-            let mut out: TxtFormat = TxtFormat::from(records);
-            out.write_to(&mut file)?;
+        Format::OfxXml => {
+            OfxFormat::for_user(records, OfxVersion::Xml, options.user_id).write_to(writer)
         }
-        Format::Bin => {
-            let mut out: BinFormat = BinFormat::from(records);
-            out.write_to(&mut file)?;
+        Format::Qif => QifFormat::for_user(records, options.user_id).write_to(writer),
+        Format::Bai2 => Bai2Format::from(records).write_to(writer),
+        Format::Sqlite => SqliteFormat::with_options(records, options).write_to(writer),
+        Format::Msgpack => MsgpackFormat::from(records).write_to(writer),
+        Format::Xlsx => XlsxFormat::from(records).write_to(writer),
+        Format::Cbor => CborFormat::from(records).write_to(writer),
+        Format::PgCopy => PgCopyFormat::new(records, CopyMode::Text).write_to(writer),
+        Format::PgCopyBinary => PgCopyFormat::new(records, CopyMode::Binary).write_to(writer),
+        #[cfg(feature = "columnar")]
+        Format::Parquet => ParquetFormat::with_options(records, options).write_to(writer),
+        #[cfg(feature = "columnar")]
+        Format::Arrow => ArrowFormat::with_options(records, options).write_to(writer),
+        Format::Fixed => {
+            let layout = options.layout.clone().ok_or_else(missing_layout)?;
+            FixedWidthFormat::new(records, layout).write_to(writer)
         }
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
     use std::fs;
 
     fn sample_record() -> Record {
@@ -210,10 +505,10 @@ mod tests {
             timestamp: 123456789,
             status: Status::SUCCESS,
             description: "Test record".to_string(),
+            extra: BTreeMap::new(),
         }
     }
 
-
     #[test]
     fn file_reader_reads_csv_round() -> Result<(), CustomError> {
         fs::create_dir_all("static").unwrap();
@@ -256,6 +551,44 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn extra_columns_survive_csv_bin_csv() -> Result<(), CustomError> {
+        let csv = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION,CHANNEL,FEE\n\
+                   1,DEPOSIT,0,10,100,1000,SUCCESS,a,web,5\n\
+                   2,DEPOSIT,0,10,100,1000,SUCCESS,b,,\n";
+        let records = CsvFormat::from_read(&mut csv.as_bytes())?.csv_rows;
+        assert_eq!(records[0].extra["CHANNEL"], "web");
+        assert!(records[1].extra.is_empty());
+
+        let mut bin = Vec::new();
+        write_records(Format::Bin, records.clone(), &mut bin)?;
+        let mut txt = Vec::new();
+        write_records(
+            Format::Txt,
+            BinFormat::from_read(&mut bin.as_slice())?.bin_rows,
+            &mut txt,
+        )?;
+        let mut back = Vec::new();
+        write_records(
+            Format::Csv,
+            TxtFormat::from_read(&mut txt.as_slice())?.txt_rows,
+            &mut back,
+        )?;
+        assert_eq!(String::from_utf8_lossy(&back), csv);
+
+        let json = serde_json::to_string(&records)?;
+        assert!(json.contains(r#""EXTRA":{"CHANNEL":"web","FEE":"5"}"#));
+        assert_eq!(serde_json::from_str::<Vec<Record>>(&json)?, records);
+
+        // дополнительное поле с именем поля записи дало бы вторую колонку AMOUNT
+        let mut clash = records[0].clone();
+        clash.extra.insert("AMOUNT".to_string(), "7".to_string());
+        let err = write_records(Format::Csv, vec![clash], &mut Vec::new());
+        assert!(matches!(err, Err(CustomError::InvalidData(_))));
+
+        Ok(())
+    }
+
     #[test]
     fn file_reader_nonexistent_file() {
         let res = file_reader("no_such_file.csv", Format::Csv);
@@ -0,0 +1,370 @@
+use crate::compare::OutputFormat;
+use crate::error::CustomError;
+use crate::models::Record;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
+
+/// Классификация записи при трёхстороннем сравнении
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStatus {
+    /// Запись не менялась ни с одной стороны
+    Unchanged,
+    /// Запись изменена (добавлена, удалена) только в левой копии
+    ChangedLeft,
+    /// Запись изменена (добавлена, удалена) только в правой копии
+    ChangedRight,
+    /// Обе копии изменили запись одинаково или в разных полях
+    ChangedBoth,
+    /// Копии изменили одно и то же поле по-разному
+    Conflicting,
+}
+
+impl MergeStatus {
+    /// Все классификации в порядке вывода сводки
+    pub const ALL: [MergeStatus; 5] = [
+        MergeStatus::Unchanged,
+        MergeStatus::ChangedLeft,
+        MergeStatus::ChangedRight,
+        MergeStatus::ChangedBoth,
+        MergeStatus::Conflicting,
+    ];
+}
+
+impl fmt::Display for MergeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MergeStatus::Unchanged => "unchanged",
+            MergeStatus::ChangedLeft => "changed-left",
+            MergeStatus::ChangedRight => "changed-right",
+            MergeStatus::ChangedBoth => "changed-both",
+            MergeStatus::Conflicting => "conflicting",
+        })
+    }
+}
+
+/// Состояние одной записи (по `tx_id`) в базе и обеих копиях
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MergeRow {
+    ///ключ записи
+    pub tx_id: u64,
+    ///классификация
+    pub status: MergeStatus,
+    ///запись в базовом файле
+    pub base: Option<Record>,
+    ///запись в левой копии
+    pub left: Option<Record>,
+    ///запись в правой копии
+    pub right: Option<Record>,
+}
+
+/// Результат трёхстороннего сравнения
+#[derive(Debug, Default)]
+pub struct ThreeWay {
+    ///строки в порядке базового файла, затем добавленные слева, затем справа
+    pub rows: Vec<MergeRow>,
+}
+
+/// Сравнивает две производные копии с общей базой по `tx_id`.
+///
+/// Изменением копии считается любое отличие записи от базовой. Запись,
+/// изменённая с обеих сторон, объединяется по полям (см. [`merge_fields`]);
+/// если одно поле изменено по-разному, это конфликт. Отсутствие записи
+/// в копии — это её удаление, отсутствие в базе — добавление.
+pub fn three_way(base: &[Record], left: &[Record], right: &[Record]) -> ThreeWay {
+    let index = |records: &[Record]| -> HashMap<u64, Record> {
+        records.iter().map(|r| (r.tx_id, r.clone())).collect()
+    };
+    let (mut base_by_id, mut left_by_id, mut right_by_id) =
+        (index(base), index(left), index(right));
+
+    let mut seen: HashSet<u64> = HashSet::new();
+    let keys: Vec<u64> = base
+        .iter()
+        .chain(left)
+        .chain(right)
+        .map(|r| r.tx_id)
+        .filter(|tx_id| seen.insert(*tx_id))
+        .collect();
+
+    let rows = keys
+        .into_iter()
+        .map(|tx_id| {
+            let base = base_by_id.remove(&tx_id);
+            let left = left_by_id.remove(&tx_id);
+            let right = right_by_id.remove(&tx_id);
+
+            let status = match (left == base, right == base) {
+                (true, true) => MergeStatus::Unchanged,
+                (false, true) => MergeStatus::ChangedLeft,
+                (true, false) => MergeStatus::ChangedRight,
+                (false, false) if merge_rows(&base, &left, &right).is_some() => {
+                    MergeStatus::ChangedBoth
+                }
+                (false, false) => MergeStatus::Conflicting,
+            };
+
+            MergeRow {
+                tx_id,
+                status,
+                base,
+                left,
+                right,
+            }
+        })
+        .collect();
+
+    ThreeWay { rows }
+}
+
+/// Объединяет по полям запись, изменённую в обеих копиях.
+///
+/// Для каждого поля (и каждого ключа `extra`) берётся значение стороны,
+/// которая его изменила; совпадающие изменения допустимы. `None`, если
+/// одно поле изменено по-разному.
+pub fn merge_fields(base: &Record, left: &Record, right: &Record) -> Option<Record> {
+    let extra_keys: BTreeSet<&String> = base
+        .extra
+        .keys()
+        .chain(left.extra.keys())
+        .chain(right.extra.keys())
+        .collect();
+    let mut extra = BTreeMap::new();
+    for key in extra_keys {
+        if let Some(value) = pick(
+            &base.extra.get(key),
+            &left.extra.get(key),
+            &right.extra.get(key),
+        )? {
+            extra.insert(key.clone(), value.clone());
+        }
+    }
+
+    Some(Record {
+        tx_id: base.tx_id,
+        tx_type: pick(&base.tx_type, &left.tx_type, &right.tx_type)?,
+        from_user_id: pick(&base.from_user_id, &left.from_user_id, &right.from_user_id)?,
+        to_user_id: pick(&base.to_user_id, &left.to_user_id, &right.to_user_id)?,
+        amount: pick(&base.amount, &left.amount, &right.amount)?,
+        timestamp: pick(&base.timestamp, &left.timestamp, &right.timestamp)?,
+        status: pick(&base.status, &left.status, &right.status)?,
+        description: pick(&base.description, &left.description, &right.description)?,
+        extra,
+    })
+}
+
+/// Значение поля после объединения: изменённое одной стороной или
+/// одинаково обеими; `None` — конфликт
+fn pick<T: PartialEq + Clone>(base: &T, left: &T, right: &T) -> Option<T> {
+    if left == base {
+        Some(right.clone())
+    } else if right == base || left == right {
+        Some(left.clone())
+    } else {
+        None
+    }
+}
+
+/// Объединённая версия записи, изменённой в обеих копиях: одинаковые
+/// версии (в том числе удаление с обеих сторон) или объединение по полям
+fn merge_rows(
+    base: &Option<Record>,
+    left: &Option<Record>,
+    right: &Option<Record>,
+) -> Option<Option<Record>> {
+    if left == right {
+        return Some(left.clone());
+    }
+    match (base, left, right) {
+        (Some(base), Some(left), Some(right)) => merge_fields(base, left, right).map(Some),
+        _ => None,
+    }
+}
+
+impl ThreeWay {
+    /// Количество записей с данной классификацией
+    pub fn count(&self, status: MergeStatus) -> usize {
+        self.rows.iter().filter(|row| row.status == status).count()
+    }
+
+    /// Есть ли конфликтующие изменения
+    pub fn has_conflicts(&self) -> bool {
+        self.count(MergeStatus::Conflicting) > 0
+    }
+
+    /// Собирает объединённый набор записей.
+    ///
+    /// Для неизменённых записей берётся базовая версия, для изменённых —
+    /// версия изменившей стороны, для изменённых с обеих сторон —
+    /// объединение по полям; удалённые записи пропускаются.
+    /// При наличии конфликтов возвращает ошибку.
+    pub fn merged(&self) -> Result<Vec<Record>, CustomError> {
+        if self.has_conflicts() {
+            return Err(CustomError::InvalidData(format!(
+                "невозможно объединить: конфликтующих записей {}",
+                self.count(MergeStatus::Conflicting)
+            )));
+        }
+
+        Ok(self
+            .rows
+            .iter()
+            .filter_map(|row| match row.status {
+                MergeStatus::Unchanged => row.base.clone(),
+                MergeStatus::ChangedLeft => row.left.clone(),
+                MergeStatus::ChangedRight => row.right.clone(),
+                MergeStatus::ChangedBoth => merge_rows(&row.base, &row.left, &row.right).flatten(),
+                MergeStatus::Conflicting => None,
+            })
+            .collect())
+    }
+
+    /// Записывает сводку и все изменённые записи в выбранном формате
+    pub fn write_to<W: Write>(
+        &self,
+        format: OutputFormat,
+        writer: &mut W,
+    ) -> Result<(), CustomError> {
+        let changed: Vec<&MergeRow> = self
+            .rows
+            .iter()
+            .filter(|row| row.status != MergeStatus::Unchanged)
+            .collect();
+
+        match format {
+            OutputFormat::Json => {
+                #[derive(Serialize)]
+                struct Document<'a> {
+                    summary: BTreeMap<String, usize>,
+                    conflicts: bool,
+                    records: &'a [&'a MergeRow],
+                }
+
+                let summary = MergeStatus::ALL
+                    .iter()
+                    .map(|status| (status.to_string(), self.count(*status)))
+                    .collect();
+                let document = Document {
+                    summary,
+                    conflicts: self.has_conflicts(),
+                    records: &changed,
+                };
+                serde_json::to_writer_pretty(&mut *writer, &document)?;
+                writeln!(writer)?;
+            }
+            OutputFormat::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                csv_writer.write_record(["TX_ID", "STATUS"])?;
+                for row in changed {
+                    csv_writer.write_record([row.tx_id.to_string(), row.status.to_string()])?;
+                }
+                csv_writer.flush()?;
+            }
+            OutputFormat::Text => {
+                for status in MergeStatus::ALL {
+                    writeln!(writer, "{}: {}", status, self.count(status))?;
+                }
+                for row in changed {
+                    writeln!(writer, "TX_ID {}: {}", row.tx_id, row.status)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+
+    fn record(tx_id: u64, amount: u64, description: &str) -> Record {
+        Record {
+            tx_id,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 1,
+            to_user_id: 2,
+            amount,
+            timestamp: 0,
+            status: Status::PENDING,
+            description: description.to_string(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn three_way_classifies_and_merges() -> Result<(), CustomError> {
+        let base = vec![
+            record(1, 10, "a"),
+            record(2, 20, "b"),
+            record(3, 30, "c"),
+            record(4, 40, "d"),
+        ];
+        let left = vec![
+            record(1, 10, "a"),
+            record(2, 21, "b"),
+            record(3, 30, "c"),
+            record(4, 41, "d"),
+            record(5, 50, "new"),
+        ];
+        let right = vec![record(1, 10, "a"), record(2, 20, "b"), record(4, 41, "d")];
+
+        let result = three_way(&base, &left, &right);
+        let statuses: Vec<_> = result.rows.iter().map(|r| (r.tx_id, r.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, MergeStatus::Unchanged),
+                (2, MergeStatus::ChangedLeft),
+                (3, MergeStatus::ChangedRight),
+                (4, MergeStatus::ChangedBoth),
+                (5, MergeStatus::ChangedLeft),
+            ]
+        );
+
+        let merged = result.merged()?;
+        let ids: Vec<_> = merged.iter().map(|r| (r.tx_id, r.amount)).collect();
+        assert_eq!(ids, vec![(1, 10), (2, 21), (4, 41), (5, 50)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn three_way_conflicts_block_merge() {
+        let base = vec![record(1, 10, "a")];
+        let left = vec![record(1, 11, "a")];
+        let right = vec![record(1, 12, "a")];
+
+        let result = three_way(&base, &left, &right);
+        assert!(result.has_conflicts());
+        assert!(matches!(result.merged(), Err(CustomError::InvalidData(_))));
+
+        // правка поля с одной стороны и удаление с другой — тоже конфликт
+        let result = three_way(&base, &left, &[]);
+        assert!(result.has_conflicts());
+    }
+
+    #[test]
+    fn three_way_merges_edits_of_different_fields() -> Result<(), CustomError> {
+        let mut base = record(1, 10, "a");
+        base.extra.insert("CHANNEL".to_string(), "WEB".to_string());
+        let mut left = record(1, 10, "edited");
+        left.extra.insert("CHANNEL".to_string(), "WEB".to_string());
+        left.extra.insert("CURRENCY".to_string(), "RUB".to_string());
+        let right = record(1, 11, "a");
+
+        let result = three_way(&[base], &[left], &[right]);
+        assert_eq!(result.rows[0].status, MergeStatus::ChangedBoth);
+
+        let merged = result.merged()?;
+        assert_eq!(merged[0].description, "edited");
+        assert_eq!(merged[0].amount, 11);
+        assert_eq!(
+            merged[0].extra,
+            BTreeMap::from([("CURRENCY".to_string(), "RUB".to_string())])
+        );
+        Ok(())
+    }
+}
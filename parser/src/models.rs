@@ -1,8 +1,20 @@
+use crate::compression::Compression;
+use crate::format::bai2::Bai2Format;
 use crate::format::bin::BinFormat;
+use crate::format::camt053::Camt053Format;
+use crate::format::cbor::CborFormat;
+#[cfg(feature = "columnar")]
+use crate::format::columnar::{ArrowFormat, ParquetFormat};
 use crate::format::csv::CsvFormat;
+use crate::format::fixed::FixedWidthFormat;
+use crate::format::msgpack::MsgpackFormat;
+use crate::format::sqlite::SqliteFormat;
 use crate::format::txt::TxtFormat;
+use crate::layout::Layout;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 use strum_macros::EnumString;
 
 ///Перечисление принимаемых форматов
@@ -17,6 +29,186 @@ pub enum Format {
     ///Формат Bin
     #[value(name = "bin")]
     Bin,
+    ///Выписка ISO 20022 camt.053 (XML)
+    #[value(name = "camt053")]
+    Camt053,
+    ///Платёжное поручение ISO 20022 pain.001 (XML, только запись)
+    #[value(name = "pain001")]
+    Pain001,
+    ///Выписка OFX 1.0.2 (SGML) для одного пользователя, только запись
+    #[value(name = "ofx")]
+    Ofx,
+    ///Выписка OFX 2.1.1 (XML) для одного пользователя, только запись
+    #[value(name = "ofx-xml")]
+    OfxXml,
+    ///Выписка QIF для одного пользователя, только запись
+    #[value(name = "qif")]
+    Qif,
+    ///Файл BAI2 банков США, только чтение
+    #[value(name = "bai2")]
+    Bai2,
+    ///База SQLite с таблицей записей
+    #[value(name = "sqlite")]
+    Sqlite,
+    ///Книга Excel с листом итогов, только запись
+    #[value(name = "xlsx")]
+    Xlsx,
+    ///Поток записей MessagePack
+    #[value(name = "msgpack")]
+    Msgpack,
+    ///Последовательность записей CBOR
+    #[value(name = "cbor")]
+    Cbor,
+    ///Текстовые данные PostgreSQL `COPY ... FROM STDIN`, только запись
+    #[value(name = "pgcopy")]
+    PgCopy,
+    ///Бинарные данные PostgreSQL `COPY ... FROM STDIN (FORMAT binary)`, только запись
+    #[value(name = "pgcopy-binary")]
+    PgCopyBinary,
+    ///Формат фиксированной ширины по раскладке, формат входа указывается явно
+    #[value(name = "fixed")]
+    Fixed,
+    ///Apache Parquet, feature `columnar`
+    #[cfg(feature = "columnar")]
+    #[value(name = "parquet")]
+    Parquet,
+    ///Файл Arrow IPC, feature `columnar`
+    #[cfg(feature = "columnar")]
+    #[value(name = "arrow")]
+    Arrow,
+}
+
+impl Format {
+    /// Строки формата по спецификации хранятся в UTF-8: кодировка
+    /// к нему не применяется
+    pub fn utf8_only(&self) -> bool {
+        match self {
+            Format::Camt053
+            | Format::Pain001
+            | Format::Ofx
+            | Format::OfxXml
+            | Format::Sqlite
+            | Format::Xlsx
+            | Format::Msgpack
+            | Format::Cbor
+            | Format::PgCopyBinary => true,
+            #[cfg(feature = "columnar")]
+            Format::Parquet | Format::Arrow => true,
+            _ => false,
+        }
+    }
+
+    /// Расширение файла, соответствующее формату
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Txt => "txt",
+            Format::Bin => "bin",
+            Format::Camt053 | Format::Pain001 => "xml",
+            Format::Ofx | Format::OfxXml => "ofx",
+            Format::Qif => "qif",
+            Format::Bai2 => "bai",
+            Format::Sqlite => "sqlite",
+            Format::Xlsx => "xlsx",
+            Format::Msgpack => "msgpack",
+            Format::Cbor => "cbor",
+            Format::PgCopy => "copy",
+            Format::PgCopyBinary => "pgcopy",
+            Format::Fixed => "dat",
+            #[cfg(feature = "columnar")]
+            Format::Parquet => "parquet",
+            #[cfg(feature = "columnar")]
+            Format::Arrow => "arrow",
+        }
+    }
+
+    /// Определяет формат по расширению файла (регистр не важен).
+    ///
+    /// Расширение сжатия отбрасывается: `a.csv.gz` — это CSV.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let path = match Compression::from_path(path) {
+            Some(_) => Path::new(path.file_stem()?),
+            None => path,
+        };
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Format::Csv),
+            "txt" => Some(Format::Txt),
+            "bin" => Some(Format::Bin),
+            "xml" => Some(Format::Camt053),
+            "ofx" => Some(Format::Ofx),
+            "qif" => Some(Format::Qif),
+            "bai" | "bai2" => Some(Format::Bai2),
+            "sqlite" | "sqlite3" | "db" => Some(Format::Sqlite),
+            "msgpack" | "mpk" => Some(Format::Msgpack),
+            "cbor" => Some(Format::Cbor),
+            #[cfg(feature = "columnar")]
+            "parquet" => Some(Format::Parquet),
+            #[cfg(feature = "columnar")]
+            "arrow" | "feather" => Some(Format::Arrow),
+            _ => None,
+        }
+    }
+}
+
+/// Параметры чтения и записи, которые нужны отдельным форматам
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatOptions {
+    ///Пользователь, для которого выгружается выписка OFX или QIF
+    pub user_id: Option<u64>,
+    ///Раскладка формата фиксированной ширины
+    pub layout: Option<Layout>,
+    ///Записей в группе строк Parquet и в пакете Arrow IPC
+    pub row_group_size: Option<usize>,
+    ///Сжатие колонок Parquet и Arrow IPC; по умолчанию Parquet сжимается Snappy,
+    ///Arrow IPC не сжимается
+    pub column_compression: Option<ColumnCompression>,
+    ///Таблица SQLite, по умолчанию `transactions`
+    pub table: Option<String>,
+    ///Запрос SQLite, которым читаются записи вместо всей таблицы
+    pub query: Option<String>,
+    ///Что делать с существующей таблицей SQLite при записи
+    pub sqlite_mode: SqliteMode,
+}
+
+/// Режим записи в существующую таблицу SQLite
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SqliteMode {
+    ///Создать таблицу; если она уже есть — ошибка
+    #[default]
+    #[value(name = "create")]
+    Create,
+    ///Пересоздать таблицу, удалив прежние записи
+    #[value(name = "replace")]
+    Replace,
+    ///Дописать записи; повтор `TX_ID` — ошибка
+    #[value(name = "append")]
+    Append,
+    ///Дописать новые записи и обновить существующие по `TX_ID`
+    #[value(name = "upsert")]
+    Upsert,
+}
+
+/// Сжатие колонок Parquet и Arrow IPC.
+///
+/// Arrow IPC поддерживает только `lz4` и `zstd`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnCompression {
+    ///Без сжатия
+    #[value(name = "none")]
+    None,
+    ///Snappy
+    #[value(name = "snappy")]
+    Snappy,
+    ///Gzip
+    #[value(name = "gzip")]
+    Gzip,
+    ///LZ4
+    #[value(name = "lz4")]
+    Lz4,
+    ///Zstandard
+    #[value(name = "zstd")]
+    Zstd,
 }
 
 /// Перечисление, представляющее входной формат данных.
@@ -34,6 +226,24 @@ pub enum InputFormat {
     Txt(TxtFormat),
     ///Входной формат Bin
     Bin(BinFormat),
+    ///Входной формат camt.053
+    Camt053(Camt053Format),
+    ///Входной формат BAI2
+    Bai2(Bai2Format),
+    ///Входной формат SQLite
+    Sqlite(SqliteFormat),
+    ///Входной формат MessagePack
+    Msgpack(MsgpackFormat),
+    ///Входной формат CBOR
+    Cbor(CborFormat),
+    ///Входной формат фиксированной ширины
+    Fixed(FixedWidthFormat),
+    ///Входной формат Parquet
+    #[cfg(feature = "columnar")]
+    Parquet(ParquetFormat),
+    ///Входной формат Arrow IPC
+    #[cfg(feature = "columnar")]
+    Arrow(ArrowFormat),
 }
 
 impl InputFormat {
@@ -43,6 +253,16 @@ impl InputFormat {
             InputFormat::Csv(csv) => csv.into(),
             InputFormat::Txt(txt) => txt.into(),
             InputFormat::Bin(bin) => bin.into(),
+            InputFormat::Camt053(camt) => camt.into(),
+            InputFormat::Bai2(bai2) => bai2.into(),
+            InputFormat::Sqlite(sqlite) => sqlite.into(),
+            InputFormat::Msgpack(msgpack) => msgpack.into(),
+            InputFormat::Cbor(cbor) => cbor.into(),
+            InputFormat::Fixed(fixed) => fixed.into(),
+            #[cfg(feature = "columnar")]
+            InputFormat::Parquet(parquet) => parquet.into(),
+            #[cfg(feature = "columnar")]
+            InputFormat::Arrow(arrow) => arrow.into(),
         }
     }
 }
@@ -118,6 +338,24 @@ pub struct Record {
     pub status: Status,
     ///произвольное текстовое описание, UTF-8 в двойныхкавычках
     pub description: String,
+    ///дополнительные поля, не входящие в формат: лишние колонки CSV, ключи TXT,
+    ///блок расширений BIN v2; упорядочены по имени
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Record {
+    /// Имена полей формата в порядке колонок CSV и ключей TXT
+    pub const FIELDS: [&'static str; 8] = [
+        "TX_ID",
+        "TX_TYPE",
+        "FROM_USER_ID",
+        "TO_USER_ID",
+        "AMOUNT",
+        "TIMESTAMP",
+        "STATUS",
+        "DESCRIPTION",
+    ];
 }
 
 // pub fn convert_format<A, B>(a: A) -> B
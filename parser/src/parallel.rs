@@ -0,0 +1,196 @@
+use crate::error::CustomError;
+use crate::format::bin::{BinRecords, YPBN};
+use crate::format::csv::CsvRecords;
+use crate::format::records;
+use crate::models::{Format, FormatOptions, Record};
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+use std::io::Read;
+use std::ops::Range;
+
+/// Меньше этого размера фрагмент не делится: накладные расходы потоков
+/// превысят выигрыш
+const MIN_CHUNK: usize = 64 * 1024;
+/// Фрагментов на поток, чтобы выровнять нагрузку при неравных записях
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Читает все записи, разбирая CSV и BIN параллельно.
+///
+/// Источник читается в память целиком и делится на фрагменты по границам
+/// записей: у CSV — по переводам строк вне кавычек, у BIN — по заголовкам
+/// `YPBN` с длиной записи. Фрагменты разбираются в пуле из `threads`
+/// потоков (`0` — по числу ядер), записи собираются в исходном порядке.
+/// Остальные форматы читаются последовательно, `options` — их параметры.
+///
+/// При ошибке возвращается первая по порядку ошибка; номера строк в
+/// сообщениях CSV отсчитываются от начала фрагмента.
+pub fn read_records<R: Read>(
+    r: &mut R,
+    format: Format,
+    options: &FormatOptions,
+    threads: usize,
+) -> Result<Vec<Record>, CustomError> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+
+    if !matches!(format, Format::Csv | Format::Bin) || threads == 1 {
+        return records(format, data.as_slice(), options).collect();
+    }
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| CustomError::InvalidData(format!("пул потоков: {}", e)))?;
+    let chunks = (pool.current_num_threads() * CHUNKS_PER_THREAD)
+        .min(data.len() / MIN_CHUNK)
+        .max(1);
+
+    let parsed: Vec<Result<Vec<Record>, CustomError>> = pool.install(|| match format {
+        Format::Csv => {
+            let header_end = csv_boundaries(&data, 0).first().copied();
+            let (header, body) = data.split_at(header_end.unwrap_or(data.len()));
+
+            split(body.len(), &csv_boundaries(body, body.len() / chunks))
+                .into_par_iter()
+                .map(|range| CsvRecords::new(header.chain(&body[range])).collect())
+                .collect()
+        }
+        Format::Bin => split(data.len(), &bin_boundaries(&data, chunks))
+            .into_par_iter()
+            .map(|range| BinRecords::new(&data[range]).collect())
+            .collect(),
+        _ => unreachable!("параллельно разбираются только CSV и BIN"),
+    });
+
+    let mut result = Vec::new();
+    for chunk in parsed {
+        result.extend(chunk?);
+    }
+    Ok(result)
+}
+
+/// Делит `0..len` на диапазоны по отсортированным границам
+fn split(len: usize, boundaries: &[usize]) -> Vec<Range<usize>> {
+    let mut start = 0;
+    let mut ranges = Vec::with_capacity(boundaries.len() + 1);
+    for &end in boundaries.iter().chain(std::iter::once(&len)) {
+        if end > start {
+            ranges.push(start..end);
+            start = end;
+        }
+    }
+    ranges
+}
+
+/// Позиции сразу после перевода строки вне кавычек, не чаще чем раз в `step` байт
+fn csv_boundaries(data: &[u8], step: usize) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut in_quotes = false;
+    let mut next = step;
+
+    for (pos, byte) in data.iter().enumerate() {
+        match byte {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes && pos + 1 >= next => {
+                boundaries.push(pos + 1);
+                if step == 0 {
+                    break;
+                }
+                next = pos + 1 + step;
+            }
+            _ => {}
+        }
+    }
+    boundaries
+}
+
+/// Начала записей BIN, разбивающие файл на `chunks` частей примерно
+/// поровну по числу записей. Сканирование останавливается на первом
+/// повреждённом заголовке: остаток достаётся последнему фрагменту и даёт
+/// ту же ошибку, что и последовательное чтение.
+fn bin_boundaries(data: &[u8], chunks: usize) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut pos = 0;
+
+    while let Some(header) = data.get(pos..pos + 8) {
+        if header[..4] != YPBN {
+            break;
+        }
+        let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        offsets.push(pos);
+        pos += 8 + size;
+    }
+
+    let per_chunk = offsets.len().div_ceil(chunks).max(1);
+    offsets.into_iter().step_by(per_chunk).skip(1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::DataFormat;
+    use crate::format::bin::BinFormat;
+    use crate::format::csv::CsvFormat;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn records(n: u64) -> Vec<Record> {
+        (0..n)
+            .map(|i| Record {
+                tx_id: i,
+                tx_type: TxType::TRANSFER,
+                from_user_id: i % 7,
+                to_user_id: i % 11,
+                amount: i * 3,
+                timestamp: 1_700_000_000_000 + i,
+                status: Status::SUCCESS,
+                description: format!("line {}\nwith \"quotes\", and comma", i),
+                extra: BTreeMap::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_matches_sequential() -> Result<(), CustomError> {
+        let expected = records(5000);
+
+        let mut csv = Vec::new();
+        CsvFormat::from(expected.clone()).write_to(&mut csv)?;
+        let mut bin = Vec::new();
+        BinFormat::from(expected.clone()).write_to(&mut bin)?;
+
+        for threads in [0, 1, 3] {
+            assert_eq!(
+                read_records(
+                    &mut csv.as_slice(),
+                    Format::Csv,
+                    &FormatOptions::default(),
+                    threads
+                )?,
+                expected
+            );
+            assert_eq!(
+                read_records(
+                    &mut bin.as_slice(),
+                    Format::Bin,
+                    &FormatOptions::default(),
+                    threads
+                )?,
+                expected
+            );
+        }
+
+        bin.truncate(bin.len() - 3);
+        assert!(
+            read_records(
+                &mut bin.as_slice(),
+                Format::Bin,
+                &FormatOptions::default(),
+                4
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+}
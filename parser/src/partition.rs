@@ -0,0 +1,340 @@
+use crate::compression::Compression;
+use crate::encoding::Encoding;
+use crate::error::CustomError;
+use crate::models::{Format, FormatOptions, Record};
+use crate::{write_file, write_records};
+use chrono::DateTime;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Критерий разбиения записей на партиции.
+///
+/// Ключевые критерии (`date`, `tx_type`, `status`) группируют записи
+/// по значению поля, размерные (`records:N`, `bytes:M`) дополнительно
+/// режут каждую группу на части.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartitionBy {
+    /// День транзакции (UTC) в виде `YYYY-MM-DD`, подстановка `{date}`
+    Date,
+    /// Тип транзакции, подстановка `{tx_type}`
+    TxType,
+    /// Статус транзакции, подстановка `{status}`
+    Status,
+    /// Не более N записей в одном файле, подстановка `{part}`
+    Records(usize),
+    /// Не более M байт в одном файле, подстановка `{part}`
+    Bytes(u64),
+}
+
+impl PartitionBy {
+    fn placeholder(&self) -> &'static str {
+        match self {
+            PartitionBy::Date => "{date}",
+            PartitionBy::TxType => "{tx_type}",
+            PartitionBy::Status => "{status}",
+            PartitionBy::Records(_) | PartitionBy::Bytes(_) => "{part}",
+        }
+    }
+}
+
+impl FromStr for PartitionBy {
+    type Err = CustomError;
+
+    /// Разбирает значения `date`, `tx_type`, `status`, `records:N`, `bytes:M`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let limit = |value: &str| {
+            value
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| CustomError::InvalidNumber(s.to_string()))
+        };
+
+        match s.split_once(':') {
+            None => match s {
+                "date" => Ok(PartitionBy::Date),
+                "tx_type" => Ok(PartitionBy::TxType),
+                "status" => Ok(PartitionBy::Status),
+                _ => Err(CustomError::InvalidEnum(format!(
+                    "Неизвестный ключ разбиения: {}",
+                    s
+                ))),
+            },
+            Some(("records", n)) => Ok(PartitionBy::Records(limit(n)? as usize)),
+            Some(("bytes", m)) => Ok(PartitionBy::Bytes(limit(m)?)),
+            Some(_) => Err(CustomError::InvalidEnum(format!(
+                "Неизвестный ключ разбиения: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Одна партиция: путь (относительно выходной директории) и её записи
+#[derive(Debug, PartialEq)]
+pub struct Partition {
+    ///Путь, полученный подстановкой значений в шаблон
+    pub path: PathBuf,
+    ///Записи, попавшие в партицию, в исходном порядке
+    pub records: Vec<Record>,
+}
+
+/// Строит шаблон пути по умолчанию для набора критериев.
+///
+/// Например, для `date,tx_type` получится `output_{date}_{tx_type}.{ext}`.
+pub fn default_template(keys: &[PartitionBy]) -> String {
+    let mut template = String::from("output");
+    for key in keys {
+        let placeholder = key.placeholder();
+        if !template.contains(placeholder) {
+            template.push('_');
+            template.push_str(placeholder);
+        }
+    }
+    template.push_str(".{ext}");
+    template
+}
+
+/// Дата транзакции (UTC) в формате `YYYY-MM-DD`
+pub fn record_date(timestamp: u64) -> String {
+    DateTime::from_timestamp_millis(timestamp as i64)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| String::from("invalid-date"))
+}
+
+/// Раскладывает записи по партициям согласно критериям и шаблону пути.
+///
+/// Шаблон может содержать `{date}`, `{tx_type}`, `{status}`, `{part}` и `{ext}`.
+/// Для каждого выбранного критерия соответствующая подстановка обязательна,
+/// иначе разные партиции записались бы в один файл.
+///
+/// Партиции возвращаются в порядке первого появления, записи внутри —
+/// в исходном порядке. Размер для `bytes:M` считается как сумма размеров
+/// отдельно сериализованных записей в выходном формате.
+pub fn partition(
+    records: Vec<Record>,
+    keys: &[PartitionBy],
+    template: &str,
+    output_format: Format,
+) -> Result<Vec<Partition>, CustomError> {
+    for key in keys {
+        if !template.contains(key.placeholder()) {
+            return Err(CustomError::InvalidData(format!(
+                "шаблон `{}` не содержит {}",
+                template,
+                key.placeholder()
+            )));
+        }
+    }
+
+    let template = template.replace("{ext}", output_format.extension());
+
+    let mut groups: Vec<(String, Vec<Record>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for record in records {
+        let mut name = template.clone();
+        for key in keys {
+            let value = match key {
+                PartitionBy::Date => record_date(record.timestamp),
+                PartitionBy::TxType => format!("{:?}", record.tx_type),
+                PartitionBy::Status => format!("{:?}", record.status),
+                PartitionBy::Records(_) | PartitionBy::Bytes(_) => continue,
+            };
+            name = name.replace(key.placeholder(), &value);
+        }
+
+        let i = *index.entry(name.clone()).or_insert_with(|| {
+            groups.push((name, Vec::new()));
+            groups.len() - 1
+        });
+        groups[i].1.push(record);
+    }
+
+    let max_records = keys.iter().find_map(|key| match key {
+        PartitionBy::Records(n) => Some(*n),
+        _ => None,
+    });
+    let max_bytes = keys.iter().find_map(|key| match key {
+        PartitionBy::Bytes(m) => Some(*m),
+        _ => None,
+    });
+
+    let mut partitions = Vec::new();
+    for (name, group) in groups {
+        let mut part = 0usize;
+        let mut chunk: Vec<Record> = Vec::new();
+        let mut chunk_bytes = 0u64;
+
+        for record in group {
+            let record_bytes = match max_bytes {
+                Some(_) => encoded_len(&output_format, &record)?,
+                None => 0,
+            };
+
+            let records_full = max_records.is_some_and(|n| chunk.len() >= n);
+            let bytes_full = max_bytes.is_some_and(|m| chunk_bytes + record_bytes > m);
+
+            if !chunk.is_empty() && (records_full || bytes_full) {
+                partitions.push(Partition {
+                    path: PathBuf::from(name.replace("{part}", &part.to_string())),
+                    records: std::mem::take(&mut chunk),
+                });
+                part += 1;
+                chunk_bytes = 0;
+            }
+
+            chunk_bytes += record_bytes;
+            chunk.push(record);
+        }
+
+        partitions.push(Partition {
+            path: PathBuf::from(name.replace("{part}", &part.to_string())),
+            records: chunk,
+        });
+    }
+
+    Ok(partitions)
+}
+
+/// Записывает партиции в `dir`, создавая недостающие директории.
+///
+/// Каждая партиция пишется через `write_to()` выбранного формата в кодировке
+/// `encoding` с параметрами `options`; при сжатии к пути добавляется расширение архива (`.gz`, `.zst`, `.bz2`).
+/// Возвращает список созданных файлов.
+pub fn write_partitions(
+    dir: &Path,
+    partitions: Vec<Partition>,
+    output_format: Format,
+    compression: Compression,
+    encoding: Encoding,
+    options: &FormatOptions,
+) -> Result<Vec<PathBuf>, CustomError> {
+    let mut written = Vec::with_capacity(partitions.len());
+
+    for partition in partitions {
+        let path = compression.with_extension(&dir.join(&partition.path));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        write_file(
+            &path,
+            output_format.clone(),
+            compression,
+            encoding,
+            options,
+            partition.records,
+        )?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+fn encoded_len(output_format: &Format, record: &Record) -> Result<u64, CustomError> {
+    let mut buf: Vec<u8> = Vec::new();
+    write_records(output_format.clone(), vec![record.clone()], &mut buf)?;
+    Ok(buf.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn record(tx_id: u64, tx_type: TxType, timestamp: u64) -> Record {
+        Record {
+            tx_id,
+            tx_type,
+            from_user_id: 1,
+            to_user_id: 2,
+            amount: 100,
+            timestamp,
+            status: Status::SUCCESS,
+            description: format!("Record number {}", tx_id),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn partition_by_date_and_type() -> Result<(), CustomError> {
+        let day = 86_400_000;
+        let records = vec![
+            record(1, TxType::DEPOSIT, 1633036860000),
+            record(2, TxType::TRANSFER, 1633036860000),
+            record(3, TxType::DEPOSIT, 1633036860000 + day),
+            record(4, TxType::DEPOSIT, 1633036860000),
+        ];
+
+        let parts = partition(
+            records,
+            &[PartitionBy::Date, PartitionBy::TxType],
+            "out/{date}/{tx_type}.{ext}",
+            Format::Bin,
+        )?;
+
+        let paths: Vec<_> = parts.iter().map(|p| p.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("out/2021-09-30/DEPOSIT.bin"),
+                PathBuf::from("out/2021-09-30/TRANSFER.bin"),
+                PathBuf::from("out/2021-10-01/DEPOSIT.bin"),
+            ]
+        );
+        let ids: Vec<_> = parts[0].records.iter().map(|r| r.tx_id).collect();
+        assert_eq!(ids, vec![1, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn partition_by_size() -> Result<(), CustomError> {
+        let records: Vec<Record> = (0..5)
+            .map(|i| record(i, TxType::DEPOSIT, 1633036860000))
+            .collect();
+        let one = encoded_len(&Format::Bin, &records[0])?;
+
+        let by_count = partition(
+            records.clone(),
+            &[PartitionBy::Records(2)],
+            "part-{part}.{ext}",
+            Format::Bin,
+        )?;
+        let sizes: Vec<_> = by_count.iter().map(|p| p.records.len()).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(by_count[2].path, PathBuf::from("part-2.bin"));
+
+        let by_bytes = partition(
+            records,
+            &[PartitionBy::Bytes(one * 3)],
+            "part-{part}.{ext}",
+            Format::Bin,
+        )?;
+        let sizes: Vec<_> = by_bytes.iter().map(|p| p.records.len()).collect();
+        assert_eq!(sizes, vec![3, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn partition_rejects_template_without_placeholder() {
+        let res = partition(
+            vec![record(1, TxType::DEPOSIT, 0)],
+            &[PartitionBy::Status],
+            "out.{ext}",
+            Format::Csv,
+        );
+
+        assert!(matches!(res, Err(CustomError::InvalidData(_))));
+        assert_eq!(
+            "records:10".parse::<PartitionBy>().unwrap(),
+            PartitionBy::Records(10)
+        );
+        assert!("records:0".parse::<PartitionBy>().is_err());
+    }
+}
@@ -0,0 +1,520 @@
+use crate::compare::{CompareOptions, Field};
+use crate::error::CustomError;
+use crate::models::Record;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Правило сравнения описаний при сверке
+#[derive(Debug, Clone, PartialEq)]
+pub enum DescriptionRule {
+    /// Описания должны совпадать посимвольно
+    Exact,
+    /// Описания не сравниваются
+    Ignore,
+    /// Нормированное расстояние Левенштейна не ниже порога (0.0..=1.0)
+    Fuzzy(f64),
+}
+
+impl FromStr for DescriptionRule {
+    type Err = CustomError;
+
+    /// Разбирает `exact`, `ignore`, `fuzzy` (порог 0.8) или `fuzzy:0.9`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "exact" => Ok(DescriptionRule::Exact),
+            None if s == "ignore" => Ok(DescriptionRule::Ignore),
+            None if s == "fuzzy" => Ok(DescriptionRule::Fuzzy(0.8)),
+            Some(("fuzzy", threshold)) => threshold
+                .parse::<f64>()
+                .ok()
+                .filter(|t| (0.0..=1.0).contains(t))
+                .map(DescriptionRule::Fuzzy)
+                .ok_or_else(|| CustomError::InvalidNumber(s.to_string())),
+            _ => Err(CustomError::InvalidEnum(format!(
+                "Неизвестное правило сравнения описаний: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Допуски, в пределах которых записи считаются совпадающими
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tolerance {
+    ///допустимое расхождение `timestamp` в миллисекундах
+    pub timestamp_ms: u64,
+    ///допустимое расхождение `amount` в наименьших единицах валюты
+    pub amount: u64,
+    ///правило сравнения `description`
+    pub description: DescriptionRule,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            timestamp_ms: 0,
+            amount: 0,
+            description: DescriptionRule::Exact,
+        }
+    }
+}
+
+/// Результат сверки одной пары записей
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchStatus {
+    /// Записи сопоставлены и совпадают в пределах допусков
+    Matched,
+    /// Записи сопоставлены по `tx_id`, но расходятся
+    Mismatched,
+    /// Запись есть только в левом источнике
+    UnmatchedLeft,
+    /// Запись есть только в правом источнике
+    UnmatchedRight,
+}
+
+impl fmt::Display for MatchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatchStatus::Matched => "matched",
+            MatchStatus::Mismatched => "mismatched",
+            MatchStatus::UnmatchedLeft => "unmatched-left",
+            MatchStatus::UnmatchedRight => "unmatched-right",
+        })
+    }
+}
+
+/// Строка отчёта сверки
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconcileRow {
+    ///классификация пары
+    pub status: MatchStatus,
+    ///запись левого источника
+    pub left: Option<Record>,
+    ///запись правого источника
+    pub right: Option<Record>,
+    ///поля, расходящиеся сверх допусков
+    pub differences: Vec<Field>,
+}
+
+/// Результат сверки двух источников
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    ///строки отчёта: сначала в порядке левого источника, затем
+    ///несопоставленные записи правого
+    pub rows: Vec<ReconcileRow>,
+}
+
+impl Reconciliation {
+    /// Количество строк с данной классификацией
+    pub fn count(&self, status: MatchStatus) -> usize {
+        self.rows.iter().filter(|row| row.status == status).count()
+    }
+
+    /// Все записи сопоставлены и совпадают
+    pub fn is_clean(&self) -> bool {
+        self.rows
+            .iter()
+            .all(|row| row.status == MatchStatus::Matched)
+    }
+}
+
+/// Формат отчёта сверки
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum ReportFormat {
+    ///Формат CSV
+    #[value(name = "csv")]
+    Csv,
+    ///Формат HTML
+    #[value(name = "html")]
+    Html,
+}
+
+/// Сверяет два источника с допусками.
+///
+/// Сначала записи сопоставляются по `tx_id` и классифицируются как
+/// `Matched` или `Mismatched`. Оставшиеся записи сопоставляются без учёта
+/// `tx_id`: по `tx_type`, `from_user_id`, `to_user_id` и `status` с
+/// допусками по `amount`, `timestamp` и `description`, выбирается кандидат
+/// с ближайшим `timestamp`. Всё, что не нашло пары, попадает в
+/// `UnmatchedLeft` / `UnmatchedRight`.
+///
+/// Поля, исключённые в `options`, не сравниваются; допуски применяются
+/// к значениям после нормализации.
+pub fn reconcile(
+    left: Vec<Record>,
+    right: Vec<Record>,
+    tolerance: &Tolerance,
+    options: &CompareOptions,
+) -> Reconciliation {
+    let mut right: Vec<Option<Record>> = right.into_iter().map(Some).collect();
+
+    // Обратный порядок: `pop()` отдаёт дубликаты `tx_id` в порядке следования
+    let mut by_tx_id: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, record) in right.iter().enumerate().rev() {
+        if let Some(record) = record {
+            by_tx_id.entry(record.tx_id).or_default().push(i);
+        }
+    }
+
+    let mut rows: Vec<ReconcileRow> = Vec::with_capacity(left.len());
+    let mut unpaired: Vec<usize> = Vec::new();
+
+    for left_record in left {
+        let paired = by_tx_id
+            .get_mut(&left_record.tx_id)
+            .and_then(|candidates| candidates.pop())
+            .and_then(|i| right[i].take());
+
+        match paired {
+            Some(right_record) => {
+                let differences = differences(&left_record, &right_record, tolerance, options);
+                let status = if differences.is_empty() {
+                    MatchStatus::Matched
+                } else {
+                    MatchStatus::Mismatched
+                };
+                rows.push(ReconcileRow {
+                    status,
+                    left: Some(left_record),
+                    right: Some(right_record),
+                    differences,
+                });
+            }
+            None => {
+                unpaired.push(rows.len());
+                rows.push(ReconcileRow {
+                    status: MatchStatus::UnmatchedLeft,
+                    left: Some(left_record),
+                    right: None,
+                    differences: Vec::new(),
+                });
+            }
+        }
+    }
+
+    let mut by_parties: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (i, record) in right.iter().enumerate() {
+        if let Some(record) = record {
+            by_parties
+                .entry((record.from_user_id, record.to_user_id))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    for row_index in unpaired {
+        let Some(left_record) = rows[row_index].left.as_ref() else {
+            continue;
+        };
+
+        let candidate = by_parties
+            .get(&(left_record.from_user_id, left_record.to_user_id))
+            .into_iter()
+            .flatten()
+            .filter_map(|&i| right[i].as_ref().map(|r| (i, r)))
+            .filter(|(_, r)| {
+                let mut differences = differences(left_record, r, tolerance, options);
+                differences.retain(|field| *field != Field::TxId);
+                differences.is_empty()
+            })
+            .min_by_key(|(_, r)| r.timestamp.abs_diff(left_record.timestamp))
+            .map(|(i, _)| i);
+
+        if let Some(i) = candidate {
+            let row = &mut rows[row_index];
+            row.status = MatchStatus::Matched;
+            row.right = right[i].take();
+        }
+    }
+
+    rows.extend(right.into_iter().flatten().map(|record| ReconcileRow {
+        status: MatchStatus::UnmatchedRight,
+        left: None,
+        right: Some(record),
+        differences: Vec::new(),
+    }));
+
+    Reconciliation { rows }
+}
+
+/// Поля, по которым пара расходится сверх допусков
+fn differences(
+    left: &Record,
+    right: &Record,
+    tolerance: &Tolerance,
+    options: &CompareOptions,
+) -> Vec<Field> {
+    options
+        .fields()
+        .iter()
+        .copied()
+        .filter(|field| match field {
+            Field::Amount => left.amount.abs_diff(right.amount) > tolerance.amount,
+            Field::Timestamp => {
+                options.timestamp(left).abs_diff(options.timestamp(right)) > tolerance.timestamp_ms
+            }
+            Field::Description => {
+                let (l, r) = (options.description(left), options.description(right));
+                match tolerance.description {
+                    DescriptionRule::Exact => l != r,
+                    DescriptionRule::Ignore => false,
+                    DescriptionRule::Fuzzy(threshold) => {
+                        strsim::normalized_levenshtein(&l, &r) < threshold
+                    }
+                }
+            }
+            _ => options.normalized(*field, left) != options.normalized(*field, right),
+        })
+        .collect()
+}
+
+const REPORT_HEADER: [&str; 12] = [
+    "STATUS",
+    "DIFFERENCES",
+    "LEFT_TX_ID",
+    "RIGHT_TX_ID",
+    "LEFT_AMOUNT",
+    "RIGHT_AMOUNT",
+    "LEFT_TIMESTAMP",
+    "RIGHT_TIMESTAMP",
+    "LEFT_STATUS",
+    "RIGHT_STATUS",
+    "LEFT_DESCRIPTION",
+    "RIGHT_DESCRIPTION",
+];
+
+fn report_cells(row: &ReconcileRow) -> [String; 12] {
+    let cell = |record: &Option<Record>, value: fn(&Record) -> String| {
+        record.as_ref().map(value).unwrap_or_default()
+    };
+
+    [
+        row.status.to_string(),
+        row.differences
+            .iter()
+            .map(Field::name)
+            .collect::<Vec<_>>()
+            .join(";"),
+        cell(&row.left, |r| r.tx_id.to_string()),
+        cell(&row.right, |r| r.tx_id.to_string()),
+        cell(&row.left, |r| r.amount.to_string()),
+        cell(&row.right, |r| r.amount.to_string()),
+        cell(&row.left, |r| r.timestamp.to_string()),
+        cell(&row.right, |r| r.timestamp.to_string()),
+        cell(&row.left, |r| format!("{:?}", r.status)),
+        cell(&row.right, |r| format!("{:?}", r.status)),
+        cell(&row.left, |r| r.description.clone()),
+        cell(&row.right, |r| r.description.clone()),
+    ]
+}
+
+/// Записывает отчёт сверки в CSV или HTML
+pub fn write_report<W: Write>(
+    reconciliation: &Reconciliation,
+    format: ReportFormat,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    match format {
+        ReportFormat::Csv => write_csv(reconciliation, writer),
+        ReportFormat::Html => write_html(reconciliation, writer),
+    }
+}
+
+fn write_csv<W: Write>(reconciliation: &Reconciliation, writer: &mut W) -> Result<(), CustomError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(REPORT_HEADER)?;
+    for row in &reconciliation.rows {
+        csv_writer.write_record(report_cells(row))?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+fn write_html<W: Write>(
+    reconciliation: &Reconciliation,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html>")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>Reconciliation report</title>")?;
+    writeln!(
+        writer,
+        "<style>table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #999; padding: 2px 6px; }} \
+         .matched {{ background: #e6ffe6; }} .mismatched {{ background: #fff3cd; }} \
+         .unmatched-left, .unmatched-right {{ background: #f8d7da; }}</style>"
+    )?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>Reconciliation report</h1>")?;
+
+    writeln!(writer, "<ul>")?;
+    for status in [
+        MatchStatus::Matched,
+        MatchStatus::Mismatched,
+        MatchStatus::UnmatchedLeft,
+        MatchStatus::UnmatchedRight,
+    ] {
+        writeln!(
+            writer,
+            "<li>{}: {}</li>",
+            status,
+            reconciliation.count(status)
+        )?;
+    }
+    writeln!(writer, "</ul>")?;
+
+    writeln!(writer, "<table>")?;
+    write!(writer, "<tr>")?;
+    for title in REPORT_HEADER {
+        write!(writer, "<th>{}</th>", title)?;
+    }
+    writeln!(writer, "</tr>")?;
+
+    for row in &reconciliation.rows {
+        write!(writer, "<tr class=\"{}\">", row.status)?;
+        for cell in report_cells(row) {
+            write!(writer, "<td>{}</td>", escape_html(&cell))?;
+        }
+        writeln!(writer, "</tr>")?;
+    }
+
+    writeln!(writer, "</table>")?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Status, TxType};
+    use std::collections::BTreeMap;
+
+    fn record(tx_id: u64, amount: u64, timestamp: u64, description: &str) -> Record {
+        Record {
+            tx_id,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 1,
+            to_user_id: 2,
+            amount,
+            timestamp,
+            status: Status::SUCCESS,
+            description: description.to_string(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn reconcile_with_tolerances() {
+        let left = vec![
+            record(1, 100, 10_000, "Payment for order 15"),
+            record(2, 500, 20_000, "Rent"),
+            record(3, 700, 30_000, "Salary"),
+            record(4, 900, 40_000, "Lost"),
+        ];
+        let right = vec![
+            record(1, 101, 12_000, "Payment for order #15"),
+            record(2, 550, 20_000, "Rent"),
+            record(77, 700, 31_000, "salary"),
+            record(9, 5, 50_000, "Extra"),
+        ];
+
+        let tolerance = Tolerance {
+            timestamp_ms: 2_000,
+            amount: 1,
+            description: DescriptionRule::Fuzzy(0.8),
+        };
+        let result = reconcile(left, right, &tolerance, &CompareOptions::default());
+
+        let statuses: Vec<_> = result.rows.iter().map(|row| row.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                MatchStatus::Matched,
+                MatchStatus::Mismatched,
+                MatchStatus::Matched,
+                MatchStatus::UnmatchedLeft,
+                MatchStatus::UnmatchedRight,
+            ]
+        );
+        assert_eq!(result.rows[1].differences, vec![Field::Amount]);
+        assert_eq!(result.rows[2].right.as_ref().map(|r| r.tx_id), Some(77));
+        assert!(!result.is_clean());
+
+        let strict = reconcile(
+            vec![record(1, 100, 10_000, "a")],
+            vec![record(1, 100, 10_001, "b")],
+            &Tolerance::default(),
+            &CompareOptions::default(),
+        );
+        assert_eq!(
+            strict.rows[0].differences,
+            vec![Field::Timestamp, Field::Description]
+        );
+
+        let ignoring = reconcile(
+            vec![record(1, 100, 10_000, "a")],
+            vec![record(1, 100, 10_001, "b")],
+            &Tolerance::default(),
+            &CompareOptions::new(&[], &[Field::Timestamp, Field::Description], vec![]),
+        );
+        assert!(ignoring.is_clean());
+    }
+
+    #[test]
+    fn reconcile_reports() -> Result<(), CustomError> {
+        let result = reconcile(
+            vec![record(1, 100, 0, "<b>")],
+            vec![record(2, 1, 0, "x")],
+            &Tolerance::default(),
+            &CompareOptions::default(),
+        );
+
+        let mut csv_buf = Vec::new();
+        write_report(&result, ReportFormat::Csv, &mut csv_buf)?;
+        let csv_text = String::from_utf8(csv_buf).unwrap();
+        assert_eq!(csv_text.lines().count(), 3);
+        assert!(csv_text.contains("unmatched-left,,1,,100,"));
+
+        let mut html_buf = Vec::new();
+        write_report(&result, ReportFormat::Html, &mut html_buf)?;
+        let html = String::from_utf8(html_buf).unwrap();
+        assert!(html.contains("<li>unmatched-right: 1</li>"));
+        assert!(html.contains("&lt;b&gt;"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn description_rule_from_str() {
+        assert_eq!(
+            "ignore".parse::<DescriptionRule>().unwrap(),
+            DescriptionRule::Ignore
+        );
+        assert_eq!(
+            "fuzzy:0.5".parse::<DescriptionRule>().unwrap(),
+            DescriptionRule::Fuzzy(0.5)
+        );
+        assert!("fuzzy:2".parse::<DescriptionRule>().is_err());
+    }
+}
@@ -0,0 +1,616 @@
+use crate::error::CustomError;
+use crate::format::bin::YPBN;
+use crate::format::txt::unquote;
+use crate::models::{Format, FormatOptions, Record};
+use crate::wire;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde::ser::{self, Impossible};
+use std::io::{BufRead, BufReader, Lines, Read, Write};
+use std::marker::PhantomData;
+
+/// Итератор строк произвольной схемы, читаемых из потока по одной
+pub type RowIter<'a, T> = Box<dyn Iterator<Item = Result<T, CustomError>> + 'a>;
+
+/// Читает строки схемы `T` из источника в указанном формате.
+///
+/// Схема — любая плоская структура с `Deserialize`, например [`Record`]
+/// или запись с полями `fee`, `currency`, `channel`:
+/// - CSV — заголовок с именами полей (с учётом `#[serde(rename)]`);
+/// - TXT — блоки `КЛЮЧ: значение`, разделённые пустыми строками или
+///   заголовками `#`; строковые значения могут быть в двойных кавычках;
+/// - BIN — кадры `YPBN` + длина + тело в кодировке [`wire`].
+///
+/// Остальные форматы (camt.053 и другие) описывают только [`Record`] и для
+/// других схем дают ошибку. Сами [`Record`] с дополнительными полями
+/// читаются встроенными форматами через [`read_records`].
+///
+/// Лишние колонки и ключи пропускаются, недостающие дают ошибку.
+pub fn read<'a, T, R>(format: Format, r: R) -> RowIter<'a, T>
+where
+    T: DeserializeOwned + 'a,
+    R: Read + 'a,
+{
+    match format {
+        Format::Csv => Box::new(
+            csv::Reader::from_reader(r)
+                .into_deserialize()
+                .map(|row| row.map_err(CustomError::from)),
+        ),
+        Format::Txt => Box::new(TxtRows::<R, T>::new(r)),
+        Format::Bin => Box::new(BinRows::<R, T>::new(r)),
+        _ => Box::new(std::iter::once(Err(fixed_schema(&format)))),
+    }
+}
+
+/// Записывает строки схемы `T` в приёмник в указанном формате.
+///
+/// TXT пишется блоками `# Record N` (с типом в скобках, если у схемы есть
+/// поле `TX_TYPE`); строки выводятся в двойных кавычках, числа и варианты
+/// перечислений — как есть, `None` — пустым значением.
+///
+/// Дополнительные поля и блок расширений BIN плоской схемой не
+/// выражаются: [`Record`] пишутся встроенными форматами через
+/// [`write_records`].
+pub fn write<T, W>(format: Format, rows: &[T], writer: &mut W) -> Result<(), CustomError>
+where
+    T: Serialize,
+    W: Write,
+{
+    match format {
+        Format::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                csv_writer.serialize(row)?;
+            }
+            csv_writer.flush()?;
+        }
+        Format::Txt => {
+            for (i, row) in rows.iter().enumerate() {
+                let fields = row.serialize(RowSerializer)?;
+                match fields.iter().find(|(key, _)| key == "TX_TYPE") {
+                    Some((_, tx_type)) => writeln!(writer, "# Record {} ({})", i + 1, tx_type)?,
+                    None => writeln!(writer, "# Record {}", i + 1)?,
+                }
+                for (key, value) in fields {
+                    writeln!(writer, "{}: {}", key, value)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        Format::Bin => {
+            for row in rows {
+                wire::write_frame(writer, &YPBN, row)?;
+            }
+        }
+        _ => return Err(fixed_schema(&format)),
+    }
+    Ok(())
+}
+
+/// Читает [`Record`] встроенным форматом, с дополнительными полями.
+///
+/// То же, что [`read`], для схемы [`Record`], но поддерживает все форматы
+/// и их расширения (колонки `extra`, блок расширений BIN).
+pub fn read_records<'a, R: Read + 'a>(format: Format, r: R) -> RowIter<'a, Record> {
+    crate::format::records(format, r, &FormatOptions::default())
+}
+
+/// Записывает [`Record`] встроенным форматом, с дополнительными полями;
+/// см. [`crate::write_records`]
+pub fn write_records<W: Write>(
+    format: Format,
+    rows: &[Record],
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    crate::write_records(format, rows.to_vec(), writer)
+}
+
+/// Ошибка для форматов со своей фиксированной структурой документа
+fn fixed_schema(format: &Format) -> CustomError {
+    CustomError::InvalidData(format!(
+        "формат {:?} поддерживает только записи Record",
+        format
+    ))
+}
+
+/// Потоковое чтение TXT для произвольной схемы
+struct TxtRows<R: Read, T> {
+    lines: Lines<BufReader<R>>,
+    row: PhantomData<T>,
+}
+
+impl<R: Read, T: DeserializeOwned> TxtRows<R, T> {
+    fn new(r: R) -> Self {
+        Self {
+            lines: BufReader::new(r).lines(),
+            row: PhantomData,
+        }
+    }
+
+    /// Собирает блок `КЛЮЧ: значение` в строку схемы через десериализатор CSV,
+    /// который уже умеет разбирать числа, перечисления и `Option` из текста
+    fn build(block: Vec<(String, String)>) -> Result<T, CustomError> {
+        let (keys, values): (Vec<String>, Vec<String>) = block.into_iter().unzip();
+        let headers = csv::StringRecord::from(keys);
+        Ok(csv::StringRecord::from(values).deserialize(Some(&headers))?)
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for TxtRows<R, T> {
+    type Item = Result<T, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut block: Vec<(String, String)> = Vec::new();
+
+        loop {
+            match self.lines.next() {
+                Some(Ok(line)) => {
+                    if !line.starts_with('#') && !line.is_empty() {
+                        if let Some((k, v)) = line.split_once(':') {
+                            block.push((k.trim().to_string(), unquote(v.trim()).to_string()));
+                        }
+                    } else if !block.is_empty() {
+                        return Some(Self::build(block));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e.into())),
+                None if block.is_empty() => return None,
+                None => return Some(Self::build(block)),
+            }
+        }
+    }
+}
+
+/// Потоковое чтение BIN для произвольной схемы; останавливается на первой ошибке
+struct BinRows<R: Read, T> {
+    reader: R,
+    failed: bool,
+    row: PhantomData<T>,
+}
+
+impl<R: Read, T: DeserializeOwned> BinRows<R, T> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            failed: false,
+            row: PhantomData,
+        }
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for BinRows<R, T> {
+    type Item = Result<T, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let next = wire::read_frame(&mut self.reader, &YPBN).transpose();
+        if let Some(Err(_)) = next {
+            self.failed = true;
+        }
+        next
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, CustomError> {
+    Err(CustomError::InvalidData(format!(
+        "TXT поддерживает только плоские структуры: {} не поддерживается",
+        what
+    )))
+}
+
+/// Раскладывает плоскую структуру на пары `(ключ, значение)` для TXT
+struct RowSerializer;
+
+/// Собирает поля структуры или словаря
+struct RowFields {
+    fields: Vec<(String, String)>,
+    key: Option<String>,
+}
+
+impl ser::Serializer for RowSerializer {
+    type Ok = Vec<(String, String)>;
+    type Error = CustomError;
+    type SerializeSeq = Impossible<Self::Ok, CustomError>;
+    type SerializeTuple = Impossible<Self::Ok, CustomError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, CustomError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, CustomError>;
+    type SerializeMap = RowFields;
+    type SerializeStruct = RowFields;
+    type SerializeStructVariant = Impossible<Self::Ok, CustomError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, CustomError> {
+        unsupported("bool")
+    }
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, CustomError> {
+        unsupported("число")
+    }
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, CustomError> {
+        unsupported("символ")
+    }
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, CustomError> {
+        unsupported("строка")
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, CustomError> {
+        unsupported("байты")
+    }
+    fn serialize_none(self) -> Result<Self::Ok, CustomError> {
+        unsupported("Option")
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Self::Ok, CustomError> {
+        unsupported("Option")
+    }
+    fn serialize_unit(self) -> Result<Self::Ok, CustomError> {
+        Ok(Vec::new())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, CustomError> {
+        Ok(Vec::new())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, CustomError> {
+        unsupported("перечисление")
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, CustomError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, CustomError> {
+        unsupported("перечисление")
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, CustomError> {
+        unsupported("последовательность")
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, CustomError> {
+        unsupported("кортеж")
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, CustomError> {
+        unsupported("кортеж")
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, CustomError> {
+        unsupported("перечисление")
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, CustomError> {
+        Ok(RowFields {
+            fields: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, CustomError> {
+        Ok(RowFields {
+            fields: Vec::with_capacity(len),
+            key: None,
+        })
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, CustomError> {
+        unsupported("перечисление")
+    }
+}
+
+impl ser::SerializeStruct for RowFields {
+    type Ok = Vec<(String, String)>;
+    type Error = CustomError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), CustomError> {
+        self.fields
+            .push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, CustomError> {
+        Ok(self.fields)
+    }
+}
+
+impl ser::SerializeMap for RowFields {
+    type Ok = Vec<(String, String)>;
+    type Error = CustomError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CustomError> {
+        let key = key.serialize(ValueSerializer)?;
+        self.key = Some(unquote(&key).to_string());
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CustomError> {
+        let key = self.key.take().unwrap_or_default();
+        self.fields.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, CustomError> {
+        Ok(self.fields)
+    }
+}
+
+/// Выводит одно значение поля в текстовом виде TXT
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = String;
+    type Error = CustomError;
+    type SerializeSeq = Impossible<String, CustomError>;
+    type SerializeTuple = Impossible<String, CustomError>;
+    type SerializeTupleStruct = Impossible<String, CustomError>;
+    type SerializeTupleVariant = Impossible<String, CustomError>;
+    type SerializeMap = Impossible<String, CustomError>;
+    type SerializeStruct = Impossible<String, CustomError>;
+    type SerializeStructVariant = Impossible<String, CustomError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i8(self, v: i8) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i16(self, v: i16) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i32(self, v: i32) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i64(self, v: i64) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u8(self, v: u8) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u16(self, v: u16) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u32(self, v: u32) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u64(self, v: u64) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_f32(self, v: f32) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_f64(self, v: f64) -> Result<String, CustomError> {
+        Ok(v.to_string())
+    }
+    fn serialize_char(self, v: char) -> Result<String, CustomError> {
+        Ok(format!("\"{}\"", v))
+    }
+    fn serialize_str(self, v: &str) -> Result<String, CustomError> {
+        Ok(format!("\"{}\"", v))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, CustomError> {
+        unsupported("байты")
+    }
+    fn serialize_none(self) -> Result<String, CustomError> {
+        Ok(String::new())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, CustomError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<String, CustomError> {
+        Ok(String::new())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, CustomError> {
+        Ok(String::new())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, CustomError> {
+        Ok(variant.to_string())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, CustomError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, CustomError> {
+        unsupported("вариант с данными")
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, CustomError> {
+        unsupported("последовательность")
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, CustomError> {
+        unsupported("кортеж")
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, CustomError> {
+        unsupported("кортеж")
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, CustomError> {
+        unsupported("вариант с данными")
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, CustomError> {
+        unsupported("вложенный словарь")
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, CustomError> {
+        unsupported("вложенная структура")
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, CustomError> {
+        unsupported("вариант с данными")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Record, Status, TxType};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "UPPERCASE")]
+    enum Channel {
+        Web,
+        Pos,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "UPPERCASE")]
+    struct Payment {
+        tx_id: u64,
+        amount: u64,
+        fee: Option<u32>,
+        currency: String,
+        channel: Channel,
+    }
+
+    #[test]
+    fn custom_schema_round_trips_in_all_formats() -> Result<(), CustomError> {
+        let rows = vec![
+            Payment {
+                tx_id: 1,
+                amount: 500,
+                fee: Some(5),
+                currency: "EUR".to_string(),
+                channel: Channel::Web,
+            },
+            Payment {
+                tx_id: 2,
+                amount: 70,
+                fee: None,
+                currency: "RUB".to_string(),
+                channel: Channel::Pos,
+            },
+        ];
+
+        for format in [Format::Csv, Format::Txt, Format::Bin] {
+            let mut buf = Vec::new();
+            write(format.clone(), &rows, &mut buf)?;
+            let parsed = read::<Payment, _>(format.clone(), buf.as_slice())
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(parsed, rows, "{:?}", format);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn record_schema_matches_builtin_formats() -> Result<(), CustomError> {
+        let record = Record {
+            tx_id: 3,
+            tx_type: TxType::TRANSFER,
+            from_user_id: 1,
+            to_user_id: 2,
+            amount: 10,
+            timestamp: 99,
+            status: Status::SUCCESS,
+            description: "Record number 3".to_string(),
+            extra: BTreeMap::new(),
+        };
+        let records = vec![
+            record.clone(),
+            Record {
+                tx_id: 4,
+                extra: BTreeMap::from([("CHANNEL".to_string(), "web".to_string())]),
+                ..record
+            },
+        ];
+
+        for format in [Format::Csv, Format::Txt, Format::Bin] {
+            let mut schema = Vec::new();
+            write_records(format.clone(), &records, &mut schema)?;
+            let mut builtin = Vec::new();
+            crate::write_records(format.clone(), records.clone(), &mut builtin)?;
+            assert_eq!(schema, builtin, "{:?}", format);
+
+            let parsed = read_records(format, builtin.as_slice()).collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(parsed, records);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use crate::error::CustomError;
+use crate::models::{Record, Status, TxType};
+use crate::partition::record_date;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Итоги по одному пользователю: сколько транзакций и на какую сумму
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UserTotal {
+    ///идентификатор пользователя
+    pub user_id: u64,
+    ///количество транзакций
+    pub tx_count: u64,
+    ///сумма транзакций
+    pub amount: u128,
+}
+
+/// Потоковый агрегатор сводной статистики.
+///
+/// Записи добавляются по одной через [`Stats::push`], поэтому статистику
+/// можно собрать за один проход по файлу любого формата, не держа записи
+/// в памяти. Отправителем считается `from_user_id` у `TRANSFER` и
+/// `WITHDRAWAL`, получателем — `to_user_id` у `TRANSFER` и `DEPOSIT`.
+#[derive(Debug, Default)]
+pub struct Stats {
+    count: u64,
+    by_tx_type: BTreeMap<String, u64>,
+    by_status: BTreeMap<String, u64>,
+    amount_sum: u128,
+    amount_min: Option<u64>,
+    amount_max: Option<u64>,
+    timestamp_min: Option<u64>,
+    timestamp_max: Option<u64>,
+    users: HashSet<u64>,
+    senders: HashMap<u64, (u64, u128)>,
+    receivers: HashMap<u64, (u64, u128)>,
+}
+
+impl Stats {
+    /// Пустой агрегатор
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Собирает статистику по потоку записей, останавливаясь на первой ошибке
+    pub fn from_records<I>(records: I) -> Result<Self, CustomError>
+    where
+        I: IntoIterator<Item = Result<Record, CustomError>>,
+    {
+        let mut stats = Self::new();
+        for record in records {
+            stats.push(&record?);
+        }
+        Ok(stats)
+    }
+
+    /// Учитывает одну запись
+    pub fn push(&mut self, record: &Record) {
+        self.count += 1;
+        *self
+            .by_tx_type
+            .entry(format!("{:?}", record.tx_type))
+            .or_insert(0) += 1;
+        *self
+            .by_status
+            .entry(format!("{:?}", record.status))
+            .or_insert(0) += 1;
+
+        self.amount_sum += record.amount as u128;
+        self.amount_min = Some(
+            self.amount_min
+                .map_or(record.amount, |m| m.min(record.amount)),
+        );
+        self.amount_max = Some(
+            self.amount_max
+                .map_or(record.amount, |m| m.max(record.amount)),
+        );
+        self.timestamp_min = Some(
+            self.timestamp_min
+                .map_or(record.timestamp, |m| m.min(record.timestamp)),
+        );
+        self.timestamp_max = Some(
+            self.timestamp_max
+                .map_or(record.timestamp, |m| m.max(record.timestamp)),
+        );
+
+        if record.tx_type != TxType::DEPOSIT {
+            self.users.insert(record.from_user_id);
+            let sender = self.senders.entry(record.from_user_id).or_insert((0, 0));
+            sender.0 += 1;
+            sender.1 += record.amount as u128;
+        }
+        if record.tx_type != TxType::WITHDRAWAL {
+            self.users.insert(record.to_user_id);
+            let receiver = self.receivers.entry(record.to_user_id).or_insert((0, 0));
+            receiver.0 += 1;
+            receiver.1 += record.amount as u128;
+        }
+    }
+
+    /// Количество записей
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Количество записей с данным типом транзакции
+    pub fn tx_type_count(&self, tx_type: &TxType) -> u64 {
+        self.by_tx_type
+            .get(&format!("{:?}", tx_type))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Количество записей с данным статусом
+    pub fn status_count(&self, status: &Status) -> u64 {
+        self.by_status
+            .get(&format!("{:?}", status))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Сумма `amount` по всем записям
+    pub fn amount_sum(&self) -> u128 {
+        self.amount_sum
+    }
+
+    /// Минимальный `amount`, если записи были
+    pub fn amount_min(&self) -> Option<u64> {
+        self.amount_min
+    }
+
+    /// Максимальный `amount`, если записи были
+    pub fn amount_max(&self) -> Option<u64> {
+        self.amount_max
+    }
+
+    /// Среднее `amount`, если записи были
+    pub fn amount_mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.amount_sum as f64 / self.count as f64)
+    }
+
+    /// Самый ранний и самый поздний `timestamp`
+    pub fn timestamp_range(&self) -> Option<(u64, u64)> {
+        self.timestamp_min.zip(self.timestamp_max)
+    }
+
+    /// Количество различных пользователей среди отправителей и получателей
+    pub fn distinct_users(&self) -> usize {
+        self.users.len()
+    }
+
+    /// `n` отправителей с наибольшей суммой отправленного
+    pub fn top_senders(&self, n: usize) -> Vec<UserTotal> {
+        top(&self.senders, n)
+    }
+
+    /// `n` получателей с наибольшей суммой полученного
+    pub fn top_receivers(&self, n: usize) -> Vec<UserTotal> {
+        top(&self.receivers, n)
+    }
+
+    /// Сводка для машиночитаемого вывода (JSON, CSV)
+    pub fn summary(&self, top: usize) -> StatsSummary {
+        StatsSummary {
+            count: self.count,
+            by_tx_type: self.by_tx_type.clone(),
+            by_status: self.by_status.clone(),
+            amount_sum: self.amount_sum,
+            amount_min: self.amount_min,
+            amount_max: self.amount_max,
+            amount_mean: self.amount_mean(),
+            timestamp_min: self.timestamp_min,
+            timestamp_max: self.timestamp_max,
+            distinct_users: self.distinct_users(),
+            top_senders: self.top_senders(top),
+            top_receivers: self.top_receivers(top),
+        }
+    }
+
+    /// Текстовый отчёт с `top` крупнейшими отправителями и получателями
+    pub fn report(&self, top: usize) -> StatsReport<'_> {
+        StatsReport { stats: self, top }
+    }
+}
+
+/// Снимок [`Stats`] для сериализации, создаётся через [`Stats::summary`]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StatsSummary {
+    ///количество записей
+    pub count: u64,
+    ///количество записей по типам транзакций
+    pub by_tx_type: BTreeMap<String, u64>,
+    ///количество записей по статусам
+    pub by_status: BTreeMap<String, u64>,
+    ///сумма `amount`
+    pub amount_sum: u128,
+    ///минимальный `amount`
+    pub amount_min: Option<u64>,
+    ///максимальный `amount`
+    pub amount_max: Option<u64>,
+    ///среднее `amount`
+    pub amount_mean: Option<f64>,
+    ///самый ранний `timestamp`
+    pub timestamp_min: Option<u64>,
+    ///самый поздний `timestamp`
+    pub timestamp_max: Option<u64>,
+    ///количество различных пользователей
+    pub distinct_users: usize,
+    ///крупнейшие отправители
+    pub top_senders: Vec<UserTotal>,
+    ///крупнейшие получатели
+    pub top_receivers: Vec<UserTotal>,
+}
+
+/// Текстовый отчёт по [`Stats`], создаётся через [`Stats::report`]
+pub struct StatsReport<'a> {
+    stats: &'a Stats,
+    top: usize,
+}
+
+fn top(totals: &HashMap<u64, (u64, u128)>, n: usize) -> Vec<UserTotal> {
+    let mut users: Vec<UserTotal> = totals
+        .iter()
+        .map(|(user_id, (tx_count, amount))| UserTotal {
+            user_id: *user_id,
+            tx_count: *tx_count,
+            amount: *amount,
+        })
+        .collect();
+    users.sort_by(|a, b| b.amount.cmp(&a.amount).then(a.user_id.cmp(&b.user_id)));
+    users.truncate(n);
+    users
+}
+
+impl fmt::Display for StatsReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = self.stats;
+
+        writeln!(f, "Records: {}", stats.count)?;
+
+        writeln!(f, "By TX_TYPE:")?;
+        for (tx_type, count) in &stats.by_tx_type {
+            writeln!(f, "  {}: {}", tx_type, count)?;
+        }
+        writeln!(f, "By STATUS:")?;
+        for (status, count) in &stats.by_status {
+            writeln!(f, "  {}: {}", status, count)?;
+        }
+
+        if let (Some(min), Some(max), Some(mean)) =
+            (stats.amount_min, stats.amount_max, stats.amount_mean())
+        {
+            writeln!(
+                f,
+                "AMOUNT: sum {}, min {}, max {}, mean {:.2}",
+                stats.amount_sum, min, max, mean
+            )?;
+        }
+
+        if let Some((first, last)) = stats.timestamp_range() {
+            writeln!(
+                f,
+                "TIMESTAMP: {} ({}) .. {} ({}), span {} ms",
+                first,
+                record_date(first),
+                last,
+                record_date(last),
+                last - first
+            )?;
+        }
+
+        writeln!(f, "Distinct users: {}", stats.distinct_users())?;
+
+        writeln!(f, "Top senders:")?;
+        for user in stats.top_senders(self.top) {
+            writeln!(
+                f,
+                "  {}: {} ({} tx)",
+                user.user_id, user.amount, user.tx_count
+            )?;
+        }
+        writeln!(f, "Top receivers:")?;
+        for user in stats.top_receivers(self.top) {
+            writeln!(
+                f,
+                "  {}: {} ({} tx)",
+                user.user_id, user.amount, user.tx_count
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::csv::CsvRecords;
+    use std::io::Cursor;
+
+    #[test]
+    fn stats_single_pass_over_csv() -> Result<(), CustomError> {
+        let data = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION\n\
+                    1,DEPOSIT,0,10,100,1000,SUCCESS,\"a\"\n\
+                    2,TRANSFER,10,20,40,3000,PENDING,\"b\"\n\
+                    3,WITHDRAWAL,20,0,10,2000,SUCCESS,\"c\"\n\
+                    4,TRANSFER,10,30,250,4000,FAILURE,\"d\"\n";
+
+        let stats = Stats::from_records(CsvRecords::new(Cursor::new(data)))?;
+
+        assert_eq!(stats.count(), 4);
+        assert_eq!(stats.tx_type_count(&TxType::TRANSFER), 2);
+        assert_eq!(stats.status_count(&Status::SUCCESS), 2);
+        assert_eq!(stats.status_count(&Status::PENDING), 1);
+        assert_eq!(stats.amount_sum(), 400);
+        assert_eq!(stats.amount_min(), Some(10));
+        assert_eq!(stats.amount_max(), Some(250));
+        assert_eq!(stats.amount_mean(), Some(100.0));
+        assert_eq!(stats.timestamp_range(), Some((1000, 4000)));
+        assert_eq!(stats.distinct_users(), 3);
+
+        let senders = stats.top_senders(1);
+        assert_eq!(
+            senders,
+            vec![UserTotal {
+                user_id: 10,
+                tx_count: 2,
+                amount: 290
+            }]
+        );
+        assert_eq!(stats.top_receivers(3)[0].user_id, 30);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,816 @@
+use crate::error::CustomError;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::{Deserialize, Serialize, ser};
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+/// Сериализует значение в тело записи BIN-формата.
+///
+/// Правила кодирования:
+/// - целые и числа с плавающей точкой — big-endian фиксированной ширины,
+///   `bool` — один байт `0`/`1`, `char` — `u32`;
+/// - строки и байты — длина `u32` и содержимое;
+/// - `Option` — байт `0`/`1` и значение;
+/// - последовательности и словари — количество элементов `u32` и элементы;
+/// - структуры и кортежи — поля подряд, без разделителей;
+/// - варианты перечислений — номер варианта `u8` и содержимое варианта.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, CustomError> {
+    let mut serializer = Serializer { output: Vec::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+/// Десериализует значение из тела записи; лишние байты в конце — ошибка
+pub fn from_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, CustomError> {
+    let mut deserializer = Deserializer::from_slice(bytes);
+    let value = T::deserialize(&mut deserializer)?;
+    if !deserializer.input.is_empty() {
+        return Err(CustomError::InvalidData(format!(
+            "лишние байты в конце записи: {}",
+            deserializer.input.len()
+        )));
+    }
+    Ok(value)
+}
+
+/// Записывает кадр: `magic`, длину тела `u32` и тело
+pub fn write_frame<W: Write, T: Serialize + ?Sized>(
+    writer: &mut W,
+    magic: &[u8],
+    value: &T,
+) -> Result<(), CustomError> {
+    let body = to_bytes(value)?;
+    let len = u32::try_from(body.len())
+        .map_err(|_| CustomError::InvalidData(format!("запись слишком длинная: {}", body.len())))?;
+
+    writer.write_all(magic)?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&body)?;
+    Ok(())
+}
+
+/// Читает кадр, записанный [`write_frame`]; `Ok(None)` — конец потока.
+///
+/// Тело читается целиком по длине из заголовка, поэтому байты в конце тела,
+/// не описанные типом `T` (например, блок расширений более новой версии),
+/// пропускаются без потери позиции в потоке.
+pub fn read_frame<R: Read, T: for<'de> Deserialize<'de>>(
+    reader: &mut R,
+    magic: &[u8],
+) -> Result<Option<T>, CustomError> {
+    match read_frame_body(reader, magic)? {
+        Some(body) => Ok(Some(T::deserialize(&mut Deserializer::from_slice(&body))?)),
+        None => Ok(None),
+    }
+}
+
+/// Читает кадр и возвращает его тело без разбора; `Ok(None)` — конец потока.
+///
+/// Нужен, когда после основной части тела может идти необязательный блок
+/// расширений: его наличие проверяется через [`Deserializer::is_empty`].
+pub fn read_frame_body<R: Read>(
+    reader: &mut R,
+    magic: &[u8],
+) -> Result<Option<Vec<u8>>, CustomError> {
+    let mut header = vec![0u8; magic.len() + 4];
+    if reader.read(&mut header[..1])? == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut header[1..])?;
+
+    if header[..magic.len()] != *magic {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Неверное магическое число заголовка",
+        )
+        .into());
+    }
+
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&header[magic.len()..]);
+    let len = u32::from_be_bytes(len) as u64;
+
+    // длина из заголовка не проверена: память растёт по мере чтения тела,
+    // а не выделяется заранее
+    let mut body = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut body)?;
+    if (body.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "Тело кадра короче заголовка: {} из {} байт",
+                body.len(),
+                len
+            ),
+        )
+        .into());
+    }
+    Ok(Some(body))
+}
+
+impl ser::Error for CustomError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CustomError::InvalidData(msg.to_string())
+    }
+}
+
+impl de::Error for CustomError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CustomError::InvalidData(msg.to_string())
+    }
+}
+
+/// Сериализатор BIN-формата, см. [`to_bytes`]
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    fn write_len(&mut self, len: usize) -> Result<(), CustomError> {
+        let len = u32::try_from(len)
+            .map_err(|_| CustomError::InvalidData(format!("слишком длинное значение: {}", len)))?;
+        self.output.extend_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+
+    fn write_variant(&mut self, index: u32) -> Result<(), CustomError> {
+        let index = u8::try_from(index)
+            .map_err(|_| CustomError::InvalidData(format!("номер варианта {} > 255", index)))?;
+        self.output.push(index);
+        Ok(())
+    }
+}
+
+impl ser::Serializer for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), CustomError> {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), CustomError> {
+        self.output.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), CustomError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), CustomError> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), CustomError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), CustomError> {
+        self.write_len(v.len())?;
+        self.output.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), CustomError> {
+        self.output.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), CustomError> {
+        self.output.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), CustomError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), CustomError> {
+        self.write_variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), CustomError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), CustomError> {
+        self.write_variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, CustomError> {
+        let len = len.ok_or_else(|| {
+            CustomError::InvalidData(String::from("длина последовательности неизвестна"))
+        })?;
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, CustomError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, CustomError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, CustomError> {
+        self.write_variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, CustomError> {
+        let len =
+            len.ok_or_else(|| CustomError::InvalidData(String::from("размер словаря неизвестен")))?;
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, CustomError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, CustomError> {
+        self.write_variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CustomError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Serializer {
+    type Ok = ();
+    type Error = CustomError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), CustomError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+/// Десериализатор BIN-формата поверх среза байт, см. [`from_bytes`]
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    /// Создаёт десериализатор, читающий тело записи с начала среза
+    pub fn from_slice(input: &'de [u8]) -> Self {
+        Self { input }
+    }
+
+    /// Прочитано ли тело до конца
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8], CustomError> {
+        if self.input.len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "запись обрывается: нужно {} байт, осталось {}",
+                    len,
+                    self.input.len()
+                ),
+            )
+            .into());
+        }
+        let (head, tail) = self.input.split_at(len);
+        self.input = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CustomError> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.take(N)?);
+        Ok(buf)
+    }
+
+    fn len(&mut self) -> Result<usize, CustomError> {
+        Ok(u32::from_be_bytes(self.array()?) as usize)
+    }
+
+    fn bytes(&mut self) -> Result<&'de [u8], CustomError> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Result<&'de str, CustomError> {
+        std::str::from_utf8(self.bytes()?).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Неверный UTF-8: {}", e)).into()
+        })
+    }
+}
+
+macro_rules! deserialize_number {
+    ($method:ident, $visit:ident, $ty:ty) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+            visitor.$visit(<$ty>::from_be_bytes(self.array()?))
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = CustomError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, CustomError> {
+        Err(CustomError::InvalidData(String::from(
+            "BIN-формат не самоописываемый: тип значения должен быть известен",
+        )))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        match self.array::<1>()?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            other => Err(CustomError::InvalidData(format!(
+                "неверное значение bool: {}",
+                other
+            ))),
+        }
+    }
+
+    deserialize_number!(deserialize_i8, visit_i8, i8);
+    deserialize_number!(deserialize_i16, visit_i16, i16);
+    deserialize_number!(deserialize_i32, visit_i32, i32);
+    deserialize_number!(deserialize_i64, visit_i64, i64);
+    deserialize_number!(deserialize_i128, visit_i128, i128);
+    deserialize_number!(deserialize_u8, visit_u8, u8);
+    deserialize_number!(deserialize_u16, visit_u16, u16);
+    deserialize_number!(deserialize_u32, visit_u32, u32);
+    deserialize_number!(deserialize_u64, visit_u64, u64);
+    deserialize_number!(deserialize_u128, visit_u128, u128);
+    deserialize_number!(deserialize_f32, visit_f32, f32);
+    deserialize_number!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        let code = u32::from_be_bytes(self.array()?);
+        let c = char::from_u32(code)
+            .ok_or_else(|| CustomError::InvalidData(format!("неверный символ: {}", code)))?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        visitor.visit_borrowed_str(self.str()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        visitor.visit_borrowed_bytes(self.bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        match self.array::<1>()?[0] {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            other => Err(CustomError::InvalidData(format!(
+                "неверный признак Option: {}",
+                other
+            ))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        let len = self.len()?;
+        visitor.visit_seq(Counted { de: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_seq(Counted { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_seq(Counted { de: self, len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        let len = self.len()?;
+        visitor.visit_map(Counted { de: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_seq(Counted {
+            de: self,
+            len: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, CustomError> {
+        Err(CustomError::InvalidData(String::from(
+            "BIN-формат не хранит имена полей",
+        )))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CustomError> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Доступ к заранее известному числу элементов или пар
+struct Counted<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Counted<'_, 'de> {
+    type Error = CustomError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, CustomError> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Counted<'_, 'de> {
+    type Error = CustomError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, CustomError> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, CustomError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = CustomError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), CustomError> {
+        let index = u32::from(self.array::<1>()?[0]);
+        let value = seed.deserialize(IntoDeserializer::<CustomError>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = CustomError;
+
+    fn unit_variant(self) -> Result<(), CustomError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, CustomError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_seq(Counted { de: self, len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CustomError> {
+        visitor.visit_seq(Counted {
+            de: self,
+            len: fields.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Channel {
+        Web,
+        Card(u16),
+        Bank { bic: String },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Payment {
+        id: u64,
+        fee: Option<i32>,
+        rate: f64,
+        approved: bool,
+        channel: Channel,
+        tags: Vec<String>,
+        extra: BTreeMap<String, String>,
+    }
+
+    #[test]
+    fn wire_round_trip_for_any_struct() -> Result<(), CustomError> {
+        let payment = Payment {
+            id: 7,
+            fee: Some(-15),
+            rate: 1.5,
+            approved: true,
+            channel: Channel::Bank {
+                bic: "044525225".to_string(),
+            },
+            tags: vec!["a".to_string(), "bc".to_string()],
+            extra: BTreeMap::from([("CURRENCY".to_string(), "RUB".to_string())]),
+        };
+
+        let bytes = to_bytes(&payment)?;
+        assert_eq!(&bytes[..8], &7u64.to_be_bytes());
+        assert_eq!(&bytes[8..13], &[1, 0xFF, 0xFF, 0xFF, 0xF1]);
+        assert_eq!(from_bytes::<Payment>(&bytes)?, payment);
+
+        let mut framed = Vec::new();
+        write_frame(&mut framed, b"TEST", &Channel::Card(3))?;
+        write_frame(&mut framed, b"TEST", &Channel::Web)?;
+        assert_eq!(
+            framed[..12],
+            [b'T', b'E', b'S', b'T', 0, 0, 0, 3, 1, 0, 3, b'T']
+        );
+
+        let mut reader = framed.as_slice();
+        assert_eq!(read_frame(&mut reader, b"TEST")?, Some(Channel::Card(3)));
+        assert_eq!(read_frame(&mut reader, b"TEST")?, Some(Channel::Web));
+        assert_eq!(read_frame::<_, Channel>(&mut reader, b"TEST")?, None);
+
+        // заголовок обещает 4 ГБ, а файл обрезан: ошибка без выделения памяти
+        let truncated = [b'T', b'E', b'S', b'T', 0xFF, 0xFF, 0xFF, 0xFF, 1, 0];
+        match read_frame_body(&mut truncated.as_slice(), b"TEST") {
+            Err(CustomError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+            other => panic!("Expected UnexpectedEof, got {:?}", other),
+        }
+
+        assert!(from_bytes::<Channel>(&[9]).is_err());
+        assert!(from_bytes::<u64>(&[0, 1]).is_err());
+
+        Ok(())
+    }
+}